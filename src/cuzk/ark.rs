@@ -0,0 +1,159 @@
+//! MSM over arkworks curves.
+//!
+//! The cuZK pipeline is specialised on the halo2curves types through
+//! [`GpuCurve`], so an arkworks curve is run on the GPU as its halo2curves
+//! twin. Both libraries store field elements in Montgomery form with radix
+//! `2^(64 * limbs)`, so coordinates and scalars are moved between them by
+//! copying their 64-bit limbs. The points are then uploaded by
+//! [`points_to_raw_bytes`](crate::points_to_raw_bytes) like those of any
+//! `GpuCurve::RAW_MONTGOMERY` curve, and only reach the 13-bit limb layout of
+//! `to_words_le_from_le_bytes` on the GPU, without a conversion to canonical
+//! form or an on-curve check on the CPU.
+
+use ark_ec::AffineRepr;
+use ark_ff::{BigInt, BigInteger, Field, PrimeField as ArkPrimeField};
+use group::prime::PrimeCurveAffine;
+use group::Curve;
+use halo2curves::{bn256, CurveAffine};
+
+use crate::cuzk::curve::GpuCurve;
+use crate::cuzk::error::MsmError;
+use crate::cuzk::msm::compute_msm;
+
+/// An arkworks affine point type with a halo2curves twin that the cuZK
+/// pipeline supports.
+///
+/// The twin must have the same base field, scalar field and curve equation,
+/// and its fields the same Montgomery radix as the arkworks fields.
+pub trait ArkCurve: AffineRepr {
+    /// The halo2curves curve with the same base field, scalar field and
+    /// curve equation
+    type Gpu: GpuCurve;
+
+    /// Build the twin of the affine point `(x, y)` from the Montgomery limbs
+    /// of its coordinates, without checking that it is on the curve
+    fn to_gpu_unchecked(x: &Self::BaseField, y: &Self::BaseField) -> Self::Gpu;
+
+    /// Build an affine point from the Montgomery limbs of the coordinates of
+    /// its twin, without checking that it is on the curve
+    fn from_gpu_unchecked(
+        x: &<Self::Gpu as CurveAffine>::Base,
+        y: &<Self::Gpu as CurveAffine>::Base,
+    ) -> Self;
+
+    /// Copy the Montgomery limbs of a scalar into the scalar field of the twin
+    fn scalar_to_gpu(scalar: &Self::ScalarField) -> <Self::Gpu as CurveAffine>::ScalarExt;
+}
+
+impl ArkCurve for ark_bn254::g1::G1Affine {
+    type Gpu = bn256::G1Affine;
+
+    fn to_gpu_unchecked(x: &ark_bn254::Fq, y: &ark_bn254::Fq) -> bn256::G1Affine {
+        bn256::G1Affine {
+            x: fq_to_gpu(x),
+            y: fq_to_gpu(y),
+        }
+    }
+
+    fn from_gpu_unchecked(x: &bn256::Fq, y: &bn256::Fq) -> Self {
+        Self::new_unchecked(fq_from_gpu(x), fq_from_gpu(y))
+    }
+
+    fn scalar_to_gpu(scalar: &ark_bn254::Fr) -> bn256::Fr {
+        bn256::Fr(scalar.0 .0)
+    }
+}
+
+impl ArkCurve for ark_bn254::g2::G2Affine {
+    type Gpu = bn256::G2Affine;
+
+    fn to_gpu_unchecked(x: &ark_bn254::Fq2, y: &ark_bn254::Fq2) -> bn256::G2Affine {
+        bn256::G2Affine {
+            x: fq2_to_gpu(x),
+            y: fq2_to_gpu(y),
+        }
+    }
+
+    fn from_gpu_unchecked(x: &bn256::Fq2, y: &bn256::Fq2) -> Self {
+        Self::new_unchecked(fq2_from_gpu(x), fq2_from_gpu(y))
+    }
+
+    fn scalar_to_gpu(scalar: &ark_bn254::Fr) -> bn256::Fr {
+        bn256::Fr(scalar.0 .0)
+    }
+}
+
+fn fq_to_gpu(value: &ark_bn254::Fq) -> bn256::Fq {
+    bn256::Fq(value.0 .0)
+}
+
+fn fq_from_gpu(value: &bn256::Fq) -> ark_bn254::Fq {
+    ark_bn254::Fq::new_unchecked(BigInt(value.0))
+}
+
+fn fq2_to_gpu(value: &ark_bn254::Fq2) -> bn256::Fq2 {
+    bn256::Fq2::new(fq_to_gpu(&value.c0), fq_to_gpu(&value.c1))
+}
+
+fn fq2_from_gpu(value: &bn256::Fq2) -> ark_bn254::Fq2 {
+    ark_bn254::Fq2::new(fq_from_gpu(value.c0()), fq_from_gpu(value.c1()))
+}
+
+/// Serialize an arkworks field element one base prime field component at a
+/// time, each as `component_bytes` little-endian bytes in canonical form.
+pub fn ark_field_to_bytes<F: Field>(value: &F, component_bytes: usize) -> Vec<u8> {
+    value
+        .to_base_prime_field_elements()
+        .flat_map(|component| {
+            let mut bytes = component.into_bigint().to_bytes_le();
+            bytes.resize(component_bytes, 0);
+            bytes
+        })
+        .collect()
+}
+
+/// Deserialize an arkworks field element written by [`ark_field_to_bytes`]
+pub fn ark_field_from_bytes<F: Field>(bytes: &[u8], component_bytes: usize) -> Result<F, MsmError> {
+    F::from_base_prime_field_elems(
+        bytes
+            .chunks(component_bytes)
+            .map(F::BasePrimeField::from_le_bytes_mod_order),
+    )
+    .ok_or_else(|| {
+        MsmError::InvalidInput(format!(
+            "{} bytes do not split into the components of the field",
+            bytes.len()
+        ))
+    })
+}
+
+/// Convert an arkworks point to its halo2curves twin. The point at infinity
+/// becomes the identity of the twin.
+pub fn ark_to_gpu_point<G: ArkCurve>(point: &G) -> G::Gpu {
+    point
+        .xy()
+        .map_or_else(G::Gpu::identity, |(x, y)| G::to_gpu_unchecked(&x, &y))
+}
+
+/// Convert a halo2curves result back to its arkworks twin
+pub fn gpu_to_ark_point<G: ArkCurve>(point: &<G::Gpu as CurveAffine>::CurveExt) -> G::Group {
+    let Some(coords): Option<halo2curves::Coordinates<G::Gpu>> =
+        point.to_affine().coordinates().into()
+    else {
+        return G::zero().into_group();
+    };
+    G::from_gpu_unchecked(coords.x(), coords.y()).into_group()
+}
+
+/// Compute an MSM over arkworks points and scalars on the GPU.
+///
+/// The result equals `ark_ec::VariableBaseMSM::msm(bases, scalars)`.
+pub async fn compute_msm_ark<G: ArkCurve>(
+    bases: &[G],
+    scalars: &[G::ScalarField],
+) -> Result<G::Group, MsmError> {
+    let gpu_points = bases.iter().map(ark_to_gpu_point).collect::<Vec<_>>();
+    let gpu_scalars = scalars.iter().map(G::scalar_to_gpu).collect::<Vec<_>>();
+    let result = compute_msm(&gpu_points, &gpu_scalars).await?;
+    Ok(gpu_to_ark_point::<G>(&result))
+}
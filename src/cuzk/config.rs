@@ -0,0 +1,351 @@
+use wgpu::Limits;
+
+use crate::cuzk::curve::{CurveModel, GpuCurve};
+use crate::cuzk::error::MsmError;
+use crate::cuzk::plan::{buffer_limit, CostModel, MsmPlan, MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+
+/// Maximum number of threads per subtask in the bucket points reduction (BPR)
+/// shader. Windows with fewer buckets use one thread per bucket.
+const BPR_WORKGROUP_SIZE: usize = 256;
+
+/// Workgroup size of the window sum shader, which runs one thread per subtask.
+const WINDOW_SUMS_WORKGROUP_SIZE: usize = 64;
+
+/// Workgroup size of the transpose shader, which runs one thread per scalar
+/// chunk and one workgroup per subtask for the prefix scan.
+const TRANSPOSE_WORKGROUP_SIZE: usize = 256;
+
+/// Workgroup size of the batch-affine SMVP shader. Every workgroup shares
+/// one field inversion per step, and keeps a field element per thread in
+/// workgroup memory.
+const BATCH_AFFINE_SMVP_WORKGROUP_SIZE: usize = 64;
+
+/// Overrides for the window size and the workgroup sizes and counts of the
+/// cuZK pipeline, for tuning on a specific GPU.
+///
+/// Every field left at `None` keeps the value picked from the input size, so
+/// `MsmConfig::default()` reproduces the behaviour of
+/// [`compute_msm`](crate::cuzk::msm::compute_msm). Overrides are validated
+/// against the shapes the shaders expect and the device limits when the
+/// pipelines are built.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MsmConfig {
+    /// Window size in bits, instead of the one picked by the cost model
+    pub chunk_size: Option<usize>,
+    /// Split every scalar into two halves with the GLV endomorphism of the
+    /// curve (see [`GpuCurve::glv`]). Curves without one return
+    /// [`MsmError::InvalidConfig`].
+    pub glv: bool,
+    /// Sum the buckets in affine coordinates, with one field inversion per
+    /// step shared across every SMVP workgroup by Montgomery's trick (see
+    /// [`ShaderManager::set_batch_affine`](crate::cuzk::shader_manager::ShaderManager::set_batch_affine)).
+    /// Only short Weierstrass curves over the base field support it. Others
+    /// return [`MsmError::InvalidConfig`].
+    pub batch_affine: bool,
+    /// Threads per workgroup of the decompose and point conversion shaders
+    pub decompose_workgroup_size: Option<usize>,
+    /// Number of x workgroups of the decompose and point conversion shaders.
+    /// The y workgroups cover the rest of the input.
+    pub decompose_num_x_workgroups: Option<usize>,
+    /// Threads per workgroup of the transpose shader
+    pub transpose_workgroup_size: Option<usize>,
+    /// Threads per workgroup of the SMVP shader
+    pub smvp_workgroup_size: Option<usize>,
+    /// Number of x workgroups of the SMVP shader. The y workgroups cover the
+    /// rest of the buckets of a subtask.
+    pub smvp_num_x_workgroups: Option<usize>,
+    /// Number of subtasks per SMVP dispatch
+    pub smvp_subtasks_per_dispatch: Option<usize>,
+    /// Threads per subtask of the BPR shader
+    pub bpr_workgroup_size: Option<usize>,
+    /// Number of subtasks per BPR dispatch
+    pub bpr_subtasks_per_dispatch: Option<usize>,
+    /// Threads per workgroup of the window sum shader
+    pub window_sums_workgroup_size: Option<usize>,
+}
+
+/// The workgroup sizes and counts of one pass of the pipeline, after applying
+/// the overrides of an [`MsmConfig`] to the defaults for its plan.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Workgroups {
+    /// Number of points and scalars after padding
+    pub input_size: usize,
+    /// Window size in bits
+    pub chunk_size: usize,
+    /// See [`MsmConfig::glv`]
+    pub glv: bool,
+    /// See [`MsmConfig::batch_affine`]
+    pub batch_affine: bool,
+    /// See [`MsmConfig::decompose_workgroup_size`]
+    pub decompose_workgroup_size: usize,
+    /// See [`MsmConfig::decompose_num_x_workgroups`]
+    pub decompose_num_x_workgroups: usize,
+    /// Number of y workgroups of the decompose and point conversion shaders
+    pub decompose_num_y_workgroups: usize,
+    /// See [`MsmConfig::transpose_workgroup_size`]
+    pub transpose_workgroup_size: usize,
+    /// Number of x workgroups of the transpose histogram and scatter passes
+    pub transpose_num_x_workgroups: usize,
+    /// See [`MsmConfig::smvp_workgroup_size`]
+    pub smvp_workgroup_size: usize,
+    /// See [`MsmConfig::smvp_num_x_workgroups`]
+    pub smvp_num_x_workgroups: usize,
+    /// Number of y workgroups of the SMVP shader
+    pub smvp_num_y_workgroups: usize,
+    /// See [`MsmConfig::smvp_subtasks_per_dispatch`]
+    pub smvp_subtasks_per_dispatch: usize,
+    /// See [`MsmConfig::bpr_workgroup_size`]
+    pub bpr_workgroup_size: usize,
+    /// See [`MsmConfig::bpr_subtasks_per_dispatch`]
+    pub bpr_subtasks_per_dispatch: usize,
+    /// See [`MsmConfig::window_sums_workgroup_size`]
+    pub window_sums_workgroup_size: usize,
+}
+
+impl MsmConfig {
+    /// Plan a pass over `input_size` inputs of curve `C`, with the window size
+    /// override if there is one and the one picked by `cost_model` otherwise.
+    /// With [`MsmConfig::glv`], the plan splits the scalars in two.
+    pub fn plan<C: GpuCurve>(
+        &self,
+        input_size: usize,
+        limits: &Limits,
+        cost_model: &CostModel,
+    ) -> Result<MsmPlan, MsmError> {
+        check(
+            !self.batch_affine || (C::MODEL == CurveModel::ShortWeierstrass && C::EXT_DEGREE == 1),
+            "batch-affine bucket sums need a short Weierstrass curve over the base field"
+                .to_string(),
+        )?;
+        let Some(chunk_size) = self.chunk_size else {
+            return if self.glv {
+                MsmPlan::select_glv::<C>(input_size, limits, cost_model)
+            } else {
+                MsmPlan::select::<C>(input_size, limits, cost_model)
+            };
+        };
+        check(
+            (MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk_size),
+            format!(
+                "the window size {chunk_size} is outside \
+                 {MIN_CHUNK_SIZE}..={MAX_CHUNK_SIZE}"
+            ),
+        )?;
+        let plan = if self.glv {
+            MsmPlan::new_glv::<C>(input_size, chunk_size).ok_or_else(|| {
+                MsmError::InvalidConfig("the curve has no GLV endomorphism".to_string())
+            })?
+        } else {
+            MsmPlan::new::<C>(input_size, chunk_size)
+        };
+        if !plan.fits(limits) {
+            return Err(MsmError::LimitsExceeded {
+                resource: format!("MSM of {input_size} inputs with {chunk_size}-bit windows"),
+                requested: plan.max_buffer_size,
+                limit: buffer_limit(limits),
+            });
+        }
+        Ok(plan)
+    }
+
+    /// Resolve the workgroup sizes and counts of a pass over `plan`, and check
+    /// them against the shaders and `limits`.
+    pub fn workgroups(&self, plan: &MsmPlan, limits: &Limits) -> Result<Workgroups, MsmError> {
+        let input_size = plan.input_size;
+        let num_buckets = plan.num_buckets();
+        let num_subtasks = plan.num_subtasks;
+
+        // Total thread count = workgroup_size * #x workgroups * #y workgroups.
+        let (c_workgroup_size, c_num_x_workgroups) = match input_size {
+            0..=256 => (input_size, 1),
+            257..=32768 => (64, 4),
+            32769..=131072 => (256, 8),
+            131073..=1048576 => (256, 32),
+            // Only reached on devices whose binding size allows partitions
+            // above 2^20.
+            _ => (256, 64),
+        };
+        let decompose_workgroup_size = self.decompose_workgroup_size.unwrap_or(c_workgroup_size);
+        let decompose_num_x_workgroups = self
+            .decompose_num_x_workgroups
+            .unwrap_or(c_num_x_workgroups);
+        let decompose_threads = decompose_workgroup_size * decompose_num_x_workgroups;
+        check(
+            decompose_threads > 0 && input_size.is_multiple_of(decompose_threads),
+            format!(
+                "{decompose_workgroup_size} threads times {decompose_num_x_workgroups} workgroups \
+                 do not divide the input size {input_size}"
+            ),
+        )?;
+
+        let transpose_workgroup_size = self
+            .transpose_workgroup_size
+            .unwrap_or(TRANSPOSE_WORKGROUP_SIZE);
+        check(
+            transpose_workgroup_size > 0,
+            "the transpose workgroup size must be positive".to_string(),
+        )?;
+
+        // Every SMVP thread handles one positive and one negative bucket.
+        let (s_workgroup_size, s_num_x_workgroups) = if self.batch_affine {
+            (BATCH_AFFINE_SMVP_WORKGROUP_SIZE.min(num_buckets), 1)
+        } else if plan.num_columns() < 256 {
+            (1, num_buckets)
+        } else if num_buckets < 32768 {
+            (32, 1)
+        } else {
+            (256, 64)
+        };
+        let smvp_workgroup_size = self.smvp_workgroup_size.unwrap_or(s_workgroup_size);
+        let smvp_num_x_workgroups = self.smvp_num_x_workgroups.unwrap_or(s_num_x_workgroups);
+        let smvp_threads = smvp_workgroup_size * smvp_num_x_workgroups;
+        check(
+            smvp_threads > 0 && num_buckets.is_multiple_of(smvp_threads),
+            format!(
+                "{smvp_workgroup_size} SMVP threads times {smvp_num_x_workgroups} workgroups \
+                 do not divide the {num_buckets} buckets of a subtask"
+            ),
+        )?;
+
+        let smvp_subtasks_per_dispatch = self
+            .smvp_subtasks_per_dispatch
+            .unwrap_or(if num_subtasks.is_multiple_of(4) { 4 } else { 1 });
+        check_divides(
+            "SMVP subtasks per dispatch",
+            smvp_subtasks_per_dispatch,
+            num_subtasks,
+        )?;
+
+        let bpr_workgroup_size = self
+            .bpr_workgroup_size
+            .unwrap_or(BPR_WORKGROUP_SIZE.min(num_buckets));
+        check(
+            bpr_workgroup_size > 0 && num_buckets.is_multiple_of(bpr_workgroup_size),
+            format!(
+                "{bpr_workgroup_size} BPR threads do not divide the {num_buckets} buckets of a \
+                 subtask"
+            ),
+        )?;
+
+        let bpr_subtasks_per_dispatch = self.bpr_subtasks_per_dispatch.unwrap_or_else(|| {
+            [16, 8, 4, 2, 1]
+                .into_iter()
+                .find(|n| num_subtasks.is_multiple_of(*n))
+                .unwrap()
+        });
+        check_divides(
+            "BPR subtasks per dispatch",
+            bpr_subtasks_per_dispatch,
+            num_subtasks,
+        )?;
+
+        let window_sums_workgroup_size = self
+            .window_sums_workgroup_size
+            .unwrap_or(WINDOW_SUMS_WORKGROUP_SIZE);
+        check(
+            window_sums_workgroup_size > 0,
+            "the window sum workgroup size must be positive".to_string(),
+        )?;
+
+        let workgroups = Workgroups {
+            input_size,
+            chunk_size: plan.chunk_size,
+            glv: plan.glv,
+            batch_affine: self.batch_affine,
+            decompose_workgroup_size,
+            decompose_num_x_workgroups,
+            decompose_num_y_workgroups: input_size / decompose_threads,
+            transpose_workgroup_size,
+            transpose_num_x_workgroups: plan.num_terms().div_ceil(transpose_workgroup_size),
+            smvp_workgroup_size,
+            smvp_num_x_workgroups,
+            smvp_num_y_workgroups: num_buckets / smvp_threads,
+            smvp_subtasks_per_dispatch,
+            bpr_workgroup_size,
+            bpr_subtasks_per_dispatch,
+            window_sums_workgroup_size,
+        };
+        workgroups.check_limits(limits, num_subtasks)?;
+        Ok(workgroups)
+    }
+}
+
+impl Workgroups {
+    /// Check every workgroup size and count against `limits`.
+    fn check_limits(&self, limits: &Limits, num_subtasks: usize) -> Result<(), MsmError> {
+        let max_size = limits
+            .max_compute_workgroup_size_x
+            .min(limits.max_compute_invocations_per_workgroup) as usize;
+        for (name, size) in [
+            ("Decompose workgroup size", self.decompose_workgroup_size),
+            ("Transpose workgroup size", self.transpose_workgroup_size),
+            ("SMVP workgroup size", self.smvp_workgroup_size),
+            ("BPR workgroup size", self.bpr_workgroup_size),
+            ("Window sum workgroup size", self.window_sums_workgroup_size),
+        ] {
+            check_limit(name, size, max_size)?;
+        }
+
+        let max_count = limits.max_compute_workgroups_per_dimension as usize;
+        for (name, count, limit) in [
+            (
+                "Decompose x workgroups",
+                self.decompose_num_x_workgroups,
+                max_count,
+            ),
+            (
+                "Decompose y workgroups",
+                self.decompose_num_y_workgroups,
+                max_count,
+            ),
+            (
+                "Transpose x workgroups",
+                self.transpose_num_x_workgroups,
+                max_count,
+            ),
+            ("Transpose z workgroups", num_subtasks, max_count),
+            ("SMVP x workgroups", self.smvp_num_x_workgroups, max_count),
+            ("SMVP y workgroups", self.smvp_num_y_workgroups, max_count),
+            (
+                "SMVP z workgroups",
+                self.smvp_subtasks_per_dispatch,
+                max_count,
+            ),
+            ("BPR workgroups", self.bpr_subtasks_per_dispatch, max_count),
+            (
+                "Window sum workgroups",
+                num_subtasks.div_ceil(self.window_sums_workgroup_size),
+                max_count,
+            ),
+        ] {
+            check_limit(name, count, limit)?;
+        }
+        Ok(())
+    }
+}
+
+fn check(condition: bool, message: String) -> Result<(), MsmError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(MsmError::InvalidConfig(message))
+    }
+}
+
+fn check_divides(name: &str, value: usize, num_subtasks: usize) -> Result<(), MsmError> {
+    check(
+        value > 0 && num_subtasks.is_multiple_of(value),
+        format!("{name} ({value}) must divide the number of subtasks ({num_subtasks})"),
+    )
+}
+
+fn check_limit(name: &str, value: usize, limit: usize) -> Result<(), MsmError> {
+    if value > limit {
+        return Err(MsmError::LimitsExceeded {
+            resource: name.to_string(),
+            requested: value as u64,
+            limit: limit as u64,
+        });
+    }
+    Ok(())
+}
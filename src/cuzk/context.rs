@@ -0,0 +1,1534 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use group::Group;
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, BufferBinding, BufferSize, CommandEncoder,
+    CommandEncoderDescriptor, ComputePipeline, Device, Queue,
+};
+
+use crate::cuzk::config::{MsmConfig, Workgroups};
+use crate::cuzk::curve::GpuCurve;
+use crate::cuzk::error::MsmError;
+use crate::cuzk::gpu::{
+    check_storage_buffer_size, create_and_write_uniform_buffer, create_bind_group,
+    create_bind_group_layout, create_bind_group_with_bindings, create_compute_pipeline,
+    create_storage_buffer, execute_pipeline, get_adapter, get_device, read_from_gpu,
+};
+use crate::cuzk::msm::{
+    identity_indices, pad_points, pad_scalars, to_u8s_for_gpu, zero_identity_scalars, WORD_SIZE,
+};
+use crate::cuzk::partition::{max_glv_partition_size, max_partition_size, partition};
+use crate::cuzk::plan::{CostModel, MsmPlan};
+use crate::cuzk::shader_manager::ShaderManager;
+use crate::cuzk::utils::{from_words_le_without_assertion, to_biguint_le};
+use crate::cuzk::verify::check_window_sum;
+use crate::{points_to_raw_bytes, scalars_to_bytes};
+
+/// A reusable MSM context.
+///
+/// The context owns the wgpu device and queue, and lazily builds the shaders,
+/// compute pipelines and storage buffers for every curve and set of
+/// [`Workgroups`] it is asked to run. Subsequent MSMs of the same curve and
+/// padded size only upload the new points and scalars and record the command
+/// buffer.
+pub struct MsmContext {
+    device: Device,
+    queue: Queue,
+    pipelines: HashMap<(TypeId, Workgroups), MsmPipelines>,
+    /// Set by the device lost callback
+    lost: Arc<Mutex<Option<String>>>,
+    /// Spot check one window of every MSM on the CPU
+    verify: bool,
+    /// Picks the window checked by the self-check
+    rng: StdRng,
+    /// Picks the window size of every pass
+    cost_model: CostModel,
+    /// Overrides of the window size and workgroup shapes
+    config: MsmConfig,
+}
+
+/// A set of affine points converted to Montgomery form and resident on the GPU.
+///
+/// Created by [`MsmContext::upload_bases`] and only valid for the context that
+/// created it.
+pub struct GpuBases<C: GpuCurve> {
+    point_x_sb: Buffer,
+    point_y_sb: Buffer,
+    /// The padded points, kept for the self-check
+    points: Vec<C>,
+    num_points: usize,
+    input_size: usize,
+    /// Indices of the points at infinity, uploaded as the generator
+    identities: Vec<usize>,
+    /// Whether the endomorphism images of the points follow them
+    glv: bool,
+}
+
+impl<C: GpuCurve> GpuBases<C> {
+    /// Number of points in the base set, before padding
+    pub fn len(&self) -> usize {
+        self.num_points
+    }
+
+    /// Whether the base set is empty
+    pub fn is_empty(&self) -> bool {
+        self.num_points == 0
+    }
+}
+
+impl MsmContext {
+    /// Request a GPU adapter and device and create an empty context
+    pub async fn new() -> Result<Self, MsmError> {
+        let adapter = get_adapter().await?;
+        let (device, queue) = get_device(&adapter).await?;
+        Ok(Self::from_device(device, queue))
+    }
+
+    /// Create a context from an existing device and queue
+    pub fn from_device(device: Device, queue: Queue) -> Self {
+        let lost = Arc::new(Mutex::new(None));
+        let lost_callback = lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            *lost_callback.lock().unwrap() = Some(format!("{reason:?}: {message}"));
+        });
+        Self {
+            device,
+            queue,
+            pipelines: HashMap::new(),
+            lost,
+            verify: false,
+            rng: StdRng::from_entropy(),
+            cost_model: CostModel::default(),
+            config: MsmConfig::default(),
+        }
+    }
+
+    /// The wgpu device used by this context
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// The wgpu queue used by this context
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    /// Enable or disable the self-check of [`MsmContext::compute_msm`].
+    ///
+    /// When enabled, one randomly chosen window of every MSM (or of every
+    /// partition) is recomputed on the CPU and compared against the window sum
+    /// read back from the GPU. A mismatch returns
+    /// [`MsmError::VerificationFailed`] instead of the result. The check costs
+    /// roughly one window's worth of point additions on the CPU. It applies
+    /// to [`MsmContext::compute_msm`], [`MsmContext::compute_msm_fixed_base`]
+    /// and [`MsmContext::compute_msm_batch`], which checks one window of
+    /// every instance.
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// Whether the self-check is enabled
+    pub fn verify(&self) -> bool {
+        self.verify
+    }
+
+    /// Seed the RNG used by the self-check, so that the windows it checks can
+    /// be replayed. The RNG is seeded from entropy by default.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Set the cost model used to pick the window size of every pass. Passes
+    /// whose window size changes build new pipelines on first use.
+    pub fn set_cost_model(&mut self, cost_model: CostModel) {
+        self.cost_model = cost_model;
+    }
+
+    /// The cost model used to pick the window size of every pass
+    pub fn cost_model(&self) -> &CostModel {
+        &self.cost_model
+    }
+
+    /// Override the window size and workgroup shapes of every pass. Overrides
+    /// are validated when a pass first runs with them, and passes whose
+    /// workgroups change build new pipelines on first use.
+    pub fn set_config(&mut self, config: MsmConfig) {
+        self.config = config;
+    }
+
+    /// The overrides of the window size and workgroup shapes
+    pub fn config(&self) -> &MsmConfig {
+        &self.config
+    }
+
+    /// The plan of the pass that runs the first partition of an MSM of
+    /// `num_points` points of curve `C`. Every partition but the last has the
+    /// same plan.
+    pub fn plan<C: GpuCurve>(&self, num_points: usize) -> Result<MsmPlan, MsmError> {
+        let input_size = num_points
+            .min(self.max_partition_size::<C>())
+            .next_power_of_two();
+        self.plan_for_input_size::<C>(input_size)
+    }
+
+    /// The plan of a pass over `input_size` padded inputs
+    fn plan_for_input_size<C: GpuCurve>(&self, input_size: usize) -> Result<MsmPlan, MsmError> {
+        self.config
+            .plan::<C>(input_size, &self.device.limits(), &self.cost_model)
+    }
+
+    /// Whether the device has been lost. A lost context cannot be used again.
+    pub fn is_lost(&self) -> bool {
+        self.lost.lock().unwrap().is_some()
+    }
+
+    /// Return [`MsmError::DeviceLost`] if the device has been lost.
+    fn check_device(&self) -> Result<(), MsmError> {
+        match self.lost.lock().unwrap().as_ref() {
+            Some(message) => Err(MsmError::DeviceLost(message.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Number of `(curve, workgroups)` configurations currently cached
+    pub fn num_cached_pipelines(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    /// Drop every cached pipeline and the storage buffers they own
+    pub fn clear_cache(&mut self) {
+        self.pipelines.clear();
+    }
+
+    /// The cache key of the pipelines for curve `C` that run `plan` with the
+    /// workgroups of the current config
+    fn pipelines_key<C: GpuCurve>(&self, plan: &MsmPlan) -> Result<(TypeId, Workgroups), MsmError> {
+        let workgroups = self.config.workgroups(plan, &self.device.limits())?;
+        Ok((TypeId::of::<C>(), workgroups))
+    }
+
+    /// Build the pipelines for curve `C` that run `plan` unless they are
+    /// cached, and return their cache key.
+    async fn pipelines<C: GpuCurve>(
+        &mut self,
+        plan: &MsmPlan,
+    ) -> Result<(TypeId, Workgroups), MsmError> {
+        let key = self.pipelines_key::<C>(plan)?;
+        if !self.pipelines.contains_key(&key) {
+            let pipelines = MsmPipelines::new::<C>(&self.device, &self.queue, plan, &key.1).await?;
+            self.pipelines.insert(key, pipelines);
+        }
+        Ok(key)
+    }
+
+    /// Largest number of points of curve `C` a single pass of the pipeline
+    /// can take on this device, with the window size of the config if it
+    /// overrides one. Larger MSMs are split into partitions of this size.
+    pub fn max_partition_size<C: GpuCurve>(&self) -> usize {
+        let limits = self.device.limits();
+        let chunk_size = self.config.chunk_size;
+        let glv_size = self
+            .config
+            .glv
+            .then(|| max_glv_partition_size::<C>(&limits, chunk_size))
+            .flatten();
+        glv_size.unwrap_or_else(|| max_partition_size::<C>(&limits, chunk_size))
+    }
+
+    /*
+     * End-to-end implementation of the modified cuZK MSM algorithm by Lu et al,
+     * 2022: https://eprint.iacr.org/2022/1321.pdf
+     *
+     * Inputs larger than `max_partition_size` are split into partitions that
+     * each run through the whole pipeline, and the partial results are summed.
+     */
+    pub async fn compute_msm<C: GpuCurve>(
+        &mut self,
+        points: &[C],
+        scalars: &[C::Scalar],
+    ) -> Result<C::Curve, MsmError> {
+        validate_input(points.len(), scalars.len())?;
+        self.check_device()?;
+        let mut result = C::Curve::identity();
+        for range in partition(points.len(), self.max_partition_size::<C>()) {
+            result += self
+                .compute_msm_partition(&points[range.clone()], &scalars[range])
+                .await?;
+        }
+        Ok(result)
+    }
+
+    /// Run a single pass of the pipeline over at most `max_partition_size` inputs.
+    async fn compute_msm_partition<C: GpuCurve>(
+        &mut self,
+        points: &[C],
+        scalars: &[C::Scalar],
+    ) -> Result<C::Curve, MsmError> {
+        let mut padded_scalars = pad_scalars::<C>(scalars);
+        zero_identity_scalars::<C>(&mut padded_scalars, &identity_indices(points));
+        let padded_points = pad_points::<C>(points);
+        let input_size = padded_scalars.len();
+        let plan = self.plan_for_input_size::<C>(input_size)?;
+
+        let point_bytes = points_to_raw_bytes(&padded_points);
+        let scalar_bytes = scalars_to_bytes(&padded_scalars);
+
+        let key = self.pipelines::<C>(&plan).await?;
+        let pipelines = &self.pipelines[&key];
+
+        self.queue
+            .write_buffer(&pipelines.points_sb, 0, &point_bytes);
+        self.queue
+            .write_buffer(&pipelines.scalars_sb, 0, &scalar_bytes);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("MSM Encoder"),
+            });
+        pipelines.decompose.encode(&mut encoder).await;
+        pipelines
+            .encode_buckets(&mut encoder, &pipelines.bucket_bind_groups)
+            .await;
+
+        let data = pipelines
+            .read_result(&self.device, &self.queue, encoder, self.verify)
+            .await?;
+        self.check_device()?;
+
+        if self.verify {
+            let window_sums = pipelines.decode_windows::<C>(&data[1])?;
+            let window = self.rng.gen_range(0..pipelines.num_subtasks);
+            check_window_sum(&plan, &padded_points, &padded_scalars, &window_sums, window)?;
+        }
+
+        pipelines.decode_result::<C>(&data[0])
+    }
+
+    /// Upload a fixed set of bases and convert them to Montgomery form once.
+    ///
+    /// The returned handle can be passed to [`MsmContext::compute_msm_fixed_base`]
+    /// any number of times, after which only the scalars are sent to the GPU.
+    pub async fn upload_bases<C: GpuCurve>(
+        &mut self,
+        points: &[C],
+    ) -> Result<GpuBases<C>, MsmError> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Upload bases encoder"),
+            });
+        let bases = self.encode_upload_bases(&mut encoder, points).await?;
+        self.queue.submit(Some(encoder.finish()));
+        Ok(bases)
+    }
+
+    /// Write `points` to the GPU and record their conversion to Montgomery
+    /// form into `encoder`. The returned handle is only usable once
+    /// `encoder` has been submitted.
+    async fn encode_upload_bases<C: GpuCurve>(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        points: &[C],
+    ) -> Result<GpuBases<C>, MsmError> {
+        validate_input(points.len(), points.len())?;
+        self.check_device()?;
+        let max_partition_size = self.max_partition_size::<C>();
+        if points.len() > max_partition_size {
+            return Err(MsmError::LimitsExceeded {
+                resource: "Fixed base point set".to_string(),
+                requested: points.len() as u64,
+                limit: max_partition_size as u64,
+            });
+        }
+        let padded_points = pad_points::<C>(points);
+        let input_size = padded_points.len();
+        let plan = self.plan_for_input_size::<C>(input_size)?;
+        let point_bytes = points_to_raw_bytes(&padded_points);
+
+        let key = self.pipelines::<C>(&plan).await?;
+        let pipelines = &self.pipelines[&key];
+        let coord_words = C::coord_words();
+
+        let point_x_sb = create_storage_buffer(
+            Some("Fixed base point X buffer"),
+            &self.device,
+            (plan.num_terms() * coord_words * 4) as u64,
+        );
+        let point_y_sb = create_storage_buffer(
+            Some("Fixed base point Y buffer"),
+            &self.device,
+            (plan.num_terms() * coord_words * 4) as u64,
+        );
+
+        self.queue
+            .write_buffer(&pipelines.points_sb, 0, &point_bytes);
+        let bind_group = create_bind_group(
+            Some("Convert point coords bind group"),
+            &self.device,
+            &pipelines.convert_point_coords.bind_group_layout,
+            vec![
+                &pipelines.points_sb,
+                &point_x_sb,
+                &point_y_sb,
+                &pipelines.input_size_ub,
+            ],
+        );
+        pipelines
+            .convert_point_coords
+            .encode_with(encoder, &[bind_group])
+            .await;
+
+        Ok(GpuBases {
+            point_x_sb,
+            point_y_sb,
+            points: padded_points,
+            num_points: points.len(),
+            input_size,
+            identities: identity_indices(points),
+            glv: plan.glv,
+        })
+    }
+
+    /// Compute an MSM over bases previously uploaded with
+    /// [`MsmContext::upload_bases`]. Only the scalars are sent to the GPU.
+    pub async fn compute_msm_fixed_base<C: GpuCurve>(
+        &mut self,
+        bases: &GpuBases<C>,
+        scalars: &[C::Scalar],
+    ) -> Result<C::Curve, MsmError> {
+        validate_input(bases.num_points, scalars.len())?;
+        self.check_device()?;
+        let mut padded_scalars = pad_scalars::<C>(scalars);
+        zero_identity_scalars::<C>(&mut padded_scalars, &bases.identities);
+        let input_size = bases.input_size;
+        let plan = self.plan_for_input_size::<C>(input_size)?;
+        check_glv(&plan, bases)?;
+
+        let scalar_bytes = scalars_to_bytes(&padded_scalars);
+
+        let key = self.pipelines::<C>(&plan).await?;
+        let pipelines = &self.pipelines[&key];
+
+        self.queue
+            .write_buffer(&pipelines.scalars_sb, 0, &scalar_bytes);
+        let bucket_bind_groups = pipelines.bucket_bind_groups(
+            &self.device,
+            &bases.point_x_sb,
+            &bases.point_y_sb,
+            &pipelines.bucket_buffers(),
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Fixed base MSM Encoder"),
+            });
+        pipelines.decompose_scalars_only.encode(&mut encoder).await;
+        pipelines
+            .encode_buckets(&mut encoder, &bucket_bind_groups)
+            .await;
+
+        let data = pipelines
+            .read_result(&self.device, &self.queue, encoder, self.verify)
+            .await?;
+        self.check_device()?;
+
+        if self.verify {
+            let window_sums = pipelines.decode_windows::<C>(&data[1])?;
+            let window = self.rng.gen_range(0..pipelines.num_subtasks);
+            check_window_sum(&plan, &bases.points, &padded_scalars, &window_sums, window)?;
+        }
+
+        pipelines.decode_result::<C>(&data[0])
+    }
+
+    /// Compute one MSM per scalar vector in `scalars`, all over the same
+    /// `points`, with a single submission and a single readback.
+    ///
+    /// The points are converted to Montgomery form once. Every instance is
+    /// recorded into the same command encoder and writes its own range of
+    /// bucket sum, g point, window sum and result buffers sized for the whole
+    /// batch. If the batch does not fit in the device's largest storage
+    /// buffer, the instances share as many ranges as fit in turn.
+    ///
+    /// Point sets larger than `max_partition_size` are split into partitions
+    /// that each run as one batch, and the partial results of every instance
+    /// are summed.
+    pub async fn compute_msm_batch<C: GpuCurve>(
+        &mut self,
+        points: &[C],
+        scalars: &[Vec<C::Scalar>],
+    ) -> Result<Vec<C::Curve>, MsmError> {
+        if scalars.is_empty() {
+            return Ok(vec![]);
+        }
+        for instance in scalars {
+            validate_input(points.len(), instance.len())?;
+        }
+        let mut results = vec![C::Curve::identity(); scalars.len()];
+        for range in partition(points.len(), self.max_partition_size::<C>()) {
+            let instances = scalars
+                .iter()
+                .map(|instance| &instance[range.clone()])
+                .collect::<Vec<_>>();
+            let partial_results = self
+                .compute_msm_batch_partition(&points[range], &instances)
+                .await?;
+            for (result, partial_result) in results.iter_mut().zip(partial_results) {
+                *result += partial_result;
+            }
+        }
+        Ok(results)
+    }
+
+    /// Run a batch over a single partition of at most `max_partition_size`
+    /// points.
+    async fn compute_msm_batch_partition<C: GpuCurve>(
+        &mut self,
+        points: &[C],
+        scalars: &[&[C::Scalar]],
+    ) -> Result<Vec<C::Curve>, MsmError> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Batch MSM Encoder"),
+            });
+        let bases = self.encode_upload_bases(&mut encoder, points).await?;
+        let input_size = bases.input_size;
+        let plan = self.plan_for_input_size::<C>(input_size)?;
+        let batch_size = scalars.len();
+        let pipelines = &self.pipelines[&self.pipelines_key::<C>(&plan)?];
+
+        let padded_scalars = scalars
+            .iter()
+            .map(|instance| {
+                let mut padded_scalars = pad_scalars::<C>(instance);
+                zero_identity_scalars::<C>(&mut padded_scalars, &bases.identities);
+                padded_scalars
+            })
+            .collect::<Vec<_>>();
+        let scalar_bytes = padded_scalars
+            .iter()
+            .flat_map(|instance| scalars_to_bytes(instance))
+            .collect::<Vec<_>>();
+        let scalars_bytelength = pipelines.scalars_sb.size();
+        check_storage_buffer_size(
+            "Batch scalars buffer",
+            &self.device,
+            scalars_bytelength * batch_size as u64,
+        )?;
+        let all_scalars_sb = create_storage_buffer(
+            Some("Batch scalars buffer"),
+            &self.device,
+            scalars_bytelength * batch_size as u64,
+        );
+        self.queue.write_buffer(&all_scalars_sb, 0, &scalar_bytes);
+
+        // Every instance binds its ranges at offsets aligned for storage
+        // buffers.
+        let limits = self.device.limits();
+        let alignment = limits.min_storage_buffer_offset_alignment as u64;
+        let max_size = limits
+            .max_buffer_size
+            .min(limits.max_storage_buffer_binding_size as u64);
+        let bucket_sum_stride = pipelines.bucket_sum_sbs[0]
+            .size()
+            .next_multiple_of(alignment);
+        let g_points_stride = pipelines.g_points_sbs[0].size().next_multiple_of(alignment);
+        let windows_stride = pipelines.windows_sb.size().next_multiple_of(alignment);
+        let result_stride = pipelines.result_sb.size().next_multiple_of(alignment);
+        let largest_stride = bucket_sum_stride.max(g_points_stride).max(windows_stride);
+        let num_slots = ((max_size / largest_stride) as usize).clamp(1, batch_size);
+        check_storage_buffer_size(
+            "Batch result buffer",
+            &self.device,
+            result_stride * batch_size as u64,
+        )?;
+
+        let batch_buffers = |label: &str, stride: u64| {
+            C::MODEL
+                .coord_names()
+                .iter()
+                .map(|coord| {
+                    create_storage_buffer(
+                        Some(&format!("Batch {label} {coord} buffer")),
+                        &self.device,
+                        stride * num_slots as u64,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let all_bucket_sum_sbs = batch_buffers("bucket sum", bucket_sum_stride);
+        let all_g_points_sbs = batch_buffers("bucket points reduction", g_points_stride);
+        let all_windows_sb = create_storage_buffer(
+            Some("Batch window sums buffer"),
+            &self.device,
+            windows_stride * num_slots as u64,
+        );
+        let all_results_sb = create_storage_buffer(
+            Some("Batch result buffer"),
+            &self.device,
+            result_stride * batch_size as u64,
+        );
+        // Instances that share a slot overwrite each other's window sums, so
+        // the self-check copies them out after every instance.
+        let windows_size = pipelines.windows_sb.size();
+        let verify_windows_sb = if self.verify {
+            check_storage_buffer_size(
+                "Batch window sums readback buffer",
+                &self.device,
+                windows_size * batch_size as u64,
+            )?;
+            Some(create_storage_buffer(
+                Some("Batch window sums readback buffer"),
+                &self.device,
+                windows_size * batch_size as u64,
+            ))
+        } else {
+            None
+        };
+
+        let range = |buffer, offset, size| BufferBinding {
+            buffer,
+            offset,
+            size: BufferSize::new(size),
+        };
+        for i in 0..batch_size {
+            let slot = (i % num_slots) as u64;
+            let buffers = BucketBuffers {
+                bucket_sums: all_bucket_sum_sbs
+                    .iter()
+                    .map(|sb| {
+                        range(
+                            sb,
+                            slot * bucket_sum_stride,
+                            pipelines.bucket_sum_sbs[0].size(),
+                        )
+                    })
+                    .collect(),
+                g_points: all_g_points_sbs
+                    .iter()
+                    .map(|sb| range(sb, slot * g_points_stride, pipelines.g_points_sbs[0].size()))
+                    .collect(),
+                windows: range(
+                    &all_windows_sb,
+                    slot * windows_stride,
+                    pipelines.windows_sb.size(),
+                ),
+                result: range(
+                    &all_results_sb,
+                    i as u64 * result_stride,
+                    pipelines.result_sb.size(),
+                ),
+            };
+            let bucket_bind_groups = pipelines.bucket_bind_groups(
+                &self.device,
+                &bases.point_x_sb,
+                &bases.point_y_sb,
+                &buffers,
+            );
+
+            encoder.copy_buffer_to_buffer(
+                &all_scalars_sb,
+                i as u64 * scalars_bytelength,
+                &pipelines.scalars_sb,
+                0,
+                scalars_bytelength,
+            );
+            pipelines.decompose_scalars_only.encode(&mut encoder).await;
+            pipelines
+                .encode_buckets(&mut encoder, &bucket_bind_groups)
+                .await;
+            if let Some(verify_windows_sb) = &verify_windows_sb {
+                encoder.copy_buffer_to_buffer(
+                    &all_windows_sb,
+                    slot * windows_stride,
+                    verify_windows_sb,
+                    i as u64 * windows_size,
+                    windows_size,
+                );
+            }
+        }
+
+        let mut buffers = vec![all_results_sb];
+        buffers.extend(verify_windows_sb);
+        let data = read_from_gpu(&self.device, &self.queue, encoder, buffers).await?;
+        self.check_device()?;
+
+        if self.verify {
+            for (windows, instance) in data[1].chunks(windows_size as usize).zip(&padded_scalars) {
+                let window_sums = pipelines.decode_windows::<C>(windows)?;
+                let window = self.rng.gen_range(0..pipelines.num_subtasks);
+                check_window_sum(&plan, &bases.points, instance, &window_sums, window)?;
+            }
+        }
+
+        data[0]
+            .chunks(result_stride as usize)
+            .map(|result| {
+                pipelines.decode_result::<C>(&result[..pipelines.result_sb.size() as usize])
+            })
+            .collect()
+    }
+}
+
+impl Drop for MsmContext {
+    fn drop(&mut self) {
+        // Destroy the GPU device object.
+        self.device.destroy();
+    }
+}
+
+/// Check that `bases` were uploaded with the same GLV setting as `plan`, since
+/// the endomorphism images of the points are only resident if they were.
+fn check_glv<C: GpuCurve>(plan: &MsmPlan, bases: &GpuBases<C>) -> Result<(), MsmError> {
+    if plan.glv != bases.glv {
+        return Err(MsmError::InvalidInput(
+            "bases were uploaded with a different GLV setting".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Check that `num_points` points and `num_scalars` scalars form a valid MSM instance.
+fn validate_input(num_points: usize, num_scalars: usize) -> Result<(), MsmError> {
+    if num_points != num_scalars {
+        return Err(MsmError::InvalidInput(format!(
+            "{num_points} points but {num_scalars} scalars"
+        )));
+    }
+    if num_points == 0 {
+        return Err(MsmError::InvalidInput("empty MSM".to_string()));
+    }
+    Ok(())
+}
+
+/// A compiled compute pipeline together with the bind groups it is
+/// dispatched with and its workgroup counts.
+struct Stage {
+    pipeline: ComputePipeline,
+    bind_group_layout: BindGroupLayout,
+    bind_groups: Vec<BindGroup>,
+    num_workgroups: (usize, usize, usize),
+}
+
+impl Stage {
+    async fn new(
+        label: &str,
+        device: &Device,
+        bind_group_layout: BindGroupLayout,
+        bind_groups: Vec<BindGroup>,
+        shader_code: &str,
+        entry_point: &str,
+        num_workgroups: (usize, usize, usize),
+    ) -> Result<Self, MsmError> {
+        let pipeline = create_compute_pipeline(
+            Some(label),
+            device,
+            &bind_group_layout,
+            shader_code,
+            entry_point,
+        )
+        .await?;
+        Ok(Self {
+            pipeline,
+            bind_group_layout,
+            bind_groups,
+            num_workgroups,
+        })
+    }
+
+    /// Dispatch the pipeline once per cached bind group.
+    async fn encode(&self, encoder: &mut CommandEncoder) {
+        self.encode_with(encoder, &self.bind_groups).await;
+    }
+
+    /// Dispatch the pipeline once per bind group in `bind_groups`.
+    async fn encode_with(&self, encoder: &mut CommandEncoder, bind_groups: &[BindGroup]) {
+        let (x, y, z) = self.num_workgroups;
+        for bind_group in bind_groups {
+            execute_pipeline(
+                encoder,
+                self.pipeline.clone(),
+                bind_group.clone(),
+                x as u32,
+                y as u32,
+                z as u32,
+            )
+            .await;
+        }
+    }
+}
+
+/// The buffers, or ranges of buffers, that the bucket stages of one MSM
+/// instance write.
+struct BucketBuffers<'a> {
+    /// One range per coordinate, in the order of `CurveModel::coord_names`
+    bucket_sums: Vec<BufferBinding<'a>>,
+    /// One range per coordinate, in the same order
+    g_points: Vec<BufferBinding<'a>>,
+    windows: BufferBinding<'a>,
+    result: BufferBinding<'a>,
+}
+
+/// Bind groups of the SMVP, BPR, window sum and Horner stages for one set of
+/// [`BucketBuffers`].
+#[derive(Default)]
+struct BucketBindGroups {
+    /// One per SMVP subtask offset
+    smvp: Vec<BindGroup>,
+    /// One per BPR subtask offset, shared by both BPR stages
+    bpr: Vec<BindGroup>,
+    /// Shared by the window sum and Horner stages
+    horner: Vec<BindGroup>,
+}
+
+/// Shaders, pipelines and buffers for one curve and set of [`Workgroups`].
+struct MsmPipelines {
+    num_subtasks: usize,
+    num_words: usize,
+    /// Base field modulus of the curve
+    p: BigUint,
+    /// Inverse of the Montgomery radix modulo `p`
+    rinv: BigUint,
+    input_size_ub: Buffer,
+    points_sb: Buffer,
+    scalars_sb: Buffer,
+    all_csc_col_ptr_sb: Buffer,
+    all_csc_val_idxs_sb: Buffer,
+    all_curr_sb: Buffer,
+    /// One bucket sum buffer per coordinate, in the order of
+    /// `CurveModel::coord_names`
+    bucket_sum_sbs: Vec<Buffer>,
+    /// One BPR output buffer per coordinate, in the same order
+    g_points_sbs: Vec<Buffer>,
+    smvp_params_ubs: Vec<Buffer>,
+    bpr_params_ubs: Vec<Buffer>,
+    /// Window sums in Montgomery form, one point per subtask
+    windows_sb: Buffer,
+    /// The MSM result, converted out of Montgomery form on the GPU
+    result_sb: Buffer,
+    /// Bind groups of the bucket stages over the buffers above, reading the
+    /// points written by `decompose`
+    bucket_bind_groups: BucketBindGroups,
+    decompose: Stage,
+    convert_point_coords: Stage,
+    decompose_scalars_only: Stage,
+    transpose_histogram: Stage,
+    transpose_scan: Stage,
+    transpose_scatter: Stage,
+    smvp: Stage,
+    bpr_1: Stage,
+    bpr_2: Stage,
+    window_sums: Stage,
+    horner: Stage,
+}
+
+impl MsmPipelines {
+    async fn new<C: GpuCurve>(
+        device: &Device,
+        queue: &Queue,
+        plan: &MsmPlan,
+        workgroups: &Workgroups,
+    ) -> Result<Self, MsmError> {
+        let input_size = plan.input_size;
+        let num_terms = plan.num_terms();
+        let chunk_size = plan.chunk_size;
+        let num_columns = plan.num_columns();
+        let num_rows = num_terms.div_ceil(num_columns);
+        let num_subtasks = plan.num_subtasks;
+        let params = C::misc_params();
+        let num_words = params.num_words;
+        let coord_words = C::coord_words();
+
+        let mut shader_manager = ShaderManager::for_curve::<C>(WORD_SIZE, chunk_size, input_size);
+        shader_manager.set_raw_montgomery_input(true);
+        shader_manager.set_glv(plan.glv);
+        shader_manager.set_batch_affine(workgroups.batch_affine);
+
+        ////////////////////////////////////////////////////////////////////////////////////////
+        // 1. Decompose scalars into chunk_size windows using signed bucket indices.          /
+        ////////////////////////////////////////////////////////////////////////////////////////
+
+        // Total thread count = workgroup_size * #x workgroups * #y workgroups * #z workgroups.
+        let c_workgroup_size = workgroups.decompose_workgroup_size;
+        let c_num_x_workgroups = workgroups.decompose_num_x_workgroups;
+        let c_num_y_workgroups = workgroups.decompose_num_y_workgroups;
+        let c_num_z_workgroups = 1;
+        let c_num_workgroups = (c_num_x_workgroups, c_num_y_workgroups, c_num_z_workgroups);
+
+        check_storage_buffer_size(
+            "Points buffer",
+            device,
+            (input_size * 2 * C::COORD_BYTES) as u64,
+        )?;
+        check_storage_buffer_size(
+            "Point X buffer",
+            device,
+            (num_terms * coord_words * 4) as u64,
+        )?;
+        check_storage_buffer_size(
+            "Scalar chunks buffer",
+            device,
+            (num_terms * num_subtasks * 4) as u64,
+        )?;
+        let points_sb = create_storage_buffer(
+            Some("Points buffer"),
+            device,
+            (input_size * 2 * C::COORD_BYTES) as u64,
+        );
+        let scalars_sb =
+            create_storage_buffer(Some("Scalars buffer"), device, (input_size * 32) as u64);
+        let point_x_sb = create_storage_buffer(
+            Some("Point X buffer"),
+            device,
+            (num_terms * coord_words * 4) as u64,
+        );
+        let point_y_sb = create_storage_buffer(
+            Some("Point Y buffer"),
+            device,
+            (num_terms * coord_words * 4) as u64,
+        );
+        let scalar_chunks_sb = create_storage_buffer(
+            Some("Scalar chunks buffer"),
+            device,
+            (num_terms * num_subtasks * 4) as u64,
+        );
+        let input_size_ub = create_and_write_uniform_buffer(
+            Some("Params buffer"),
+            device,
+            queue,
+            &to_u8s_for_gpu(vec![num_terms])?,
+        );
+
+        let decompose = {
+            let c_shader = shader_manager.gen_decomp_scalars_shader(
+                c_workgroup_size,
+                c_num_y_workgroups,
+                num_subtasks,
+                num_columns,
+            )?;
+            let bind_group_layout = create_bind_group_layout(
+                Some("Bind group layout"),
+                device,
+                vec![&points_sb, &scalars_sb],
+                vec![&point_x_sb, &point_y_sb, &scalar_chunks_sb],
+                vec![&input_size_ub],
+            );
+            let bind_group = create_bind_group(
+                Some("Bind group"),
+                device,
+                &bind_group_layout,
+                vec![
+                    &points_sb,
+                    &scalars_sb,
+                    &point_x_sb,
+                    &point_y_sb,
+                    &scalar_chunks_sb,
+                    &input_size_ub,
+                ],
+            );
+            Stage::new(
+                "Convert point coords and decompose shader",
+                device,
+                bind_group_layout,
+                vec![bind_group],
+                &c_shader,
+                "main",
+                c_num_workgroups,
+            )
+            .await?
+        };
+
+        // The two halves of the decompose shader, used in fixed-base mode. The
+        // point conversion is bound to the output buffers of each base set when
+        // it is uploaded, so it has no cached bind group.
+        let convert_point_coords = {
+            let shader = shader_manager
+                .gen_convert_point_coords_shader(c_workgroup_size, c_num_y_workgroups)?;
+            let bind_group_layout = create_bind_group_layout(
+                Some("Convert point coords bind group layout"),
+                device,
+                vec![&points_sb],
+                vec![&point_x_sb, &point_y_sb],
+                vec![&input_size_ub],
+            );
+            Stage::new(
+                "Convert point coords shader",
+                device,
+                bind_group_layout,
+                vec![],
+                &shader,
+                "main",
+                c_num_workgroups,
+            )
+            .await?
+        };
+
+        let decompose_scalars_only = {
+            let shader = shader_manager.gen_decomp_scalars_only_shader(
+                c_workgroup_size,
+                c_num_y_workgroups,
+                num_subtasks,
+                num_columns,
+            )?;
+            let bind_group_layout = create_bind_group_layout(
+                Some("Decompose scalars bind group layout"),
+                device,
+                vec![&scalars_sb],
+                vec![&scalar_chunks_sb],
+                vec![&input_size_ub],
+            );
+            let bind_group = create_bind_group(
+                Some("Decompose scalars bind group"),
+                device,
+                &bind_group_layout,
+                vec![&scalars_sb, &scalar_chunks_sb, &input_size_ub],
+            );
+            Stage::new(
+                "Decompose scalars shader",
+                device,
+                bind_group_layout,
+                vec![bind_group],
+                &shader,
+                "main",
+                c_num_workgroups,
+            )
+            .await?
+        };
+
+        ////////////////////////////////////////////////////////////////////////////////////////
+        // 2. Sparse Matrix Transposition                                                     /
+        ////////////////////////////////////////////////////////////////////////////////////////
+
+        let all_csc_col_ptr_sb = create_storage_buffer(
+            Some("All CSC col"),
+            device,
+            (num_subtasks * (num_columns + 1) * 4) as u64,
+        );
+        let all_csc_val_idxs_sb =
+            create_storage_buffer(Some("All CSC Val Indexes"), device, scalar_chunks_sb.size());
+        let all_curr_sb = create_storage_buffer(
+            Some("All Current"),
+            device,
+            (num_subtasks * num_columns * 4) as u64,
+        );
+        let t_params_ub = create_and_write_uniform_buffer(
+            Some("Transpose GPU Uniform Params"),
+            device,
+            queue,
+            &to_u8s_for_gpu(vec![num_rows, num_columns, num_terms])?,
+        );
+
+        let (transpose_histogram, transpose_scan, transpose_scatter) = {
+            let t_shader =
+                shader_manager.gen_transpose_shader(workgroups.transpose_workgroup_size)?;
+            let bind_group_layout = create_bind_group_layout(
+                Some("Transpose GPU Bind Group Layout"),
+                device,
+                vec![&scalar_chunks_sb],
+                vec![&all_csc_col_ptr_sb, &all_csc_val_idxs_sb, &all_curr_sb],
+                vec![&t_params_ub],
+            );
+            let bind_group = create_bind_group(
+                Some("Transpose GPU Bind Group"),
+                device,
+                &bind_group_layout,
+                vec![
+                    &scalar_chunks_sb,
+                    &all_csc_col_ptr_sb,
+                    &all_csc_val_idxs_sb,
+                    &all_curr_sb,
+                    &t_params_ub,
+                ],
+            );
+            let t_num_workgroups = (workgroups.transpose_num_x_workgroups, 1, num_subtasks);
+            (
+                Stage::new(
+                    "Transpose GPU Histogram Pipeline",
+                    device,
+                    bind_group_layout.clone(),
+                    vec![bind_group.clone()],
+                    &t_shader,
+                    "histogram",
+                    t_num_workgroups,
+                )
+                .await?,
+                Stage::new(
+                    "Transpose GPU Scan Pipeline",
+                    device,
+                    bind_group_layout.clone(),
+                    vec![bind_group.clone()],
+                    &t_shader,
+                    "scan",
+                    (1, 1, num_subtasks),
+                )
+                .await?,
+                Stage::new(
+                    "Transpose GPU Scatter Pipeline",
+                    device,
+                    bind_group_layout,
+                    vec![bind_group],
+                    &t_shader,
+                    "scatter",
+                    t_num_workgroups,
+                )
+                .await?,
+            )
+        };
+
+        ////////////////////////////////////////////////////////////////////////////////////////
+        // 3. Sparse Matrix Vector Product (SMVP)                                             /
+        ////////////////////////////////////////////////////////////////////////////////////////
+
+        let s_workgroup_size = workgroups.smvp_workgroup_size;
+        let s_num_x_workgroups = workgroups.smvp_num_x_workgroups;
+        let s_num_y_workgroups = workgroups.smvp_num_y_workgroups;
+
+        // This is a dynamic variable that determines the number of CSR
+        // matrices processed per invocation of the shader. A safe default is 1.
+        let num_subtask_chunk_size = workgroups.smvp_subtasks_per_dispatch;
+
+        // The x and y workgroups cover the buckets of one subtask, and each z
+        // workgroup handles one of the subtasks of an invocation.
+        let s_num_z_workgroups = num_subtask_chunk_size;
+
+        // Buffers that store the SMVP result, ie. bucket sums. They are
+        // overwritten per iteration.
+        let bucket_sum_coord_bytelength = (num_columns / 2) * coord_words * 4 * num_subtasks;
+        check_storage_buffer_size(
+            "Bucket sum buffer",
+            device,
+            bucket_sum_coord_bytelength as u64,
+        )?;
+        let bucket_sum_sbs = C::MODEL
+            .coord_names()
+            .iter()
+            .map(|coord| {
+                create_storage_buffer(
+                    Some(&format!("Bucket sum {} buffer", coord)),
+                    device,
+                    bucket_sum_coord_bytelength as u64,
+                )
+            })
+            .collect::<Vec<_>>();
+        let smvp_params_ubs = (0..num_subtasks)
+            .step_by(num_subtask_chunk_size)
+            .map(|offset| {
+                Ok(create_and_write_uniform_buffer(
+                    None,
+                    device,
+                    queue,
+                    &to_u8s_for_gpu(vec![
+                        num_terms,
+                        s_num_y_workgroups,
+                        s_num_z_workgroups,
+                        offset,
+                    ])?,
+                ))
+            })
+            .collect::<Result<Vec<_>, MsmError>>()?;
+
+        // The batch-affine shader keeps one field element per thread, the
+        // shared inverse and the step count in workgroup memory.
+        let (smvp_entry_point, smvp_workgroup_storage) = if workgroups.batch_affine {
+            ("batch_affine", ((s_workgroup_size + 1) * num_words + 2) * 4)
+        } else {
+            ("main", 0)
+        };
+        let max_workgroup_storage = device.limits().max_compute_workgroup_storage_size as usize;
+        if smvp_workgroup_storage > max_workgroup_storage {
+            return Err(MsmError::LimitsExceeded {
+                resource: "Batch-affine SMVP workgroup storage".to_string(),
+                requested: smvp_workgroup_storage as u64,
+                limit: max_workgroup_storage as u64,
+            });
+        }
+
+        let smvp = {
+            let smvp_shader = shader_manager.gen_smvp_shader(s_workgroup_size, num_columns)?;
+            let bind_group_layout = create_bind_group_layout(
+                Some("Bind group layout"),
+                device,
+                vec![
+                    &all_csc_col_ptr_sb,
+                    &all_csc_val_idxs_sb,
+                    &point_x_sb,
+                    &point_y_sb,
+                ],
+                bucket_sum_sbs.iter().collect(),
+                vec![&smvp_params_ubs[0]],
+            );
+            Stage::new(
+                "Compute pipeline",
+                device,
+                bind_group_layout,
+                vec![],
+                &smvp_shader,
+                smvp_entry_point,
+                (s_num_x_workgroups, s_num_y_workgroups, s_num_z_workgroups),
+            )
+            .await?
+        };
+
+        ////////////////////////////////////////////////////////////////////////////////////////
+        // 4. Bucket Reduction                                                                /
+        ////////////////////////////////////////////////////////////////////////////////////////
+
+        // This is a dynamic variable that determines the number of CSR
+        // matrices processed per invocation of the BPR shader. A safe default is 1.
+        let num_subtasks_per_bpr = workgroups.bpr_subtasks_per_dispatch;
+        let bpr_workgroup_size = workgroups.bpr_workgroup_size;
+
+        // Buffers that store the bucket points reduction (BPR) output.
+        let g_points_coord_bytelength = num_subtasks * bpr_workgroup_size * coord_words * 4;
+        let g_points_sbs = C::MODEL
+            .coord_names()
+            .iter()
+            .map(|coord| {
+                create_storage_buffer(
+                    Some(&format!("Bucket points reduction {} buffer", coord)),
+                    device,
+                    g_points_coord_bytelength as u64,
+                )
+            })
+            .collect::<Vec<_>>();
+        let bpr_params_ubs = (0..num_subtasks)
+            .step_by(num_subtasks_per_bpr)
+            .map(|subtask_idx| {
+                Ok(create_and_write_uniform_buffer(
+                    None,
+                    device,
+                    queue,
+                    &to_u8s_for_gpu(vec![subtask_idx, num_columns, num_subtasks_per_bpr])?,
+                ))
+            })
+            .collect::<Result<Vec<_>, MsmError>>()?;
+
+        let (bpr_1, bpr_2) = {
+            let bpr_shader = shader_manager.gen_bpr_shader(bpr_workgroup_size)?;
+            let bind_group_layout = create_bind_group_layout(
+                Some("Bind group layout"),
+                device,
+                vec![],
+                bucket_sum_sbs.iter().chain(&g_points_sbs).collect(),
+                vec![&bpr_params_ubs[0]],
+            );
+            let num_workgroups = (num_subtasks_per_bpr, 1, 1);
+            (
+                Stage::new(
+                    "Compute pipeline",
+                    device,
+                    bind_group_layout.clone(),
+                    vec![],
+                    &bpr_shader,
+                    "stage_1",
+                    num_workgroups,
+                )
+                .await?,
+                Stage::new(
+                    "Compute pipeline",
+                    device,
+                    bind_group_layout,
+                    vec![],
+                    &bpr_shader,
+                    "stage_2",
+                    num_workgroups,
+                )
+                .await?,
+            )
+        };
+
+        ////////////////////////////////////////////////////////////////////////////////////////
+        // 5. Window Sums and Horner's Method                                                  /
+        //                                                                                     /
+        // Sum the g points of every subtask and combine the window sums with Horner's method  /
+        // (Formula 3 of the cuZK paper), so that only the final point is read back.           /
+        ////////////////////////////////////////////////////////////////////////////////////////
+
+        let num_coords = C::MODEL.coord_names().len();
+        let windows_sb = create_storage_buffer(
+            Some("Window sums buffer"),
+            device,
+            (num_subtasks * num_coords * coord_words * 4) as u64,
+        );
+        let result_sb = create_storage_buffer(
+            Some("Result buffer"),
+            device,
+            (num_coords * coord_words * 4) as u64,
+        );
+
+        let (window_sums, horner) = {
+            let workgroup_size = workgroups.window_sums_workgroup_size;
+            let horner_shader = shader_manager.gen_horner_shader(
+                workgroup_size,
+                num_subtasks,
+                bpr_workgroup_size,
+            )?;
+            let bind_group_layout = create_bind_group_layout(
+                Some("Bind group layout"),
+                device,
+                g_points_sbs.iter().collect(),
+                vec![&windows_sb, &result_sb],
+                vec![],
+            );
+            (
+                Stage::new(
+                    "Compute pipeline",
+                    device,
+                    bind_group_layout.clone(),
+                    vec![],
+                    &horner_shader,
+                    "window_sums",
+                    (num_subtasks.div_ceil(workgroup_size), 1, 1),
+                )
+                .await?,
+                Stage::new(
+                    "Compute pipeline",
+                    device,
+                    bind_group_layout,
+                    vec![],
+                    &horner_shader,
+                    "horner",
+                    (1, 1, 1),
+                )
+                .await?,
+            )
+        };
+
+        let mut pipelines = Self {
+            num_subtasks,
+            num_words,
+            p: C::base_modulus(),
+            rinv: params.rinv,
+            input_size_ub,
+            points_sb,
+            scalars_sb,
+            all_csc_col_ptr_sb,
+            all_csc_val_idxs_sb,
+            all_curr_sb,
+            bucket_sum_sbs,
+            g_points_sbs,
+            smvp_params_ubs,
+            bpr_params_ubs,
+            windows_sb,
+            result_sb,
+            bucket_bind_groups: BucketBindGroups::default(),
+            decompose,
+            convert_point_coords,
+            decompose_scalars_only,
+            transpose_histogram,
+            transpose_scan,
+            transpose_scatter,
+            smvp,
+            bpr_1,
+            bpr_2,
+            window_sums,
+            horner,
+        };
+        pipelines.bucket_bind_groups = pipelines.bucket_bind_groups(
+            device,
+            &point_x_sb,
+            &point_y_sb,
+            &pipelines.bucket_buffers(),
+        );
+        Ok(pipelines)
+    }
+
+    /// The bucket sum, g point, window sum and result buffers of the pipelines
+    fn bucket_buffers(&self) -> BucketBuffers<'_> {
+        BucketBuffers {
+            bucket_sums: self
+                .bucket_sum_sbs
+                .iter()
+                .map(Buffer::as_entire_buffer_binding)
+                .collect(),
+            g_points: self
+                .g_points_sbs
+                .iter()
+                .map(Buffer::as_entire_buffer_binding)
+                .collect(),
+            windows: self.windows_sb.as_entire_buffer_binding(),
+            result: self.result_sb.as_entire_buffer_binding(),
+        }
+    }
+
+    /// Create the bind groups of the SMVP, BPR and Horner stages that read
+    /// the points from `point_x_sb` and `point_y_sb` and write `buffers`.
+    fn bucket_bind_groups(
+        &self,
+        device: &Device,
+        point_x_sb: &Buffer,
+        point_y_sb: &Buffer,
+        buffers: &BucketBuffers,
+    ) -> BucketBindGroups {
+        let BucketBuffers {
+            bucket_sums,
+            g_points,
+            windows,
+            result,
+        } = buffers;
+        let smvp = self
+            .smvp_params_ubs
+            .iter()
+            .map(|params_ub| {
+                create_bind_group_with_bindings(
+                    Some("Bind group"),
+                    device,
+                    &self.smvp.bind_group_layout,
+                    [
+                        &self.all_csc_col_ptr_sb,
+                        &self.all_csc_val_idxs_sb,
+                        point_x_sb,
+                        point_y_sb,
+                    ]
+                    .into_iter()
+                    .map(Buffer::as_entire_buffer_binding)
+                    .chain(bucket_sums.iter().cloned())
+                    .chain([params_ub.as_entire_buffer_binding()])
+                    .collect(),
+                )
+            })
+            .collect();
+        // The BPR shader binds the x, y and z buffers of the bucket sums and
+        // g points first, followed by the t buffers of twisted Edwards curves.
+        let bpr_storage = bucket_sums[..3]
+            .iter()
+            .chain(&g_points[..3])
+            .chain(&bucket_sums[3..])
+            .chain(&g_points[3..])
+            .cloned()
+            .collect::<Vec<_>>();
+        let bpr = self
+            .bpr_params_ubs
+            .iter()
+            .map(|params_ub| {
+                create_bind_group_with_bindings(
+                    Some("Bind group"),
+                    device,
+                    &self.bpr_1.bind_group_layout,
+                    bpr_storage
+                        .iter()
+                        .cloned()
+                        .chain([params_ub.as_entire_buffer_binding()])
+                        .collect(),
+                )
+            })
+            .collect();
+        let horner = create_bind_group_with_bindings(
+            Some("Bind group"),
+            device,
+            &self.horner.bind_group_layout,
+            g_points.iter().chain([windows, result]).cloned().collect(),
+        );
+        BucketBindGroups {
+            smvp,
+            bpr,
+            horner: vec![horner],
+        }
+    }
+
+    /// Record the transpose, SMVP, BPR, window sum and Horner stages into
+    /// `encoder`. The scalar chunks must already have been written by one of
+    /// the decompose stages.
+    async fn encode_buckets(&self, encoder: &mut CommandEncoder, bind_groups: &BucketBindGroups) {
+        // The transpose shader accumulates into these buffers, so they must
+        // start from zero on every run.
+        encoder.clear_buffer(&self.all_csc_col_ptr_sb, 0, None);
+        encoder.clear_buffer(&self.all_curr_sb, 0, None);
+
+        self.transpose_histogram.encode(encoder).await;
+        self.transpose_scan.encode(encoder).await;
+        self.transpose_scatter.encode(encoder).await;
+        self.smvp.encode_with(encoder, &bind_groups.smvp).await;
+        self.bpr_1.encode_with(encoder, &bind_groups.bpr).await;
+        self.bpr_2.encode_with(encoder, &bind_groups.bpr).await;
+        self.window_sums
+            .encode_with(encoder, &bind_groups.horner)
+            .await;
+        self.horner.encode_with(encoder, &bind_groups.horner).await;
+    }
+
+    /// Submit `encoder` and map the result back from GPU to CPU, followed by
+    /// the window sums if `with_windows` is set.
+    async fn read_result(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        encoder: CommandEncoder,
+        with_windows: bool,
+    ) -> Result<Vec<Vec<u8>>, MsmError> {
+        let mut buffers = vec![self.result_sb.clone()];
+        if with_windows {
+            buffers.push(self.windows_sb.clone());
+        }
+        read_from_gpu(device, queue, encoder, buffers).await
+    }
+
+    /// Decode the result read back from the Horner stage, which is already
+    /// out of Montgomery form.
+    fn decode_result<C: GpuCurve>(&self, data: &[u8]) -> Result<C::Curve, MsmError> {
+        let num_words = self.num_words;
+        let component_bytes = C::COORD_BYTES / C::EXT_DEGREE;
+        let coords = bytemuck::cast_slice::<u8, u32>(data)
+            .chunks(num_words * C::EXT_DEGREE)
+            .map(|c| {
+                let mut coord_bytes = Vec::with_capacity(C::COORD_BYTES);
+                for component in c.chunks(num_words) {
+                    let limbs = component
+                        .iter()
+                        .map(|&limb| limb as u16)
+                        .collect::<Vec<_>>();
+                    let value: C::Base =
+                        from_words_le_without_assertion(&limbs, num_words, WORD_SIZE);
+                    // Extension fields decode a component as the constant
+                    // term, so only keep its first `component_bytes`.
+                    let mut component_le = C::base_to_bytes(&value);
+                    component_le.truncate(component_bytes);
+                    coord_bytes.extend(component_le);
+                }
+                C::base_from_bytes(&coord_bytes)
+            })
+            .collect::<Vec<_>>();
+        C::from_gpu_coords(&coords)
+            .ok_or_else(|| MsmError::InvalidOutput("the result is not on the curve".to_string()))
+    }
+
+    /// Convert the window sums read back for the self-check out of
+    /// Montgomery form. A window sum that is not on the curve fails the
+    /// self-check.
+    fn decode_windows<C: GpuCurve>(&self, data: &[u8]) -> Result<Vec<C::Curve>, MsmError> {
+        let num_words = self.num_words;
+        let component_bytes = C::COORD_BYTES / C::EXT_DEGREE;
+        let coords = bytemuck::cast_slice::<u8, u32>(data)
+            .chunks(num_words * C::EXT_DEGREE)
+            .map(|c| {
+                // Convert each component out of Montgomery form and
+                // serialize them back to back.
+                let mut coord_bytes = Vec::with_capacity(C::COORD_BYTES);
+                for component in c.chunks(num_words) {
+                    let c_biguint_montgomery =
+                        to_biguint_le(component, num_words, WORD_SIZE as u32);
+                    let c_biguint = c_biguint_montgomery * &self.rinv % &self.p;
+                    let mut component_le = c_biguint.to_bytes_le();
+                    component_le.resize(component_bytes, 0);
+                    coord_bytes.extend(component_le);
+                }
+                C::base_from_bytes(&coord_bytes)
+            })
+            .collect::<Vec<_>>();
+        coords
+            .chunks(C::MODEL.coord_names().len())
+            .enumerate()
+            .map(|(window, point)| {
+                C::from_gpu_coords(point).ok_or(MsmError::VerificationFailed { window })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,241 @@
+use ff::{PrimeField, WithSmallOrderMulGroup};
+use halo2curves::{bls12381, bn256, grumpkin, pasta, secp256k1, secp256r1};
+use halo2curves::{CurveAffine, CurveExt};
+use num_bigint::BigUint;
+use num_traits::Num;
+
+use crate::cuzk::glv::Glv;
+use crate::cuzk::msm::WORD_SIZE;
+use crate::cuzk::utils::{
+    bytes_to_field, calc_num_words_for, compute_misc_params, field_to_bytes, MiscParams,
+};
+
+/// Coordinate system that the shaders use for the points of a curve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveModel {
+    /// `y^2 = x^3 + ax + b` in Jacobian coordinates `(x, y, z)`
+    ShortWeierstrass,
+    /// `ax^2 + y^2 = 1 + dx^2y^2` in extended coordinates `(x, y, z, t)`
+    TwistedEdwards,
+}
+
+impl CurveModel {
+    /// Names of the projective coordinates, in the order of the bucket and
+    /// g point buffers.
+    pub fn coord_names(self) -> &'static [&'static str] {
+        match self {
+            CurveModel::ShortWeierstrass => &["X", "Y", "Z"],
+            CurveModel::TwistedEdwards => &["X", "Y", "Z", "T"],
+        }
+    }
+}
+
+/// A curve that the cuZK pipeline can run on.
+///
+/// The trait provides everything the shaders are specialised on: the base
+/// field modulus, the curve constants, the bit length of the scalars and the
+/// byte layout of the affine coordinates that are uploaded to the GPU. The
+/// defaults derive all of these from the halo2curves field and curve traits,
+/// so most short-Weierstrass curves only need an empty impl.
+///
+/// Twisted Edwards curves set `MODEL` to `CurveModel::TwistedEdwards`, return
+/// the Edwards `a` and `d` from `curve_a` and `edwards_d`, and convert the
+/// extended coordinates computed on the GPU in `from_gpu_coords`.
+///
+/// Curves over a quadratic extension of the base field, such as BN254 G2, set
+/// `EXT_DEGREE` to 2. Their coordinates are then uploaded and stored on the
+/// GPU as two base field elements each.
+pub trait GpuCurve: CurveAffine<CurveExt: CurveExt<Base = Self::Base>> + 'static {
+    /// Size in bytes of one serialized base field coordinate
+    const COORD_BYTES: usize = 32;
+
+    /// Number of base field components of one coordinate, stored back to back
+    /// in `COORD_BYTES` starting with the constant term
+    const EXT_DEGREE: usize = 1;
+
+    /// Coordinate system of the curve in the shaders
+    const MODEL: CurveModel = CurveModel::ShortWeierstrass;
+
+    /// Whether `base_to_raw_bytes` writes Montgomery form with radix
+    /// `2^(8 * COORD_BYTES / EXT_DEGREE)` per component, as the 64-bit limbs
+    /// of the halo2curves fields do. The GPU then converts the coordinates
+    /// straight to its own Montgomery form.
+    const RAW_MONTGOMERY: bool = false;
+
+    /// The modulus of the prime field the coordinates are built from
+    fn base_modulus() -> BigUint {
+        BigUint::from_str_radix(
+            <Self::Base as PrimeField>::MODULUS.trim_start_matches("0x"),
+            16,
+        )
+        .expect("Invalid modulus")
+    }
+
+    /// Number of bits of the scalar field modulus
+    fn scalar_bits() -> usize {
+        <Self::ScalarExt as PrimeField>::NUM_BITS as usize
+    }
+
+    /// The curve constant `a`, with its components packed little-endian into
+    /// `COORD_BYTES` bytes
+    fn curve_a() -> BigUint {
+        BigUint::from_bytes_le(&Self::base_to_bytes(&<Self as CurveAffine>::a()))
+    }
+
+    /// The curve constant `b`, with its components packed little-endian into
+    /// `COORD_BYTES` bytes
+    fn curve_b() -> BigUint {
+        BigUint::from_bytes_le(&Self::base_to_bytes(&<Self as CurveAffine>::b()))
+    }
+
+    /// The twisted Edwards constant `d`. Unused by short-Weierstrass curves.
+    fn edwards_d() -> BigUint {
+        BigUint::ZERO
+    }
+
+    /// Build a curve point from the projective coordinates computed on the
+    /// GPU, `[x, y, z]` in Jacobian coordinates for short-Weierstrass curves
+    /// and `[x, y, z, t]` in extended coordinates for twisted Edwards curves.
+    fn from_gpu_coords(coords: &[Self::Base]) -> Option<Self::Curve> {
+        Self::Curve::new_jacobian(coords[0], coords[1], coords[2]).into()
+    }
+
+    /// Serialize a base field element into `COORD_BYTES` little-endian bytes
+    fn base_to_bytes(value: &Self::Base) -> Vec<u8> {
+        field_to_bytes(value)
+    }
+
+    /// Deserialize a base field element from at most `COORD_BYTES`
+    /// little-endian bytes
+    fn base_from_bytes(bytes: &[u8]) -> Self::Base {
+        bytes_to_field(bytes)
+    }
+
+    /// Serialize a base field element in the curve library's internal
+    /// Montgomery form, skipping the conversion to canonical form. Only used
+    /// when `RAW_MONTGOMERY` is set.
+    fn base_to_raw_bytes(value: &Self::Base) -> Vec<u8> {
+        Self::base_to_bytes(value)
+    }
+
+    /// Number of `WORD_SIZE`-bit limbs of one base field component on the GPU
+    fn num_words() -> usize {
+        calc_num_words_for(&Self::base_modulus(), WORD_SIZE)
+    }
+
+    /// Number of u32 limbs of one coordinate on the GPU
+    fn coord_words() -> usize {
+        Self::num_words() * Self::EXT_DEGREE
+    }
+
+    /// Montgomery parameters of the base field for `WORD_SIZE`-bit limbs
+    fn misc_params() -> MiscParams {
+        compute_misc_params(&Self::base_modulus(), WORD_SIZE)
+    }
+
+    /// The GLV endomorphism the decompose shaders can split scalars with, or
+    /// `None` if the curve has none or the pipeline does not support it
+    fn glv() -> Option<Glv> {
+        None
+    }
+}
+
+/// BN254 G1 has `j = 0`, so `(x, y) -> (zeta * x, y)` is an endomorphism. It
+/// multiplies points by the cube root of unity `Fr::ZETA`.
+impl GpuCurve for bn256::G1Affine {
+    const RAW_MONTGOMERY: bool = true;
+
+    fn base_to_raw_bytes(value: &bn256::Fq) -> Vec<u8> {
+        limbs_to_bytes(&value.0)
+    }
+
+    fn glv() -> Option<Glv> {
+        let to_biguint = |bytes: &[u8]| BigUint::from_bytes_le(bytes);
+        Some(Glv::new(
+            to_biguint(&field_to_bytes(&bn256::Fq::ZETA)),
+            to_biguint(&field_to_bytes(&bn256::Fr::ZETA)),
+            BigUint::from_str_radix(bn256::Fr::MODULUS.trim_start_matches("0x"), 16)
+                .expect("Invalid modulus"),
+        ))
+    }
+}
+
+/// G2 is defined over `Fq2 = Fq[u] / (u^2 + 1)`, serialized as `c0 || c1`.
+impl GpuCurve for bn256::G2Affine {
+    const COORD_BYTES: usize = 64;
+    const EXT_DEGREE: usize = 2;
+    const RAW_MONTGOMERY: bool = true;
+
+    fn base_to_raw_bytes(value: &bn256::Fq2) -> Vec<u8> {
+        [limbs_to_bytes(&value.c0().0), limbs_to_bytes(&value.c1().0)].concat()
+    }
+}
+
+/// The 381-bit base field takes 48 bytes per coordinate.
+impl GpuCurve for bls12381::G1Affine {
+    const COORD_BYTES: usize = 48;
+    const RAW_MONTGOMERY: bool = true;
+
+    fn base_to_raw_bytes(value: &bls12381::Fq) -> Vec<u8> {
+        limbs_to_bytes(&value.0)
+    }
+}
+
+/// Grumpkin's base field is the BN254 scalar field, and its scalar field the
+/// BN254 base field.
+impl GpuCurve for grumpkin::G1Affine {
+    const RAW_MONTGOMERY: bool = true;
+
+    fn base_to_raw_bytes(value: &grumpkin::Fq) -> Vec<u8> {
+        limbs_to_bytes(&value.0)
+    }
+}
+
+impl GpuCurve for pasta::PallasAffine {
+    const RAW_MONTGOMERY: bool = true;
+
+    fn base_to_raw_bytes(value: &pasta::Fp) -> Vec<u8> {
+        limbs_to_bytes(&value.0)
+    }
+}
+
+impl GpuCurve for pasta::VestaAffine {
+    const RAW_MONTGOMERY: bool = true;
+
+    fn base_to_raw_bytes(value: &pasta::Fq) -> Vec<u8> {
+        limbs_to_bytes(&value.0)
+    }
+}
+
+impl GpuCurve for secp256k1::Secp256k1Affine {
+    const RAW_MONTGOMERY: bool = true;
+
+    fn base_to_raw_bytes(value: &secp256k1::Fp) -> Vec<u8> {
+        limbs_to_bytes(&value.0)
+    }
+}
+
+/// secp256r1 has `a = -3`, for which the shaders use a dedicated doubling
+/// formula.
+impl GpuCurve for secp256r1::Secp256r1Affine {
+    const RAW_MONTGOMERY: bool = true;
+
+    fn base_to_raw_bytes(value: &secp256r1::Fp) -> Vec<u8> {
+        limbs_to_bytes(&value.0)
+    }
+}
+
+/// Serialize the little-endian 64-bit limbs of a halo2curves field element
+fn limbs_to_bytes(limbs: &[u64]) -> Vec<u8> {
+    limbs.iter().flat_map(|limb| limb.to_le_bytes()).collect()
+}
+
+/// Number of signed windows of `chunk_size` bits needed for scalars of
+/// `scalar_bits` bits.
+///
+/// The signed digit of a window can carry one into the next window, and the
+/// digit of the top window must stay below `2^(chunk_size - 1)`, so the
+/// windows cover two bits more than the scalar.
+pub fn num_subtasks(scalar_bits: usize, chunk_size: usize) -> usize {
+    (scalar_bits + 2).div_ceil(chunk_size)
+}
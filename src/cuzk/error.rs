@@ -0,0 +1,110 @@
+use std::fmt;
+
+use wgpu::{BufferAsyncError, RequestDeviceError};
+
+/// Errors returned by the GPU MSM pipeline
+#[derive(Debug)]
+pub enum MsmError {
+    /// No GPU adapter is available on this system or browser
+    NoAdapter,
+    /// The adapter refused to create a device with the requested limits
+    RequestDevice(RequestDeviceError),
+    /// The device was lost, e.g. after a driver reset or a browser tab
+    /// losing its GPU process
+    DeviceLost(String),
+    /// A buffer or dispatch is larger than the device allows
+    LimitsExceeded {
+        /// What exceeded the limit, e.g. a buffer label
+        resource: String,
+        /// The requested size
+        requested: u64,
+        /// The device limit
+        limit: u64,
+    },
+    /// A shader template failed to render, or the generated WGSL failed to
+    /// compile or validate
+    Shader(String),
+    /// Mapping a staging buffer for readback failed
+    BufferMap(BufferAsyncError),
+    /// The points or scalars passed in are not a valid MSM instance
+    InvalidInput(String),
+    /// An `MsmConfig` override does not fit the shape of the pipeline
+    InvalidConfig(String),
+    /// The self-check found a window sum computed on the GPU that does not
+    /// match the CPU recomputation
+    VerificationFailed {
+        /// Index of the mismatching window
+        window: usize,
+    },
+    /// The GPU returned coordinates that are not a point on the curve
+    InvalidOutput(String),
+}
+
+impl fmt::Display for MsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MsmError::NoAdapter => write!(f, "No suitable GPU adapters found on the system"),
+            MsmError::RequestDevice(e) => write!(f, "Could not create device: {e}"),
+            MsmError::DeviceLost(msg) => write!(f, "Device lost: {msg}"),
+            MsmError::LimitsExceeded {
+                resource,
+                requested,
+                limit,
+            } => write!(
+                f,
+                "{resource} requires {requested} but the device limit is {limit}"
+            ),
+            MsmError::Shader(msg) => write!(f, "Shader error: {msg}"),
+            MsmError::BufferMap(e) => write!(f, "Could not map buffer: {e}"),
+            MsmError::InvalidInput(msg) => write!(f, "Invalid input: {msg}"),
+            MsmError::InvalidConfig(msg) => write!(f, "Invalid config: {msg}"),
+            MsmError::VerificationFailed { window } => write!(
+                f,
+                "GPU result for window {window} does not match the CPU recomputation"
+            ),
+            MsmError::InvalidOutput(msg) => write!(f, "Invalid GPU output: {msg}"),
+        }
+    }
+}
+
+impl MsmError {
+    /// Whether the error means there is no GPU or it went away, so the same
+    /// MSM should be retried on the CPU.
+    pub fn is_gpu_unavailable(&self) -> bool {
+        matches!(self, MsmError::NoAdapter | MsmError::DeviceLost(_))
+    }
+}
+
+impl std::error::Error for MsmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MsmError::RequestDevice(e) => Some(e),
+            MsmError::BufferMap(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<RequestDeviceError> for MsmError {
+    fn from(e: RequestDeviceError) -> Self {
+        MsmError::RequestDevice(e)
+    }
+}
+
+impl From<BufferAsyncError> for MsmError {
+    fn from(e: BufferAsyncError) -> Self {
+        MsmError::BufferMap(e)
+    }
+}
+
+impl From<handlebars::RenderError> for MsmError {
+    fn from(e: handlebars::RenderError) -> Self {
+        MsmError::Shader(e.to_string())
+    }
+}
+
+impl From<handlebars::TemplateError> for MsmError {
+    fn from(e: handlebars::TemplateError) -> Self {
+        MsmError::Shader(e.to_string())
+    }
+}
@@ -0,0 +1,135 @@
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{Signed, Zero};
+
+/// Bit width of the limbs the GLV decomposition works on in the shaders
+pub const GLV_LIMB_BITS: usize = 16;
+
+/// The GLV endomorphism `(x, y) -> (beta * x, y)` of a curve and the constants
+/// the decompose shaders split scalars with.
+///
+/// The endomorphism multiplies every point of the prime order subgroup by
+/// `lambda`. A scalar `k` is split into `k1 + k2 * lambda` with `k1` and `k2`
+/// about half as long as `k`, using a short basis `v1`, `v2` of the lattice of
+/// vectors `(a, b)` with `a + b * lambda = 0` modulo the group order. See
+/// Algorithm 3.74 of the Guide to Elliptic Curve Cryptography.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Glv {
+    /// Cube root of unity in the base field that scales `x`
+    pub beta: BigUint,
+    /// Cube root of unity modulo the group order that `beta` corresponds to
+    pub lambda: BigUint,
+    /// Order of the prime order subgroup
+    pub order: BigUint,
+    /// First short lattice vector `(a1, b1)`
+    pub v1: (BigInt, BigInt),
+    /// Second short lattice vector `(a2, b2)`
+    pub v2: (BigInt, BigInt),
+    /// `b2 * 2^shift / det`, truncated, so that `g1 * k >> shift` rounds
+    /// the first coordinate of `k` in the lattice basis
+    pub g1: BigInt,
+    /// `-b1 * 2^shift / det`, truncated
+    pub g2: BigInt,
+    /// Precision of `g1` and `g2`, a multiple of `GLV_LIMB_BITS` at least as
+    /// large as the bit length of the order
+    pub shift: usize,
+    /// Bound on the bit length of the magnitudes of `k1` and `k2`
+    pub half_bits: usize,
+}
+
+impl Glv {
+    /// Derive the lattice basis and rounding constants for the endomorphism
+    /// with constants `beta` and `lambda` of a group of order `order`.
+    pub fn new(beta: BigUint, lambda: BigUint, order: BigUint) -> Self {
+        let n = BigInt::from(order.clone());
+        let sqrt_n = n.sqrt();
+
+        // Extended Euclid on (n, lambda). Every remainder r_i satisfies
+        // r_i = t_i * lambda mod n, so (r_i, -t_i) is in the lattice.
+        let mut rows = vec![
+            (n.clone(), BigInt::zero()),
+            (BigInt::from(lambda.clone()), BigInt::from(1)),
+        ];
+        while !rows[rows.len() - 1].0.is_zero() {
+            let (r0, t0) = rows[rows.len() - 2].clone();
+            let (r1, t1) = rows[rows.len() - 1].clone();
+            let q = &r0 / &r1;
+            rows.push((r0 - &q * &r1, t0 - &q * t1));
+        }
+        let l = rows.iter().rposition(|(r, _)| *r >= sqrt_n).unwrap();
+        let vector = |i: usize| (rows[i].0.clone(), -rows[i].1.clone());
+        let norm = |(a, b): &(BigInt, BigInt)| a * a + b * b;
+        let v1 = vector(l + 1);
+        let v2 = match rows.get(l + 2) {
+            Some(_) if norm(&vector(l + 2)) < norm(&vector(l)) => vector(l + 2),
+            _ => vector(l),
+        };
+
+        let det = &v1.0 * &v2.1 - &v2.0 * &v1.1;
+        let shift = (order.bits() as usize).next_multiple_of(GLV_LIMB_BITS);
+        let g1 = (&v2.1 << shift) / &det;
+        let g2 = (-&v1.1 << shift) / &det;
+
+        // Both coordinates of k in the basis are rounded by less than two,
+        // which bounds k1 and k2 by twice the sums of the basis coordinates.
+        let half_bits = (v1.0.abs() + v2.0.abs())
+            .max(v1.1.abs() + v2.1.abs())
+            .bits() as usize
+            + 1;
+
+        Self {
+            beta,
+            lambda,
+            order,
+            v1,
+            v2,
+            g1,
+            g2,
+            shift,
+            half_bits,
+        }
+    }
+
+    /// Split `k` into `k1 + k2 * lambda` modulo the order, and return the
+    /// magnitude and sign of `k1` and `k2` in that order.
+    ///
+    /// This is the arithmetic the decompose shaders run. Every product is
+    /// exact, so the signed integers only differ from the shaders' two's
+    /// complement limbs in representation.
+    pub fn decompose(&self, k: &BigUint) -> [(BigUint, bool); 2] {
+        // The shaders round the magnitude of g * k down, i.e. towards zero.
+        let round = |g: &BigInt| {
+            let c = BigInt::from((g.magnitude() * k) >> self.shift);
+            if g.is_negative() {
+                -c
+            } else {
+                c
+            }
+        };
+        let (c1, c2) = (round(&self.g1), round(&self.g2));
+        let k = BigInt::from(k.clone());
+        let k1 = &k - &c1 * &self.v1.0 - &c2 * &self.v2.0;
+        let k2 = -&c1 * &self.v1.1 - &c2 * &self.v2.1;
+        [k1, k2].map(|half| (half.magnitude().clone(), half.sign() == Sign::Minus))
+    }
+
+    /// Number of `GLV_LIMB_BITS`-bit limbs of the intermediates in the
+    /// shaders, enough to hold `g * k` for a scalar below `2^shift`
+    pub fn num_limbs(&self) -> usize {
+        let g_bits = self.g1.bits().max(self.g2.bits()) as usize;
+        (self.shift + g_bits).div_ceil(GLV_LIMB_BITS) + 1
+    }
+}
+
+/// Generate the GPU representation of `value` as a comma-separated list of
+/// `num_limbs` limbs of `GLV_LIMB_BITS` bits, in two's complement.
+pub fn gen_glv_limbs(value: &BigInt, num_limbs: usize) -> String {
+    let modulus = BigInt::from(1) << (num_limbs * GLV_LIMB_BITS);
+    let value = ((value % &modulus) + &modulus) % &modulus;
+    let mut bytes = value.magnitude().to_bytes_le();
+    bytes.resize(num_limbs * 2, 0);
+    bytes
+        .chunks(2)
+        .map(|limb| format!("{}u", u16::from_le_bytes([limb[0], limb[1]])))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -1,14 +1,17 @@
 use wgpu::{
-    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
-    BindGroupLayoutDescriptor, BindGroupLayoutEntry, Buffer, BufferAsyncError, BufferDescriptor,
-    BufferSlice, BufferUsages, CommandEncoder, ComputePipeline, ComputePipelineDescriptor, Device,
-    Features, Instance, Limits, MapMode, MemoryHints, PipelineCompilationOptions,
-    PipelineLayoutDescriptor, PowerPreference, Queue, ShaderModuleDescriptor, ShaderSource,
     util::{BufferInitDescriptor, DeviceExt},
+    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, Buffer, BufferAsyncError,
+    BufferBinding, BufferDescriptor, BufferSlice, BufferUsages, CommandEncoder, ComputePipeline,
+    ComputePipelineDescriptor, Device, ErrorFilter, Features, Instance, Limits, MapMode,
+    MemoryHints, PipelineCompilationOptions, PipelineLayoutDescriptor, PowerPreference, Queue,
+    ShaderModuleDescriptor, ShaderSource,
 };
 
+use crate::cuzk::error::MsmError;
+
 /// Get an adapter
-pub async fn get_adapter() -> Adapter {
+pub async fn get_adapter() -> Result<Adapter, MsmError> {
     let instance = Instance::default();
 
     // Request an adapter (the GPU) from the browser
@@ -19,11 +22,11 @@ pub async fn get_adapter() -> Adapter {
             force_fallback_adapter: false,
         })
         .await
-        .expect("No suitable GPU adapters found on the system!")
+        .ok_or(MsmError::NoAdapter)
 }
 
 /// Get a device
-pub async fn get_device(adapter: &Adapter) -> (Device, Queue) {
+pub async fn get_device(adapter: &Adapter) -> Result<(Device, Queue), MsmError> {
     let required_limits = Limits {
         max_buffer_size: adapter.limits().max_buffer_size,
         max_storage_buffer_binding_size: adapter.limits().max_storage_buffer_binding_size,
@@ -47,10 +50,25 @@ pub async fn get_device(adapter: &Adapter) -> (Device, Queue) {
             },
             None,
         )
-        .await
-        .expect("Could not create adapter for device");
+        .await?;
+
+    Ok((device, queue))
+}
 
-    (device, queue)
+/// Check that a storage buffer of `size` bytes can be created and bound on `device`
+pub fn check_storage_buffer_size(label: &str, device: &Device, size: u64) -> Result<(), MsmError> {
+    let limits = device.limits();
+    let limit = limits
+        .max_buffer_size
+        .min(limits.max_storage_buffer_binding_size as u64);
+    if size > limit {
+        return Err(MsmError::LimitsExceeded {
+            resource: label.to_string(),
+            requested: size,
+            limit,
+        });
+    }
+    Ok(())
 }
 
 /// Create a storage buffer
@@ -101,7 +119,7 @@ pub async fn read_from_gpu(
     queue: &Queue,
     mut encoder: CommandEncoder,
     storage_buffers: Vec<Buffer>,
-) -> Vec<Vec<u8>> {
+) -> Result<Vec<Vec<u8>>, MsmError> {
     let mut staging_buffers = Vec::new();
 
     for (i, storage_buffer) in storage_buffers.iter().enumerate() {
@@ -124,13 +142,13 @@ pub async fn read_from_gpu(
     let mut data = Vec::new();
     for staging_buffer in staging_buffers {
         let staging_slice = staging_buffer.slice(..);
-        let _buffer_future = map_buffer_async_browser(staging_slice, MapMode::Read).await;
+        map_buffer_async_browser(staging_slice, MapMode::Read).await?;
         device.poll(wgpu::Maintain::Wait);
         let result_data = staging_slice.get_mapped_range();
         data.push(result_data.to_vec());
     }
 
-    data
+    Ok(data)
 }
 
 /// Read data from the GPU for testing
@@ -194,9 +212,11 @@ pub fn create_bind_group_layout(
         .collect::<Vec<_>>();
     device.create_bind_group_layout(&BindGroupLayoutDescriptor {
         label,
-        entries: &[storage_buffer_read_only_entries,
+        entries: &[
+            storage_buffer_read_only_entries,
             storage_buffer_entries,
-            uniform_buffer_entries]
+            uniform_buffer_entries,
+        ]
         .concat(),
     })
 }
@@ -249,16 +269,35 @@ pub fn create_bind_group(
     device: &Device,
     bind_group_layout: &BindGroupLayout,
     buffers: Vec<&Buffer>,
+) -> BindGroup {
+    create_bind_group_with_bindings(
+        label,
+        device,
+        bind_group_layout,
+        buffers
+            .iter()
+            .map(|buffer| buffer.as_entire_buffer_binding())
+            .collect(),
+    )
+}
+
+/// Create a bind group over ranges of buffers. Offsets into storage buffers
+/// must be multiples of `min_storage_buffer_offset_alignment`.
+pub fn create_bind_group_with_bindings(
+    label: Option<&str>,
+    device: &Device,
+    bind_group_layout: &BindGroupLayout,
+    bindings: Vec<BufferBinding>,
 ) -> BindGroup {
     device.create_bind_group(&BindGroupDescriptor {
         label,
         layout: bind_group_layout,
-        entries: &buffers
-            .iter()
+        entries: &bindings
+            .into_iter()
             .enumerate()
-            .map(|(i, buffer)| BindGroupEntry {
+            .map(|(i, binding)| BindGroupEntry {
                 binding: i as u32,
-                resource: buffer.as_entire_binding(),
+                resource: BindingResource::Buffer(binding),
             })
             .collect::<Vec<_>>(),
     })
@@ -271,7 +310,11 @@ pub async fn create_compute_pipeline(
     bind_group_layout: &BindGroupLayout,
     code: &str,
     entry_point: &str,
-) -> ComputePipeline {
+) -> Result<ComputePipeline, MsmError> {
+    // Catch WGSL compilation and pipeline validation errors instead of
+    // letting wgpu's default handler panic.
+    device.push_error_scope(ErrorFilter::Validation);
+
     let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
         label,
         bind_group_layouts: &[bind_group_layout],
@@ -283,14 +326,19 @@ pub async fn create_compute_pipeline(
         source: ShaderSource::Wgsl(code.into()),
     });
 
-    device.create_compute_pipeline(&ComputePipelineDescriptor {
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
         label,
         layout: Some(&pipeline_layout),
         module: &module,
         entry_point: Some(entry_point),
         compilation_options: PipelineCompilationOptions::default(),
         cache: None,
-    })
+    });
+
+    match device.pop_error_scope().await {
+        Some(e) => Err(MsmError::Shader(e.to_string())),
+        None => Ok(pipeline),
+    }
 }
 
 /// Execute a compute pipeline
@@ -1,5 +1,15 @@
+#[cfg(feature = "arkworks")]
+pub mod ark;
+pub mod config;
+pub mod context;
+pub mod curve;
+pub mod error;
+pub mod glv;
 pub mod gpu;
 pub mod msm;
+pub mod partition;
+pub mod plan;
 pub mod shader_manager;
 pub mod test;
 pub mod utils;
+pub mod verify;
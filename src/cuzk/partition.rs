@@ -0,0 +1,84 @@
+use std::ops::Range;
+
+use wgpu::Limits;
+
+use crate::cuzk::curve::{num_subtasks, GpuCurve};
+use crate::cuzk::plan::{buffer_limit, MsmPlan, MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+
+/// Size in bytes of the largest per-input storage buffer the cuZK pipeline
+/// allocates for a padded input of `input_size` points and scalars, split into
+/// `chunk_size`-bit windows.
+///
+/// These are the packed points (two `C::COORD_BYTES` coordinates), one
+/// Montgomery coordinate (`C::coord_words()` u32 limbs), and the scalar chunks
+/// (one u32 per subtask).
+pub fn max_buffer_size_for_input<C: GpuCurve>(input_size: usize, chunk_size: usize) -> u64 {
+    let num_subtasks = num_subtasks(C::scalar_bits(), chunk_size);
+    max_buffer_size_for_terms::<C>(input_size, input_size, num_subtasks)
+}
+
+/// [`max_buffer_size_for_input`] for `input_size` packed points that are
+/// expanded into `num_terms` Montgomery points and scalar chunk columns.
+pub(crate) fn max_buffer_size_for_terms<C: GpuCurve>(
+    input_size: usize,
+    num_terms: usize,
+    num_subtasks: usize,
+) -> u64 {
+    let packed_points = input_size as u64 * (2 * C::COORD_BYTES) as u64;
+    let bytes_per_term = (C::coord_words() * 4).max(num_subtasks * 4);
+    packed_points.max(num_terms as u64 * bytes_per_term as u64)
+}
+
+/// Largest power-of-two input size for which some window size keeps the
+/// storage buffers for curve `C` within `limits`. With `Some(chunk_size)`,
+/// only that window size is tried, as for an [`MsmConfig`] that overrides it.
+///
+/// [`MsmConfig`]: crate::cuzk::config::MsmConfig
+pub fn max_partition_size<C: GpuCurve>(limits: &Limits, chunk_size: Option<usize>) -> usize {
+    largest_fitting(limits, chunk_size, |size, chunk_size| {
+        MsmPlan::new::<C>(size, chunk_size)
+    })
+}
+
+/// [`max_partition_size`] for passes that split the scalars with the GLV
+/// endomorphism of `C`, which hold twice as many points on the GPU. Returns
+/// `None` if `C` has none.
+pub fn max_glv_partition_size<C: GpuCurve>(
+    limits: &Limits,
+    chunk_size: Option<usize>,
+) -> Option<usize> {
+    C::glv()?;
+    Some(largest_fitting(limits, chunk_size, |size, chunk_size| {
+        MsmPlan::new_glv::<C>(size, chunk_size).unwrap()
+    }))
+}
+
+fn largest_fitting(
+    limits: &Limits,
+    chunk_size: Option<usize>,
+    plan: impl Fn(usize, usize) -> MsmPlan,
+) -> usize {
+    let chunk_sizes = chunk_size.map_or(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE, |c| c..=c);
+    let limit = buffer_limit(limits);
+    let mut size = 1usize << limit.max(1).ilog2().min(usize::BITS - 1);
+    while size > 1
+        && !chunk_sizes
+            .clone()
+            .any(|chunk_size| plan(size, chunk_size).fits(limits))
+    {
+        size >>= 1;
+    }
+    size
+}
+
+/// Split `len` inputs into consecutive ranges of at most `max_partition_size`.
+///
+/// Every range but the last has exactly `max_partition_size` elements, so that
+/// they all share one set of pipelines. Only the last one is padded.
+pub fn partition(len: usize, max_partition_size: usize) -> Vec<Range<usize>> {
+    assert!(max_partition_size > 0);
+    (0..len)
+        .step_by(max_partition_size)
+        .map(|start| start..(start + max_partition_size).min(len))
+        .collect()
+}
@@ -0,0 +1,200 @@
+use wgpu::Limits;
+
+use crate::cuzk::curve::{num_subtasks, GpuCurve};
+use crate::cuzk::error::MsmError;
+use crate::cuzk::glv::Glv;
+use crate::cuzk::partition::max_buffer_size_for_terms;
+
+/// Smallest window size the planner considers.
+pub const MIN_CHUNK_SIZE: usize = 2;
+
+/// Largest window size the planner considers. The decompose shaders extract a
+/// window from at most two 16-bit halves of the scalar.
+pub const MAX_CHUNK_SIZE: usize = 16;
+
+/// Relative costs of the work in one pass of the pipeline, used by
+/// [`MsmPlan::select`] to compare window sizes.
+///
+/// Only the ratios matter. The defaults count point additions: every term is
+/// added into one bucket per window, and the running sum reduction adds every
+/// bucket twice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CostModel {
+    /// Cost of adding one term into its bucket in the SMVP stage
+    pub bucket_add: f64,
+    /// Cost of reducing one bucket in the BPR stage
+    pub bucket_reduce: f64,
+    /// Fixed cost of each window, e.g. the dispatch overhead of its SMVP and
+    /// BPR invocations
+    pub window: f64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            bucket_add: 1.0,
+            bucket_reduce: 2.0,
+            window: 0.0,
+        }
+    }
+}
+
+impl CostModel {
+    /// Estimated cost of running `plan`
+    pub fn cost(&self, plan: &MsmPlan) -> f64 {
+        let per_window = plan.num_terms() as f64 * self.bucket_add
+            + plan.num_buckets() as f64 * self.bucket_reduce
+            + self.window;
+        plan.num_subtasks as f64 * per_window
+    }
+}
+
+/// The shape of one pass of the cuZK pipeline over a padded input.
+///
+/// The windows cover the scalar bits plus the two bits of headroom the signed
+/// digits need (see [`num_subtasks`]). The window size does not have to
+/// divide that, in which case the last window is shorter.
+///
+/// With GLV, every scalar is split into two halves of `Glv::half_bits` bits,
+/// one for the point and one for its image under the endomorphism, so the
+/// pass runs over twice as many terms in about half as many windows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MsmPlan {
+    /// Number of points and scalars after padding
+    pub input_size: usize,
+    /// Window size in bits
+    pub chunk_size: usize,
+    /// Number of windows
+    pub num_subtasks: usize,
+    /// Number of bits in the last window, at most `chunk_size`
+    pub last_chunk_size: usize,
+    /// Size in bytes of the largest storage buffer the pass allocates
+    pub max_buffer_size: u64,
+    /// Whether scalars are split with the GLV endomorphism of the curve
+    pub glv: bool,
+}
+
+impl MsmPlan {
+    /// Plan a pass over `input_size` inputs of curve `C` with a fixed window
+    /// size.
+    pub fn new<C: GpuCurve>(input_size: usize, chunk_size: usize) -> Self {
+        Self::build::<C>(input_size, chunk_size, None)
+    }
+
+    /// Plan a pass over `input_size` inputs of curve `C` with a fixed window
+    /// size, splitting the scalars with the GLV endomorphism of `C`. Returns
+    /// `None` if `C` has none.
+    pub fn new_glv<C: GpuCurve>(input_size: usize, chunk_size: usize) -> Option<Self> {
+        C::glv().map(|glv| Self::build::<C>(input_size, chunk_size, Some(&glv)))
+    }
+
+    fn build<C: GpuCurve>(input_size: usize, chunk_size: usize, glv: Option<&Glv>) -> Self {
+        let scalar_bits = glv.map_or(C::scalar_bits(), |glv| glv.half_bits);
+        let num_terms = if glv.is_some() {
+            2 * input_size
+        } else {
+            input_size
+        };
+        let num_subtasks = num_subtasks(scalar_bits, chunk_size);
+        let last_chunk_size = scalar_bits + 2 - (num_subtasks - 1) * chunk_size;
+        let num_buckets = 1usize << (chunk_size - 1);
+        let bucket_sum_size = (num_buckets * C::coord_words() * 4 * num_subtasks) as u64;
+        Self {
+            input_size,
+            chunk_size,
+            num_subtasks,
+            last_chunk_size,
+            max_buffer_size: max_buffer_size_for_terms::<C>(input_size, num_terms, num_subtasks)
+                .max(bucket_sum_size),
+            glv: glv.is_some(),
+        }
+    }
+
+    /// Pick the window size with the lowest cost under `cost_model` among
+    /// those whose buffers fit within `limits`. Ties go to the smaller
+    /// window.
+    pub fn select<C: GpuCurve>(
+        input_size: usize,
+        limits: &Limits,
+        cost_model: &CostModel,
+    ) -> Result<Self, MsmError> {
+        let plans = (MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE)
+            .map(|chunk_size| Self::new::<C>(input_size, chunk_size))
+            .collect::<Vec<_>>();
+        Self::select_among(input_size, &plans, limits, cost_model)
+    }
+
+    /// [`MsmPlan::select`] among the plans that split the scalars with the
+    /// GLV endomorphism of `C`. Returns [`MsmError::InvalidConfig`] if `C`
+    /// has none.
+    pub fn select_glv<C: GpuCurve>(
+        input_size: usize,
+        limits: &Limits,
+        cost_model: &CostModel,
+    ) -> Result<Self, MsmError> {
+        let glv = C::glv().ok_or_else(|| {
+            MsmError::InvalidConfig("the curve has no GLV endomorphism".to_string())
+        })?;
+        let plans = (MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE)
+            .map(|chunk_size| Self::build::<C>(input_size, chunk_size, Some(&glv)))
+            .collect::<Vec<_>>();
+        Self::select_among(input_size, &plans, limits, cost_model)
+    }
+
+    fn select_among(
+        input_size: usize,
+        plans: &[Self],
+        limits: &Limits,
+        cost_model: &CostModel,
+    ) -> Result<Self, MsmError> {
+        plans
+            .iter()
+            .filter(|plan| plan.fits(limits))
+            .min_by(|a, b| cost_model.cost(a).total_cmp(&cost_model.cost(b)))
+            .copied()
+            .ok_or_else(|| MsmError::LimitsExceeded {
+                resource: format!("MSM of {input_size} inputs"),
+                requested: plans.iter().map(|plan| plan.max_buffer_size).min().unwrap(),
+                limit: buffer_limit(limits),
+            })
+    }
+
+    /// [`MsmPlan::select`] under the WebGPU default limits and the default
+    /// cost model
+    pub fn for_input_size<C: GpuCurve>(input_size: usize) -> Result<Self, MsmError> {
+        Self::select::<C>(input_size, &Limits::default(), &CostModel::default())
+    }
+
+    /// Number of point and scalar pairs the windows are taken over, i.e.
+    /// twice the input size with GLV
+    pub fn num_terms(&self) -> usize {
+        if self.glv {
+            2 * self.input_size
+        } else {
+            self.input_size
+        }
+    }
+
+    /// Number of columns of the scalar chunk matrix, i.e. `2^chunk_size`
+    pub fn num_columns(&self) -> usize {
+        1 << self.chunk_size
+    }
+
+    /// Number of buckets per window. The signed digits need only half of the
+    /// columns.
+    pub fn num_buckets(&self) -> usize {
+        self.num_columns() / 2
+    }
+
+    /// Whether every storage buffer of the pass fits within `limits`
+    pub fn fits(&self, limits: &Limits) -> bool {
+        self.max_buffer_size <= buffer_limit(limits)
+    }
+}
+
+/// Largest storage buffer that can be both allocated and bound under `limits`
+pub(crate) fn buffer_limit(limits: &Limits) -> u64 {
+    limits
+        .max_buffer_size
+        .min(limits.max_storage_buffer_binding_size as u64)
+}
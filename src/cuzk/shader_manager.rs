@@ -1,10 +1,22 @@
+use halo2curves::bn256::G1Affine;
 use handlebars::Handlebars;
+use num_bigint::BigUint;
+use num_traits::Signed;
 use once_cell::sync::Lazy;
 use serde_json::json;
 
 /// Decompose scalars shader
 pub static DECOMPOSE_SCALARS_SHADER: Lazy<String> =
     Lazy::new(|| include_str!("wgsl/cuzk/decompose_scalars.template.wgsl").to_string());
+/// Convert point coordinates shader, the point half of the decompose shader
+pub static CONVERT_POINT_COORDS_SHADER: Lazy<String> =
+    Lazy::new(|| include_str!("wgsl/cuzk/convert_point_coords.template.wgsl").to_string());
+/// Decompose scalars only shader, the scalar half of the decompose shader
+pub static DECOMPOSE_SCALARS_ONLY_SHADER: Lazy<String> =
+    Lazy::new(|| include_str!("wgsl/cuzk/decompose_scalars_only.template.wgsl").to_string());
+/// GLV scalar decomposition functions
+pub static GLV_FUNCS: Lazy<String> =
+    Lazy::new(|| include_str!("wgsl/cuzk/glv.template.wgsl").to_string());
 /// Extract word from bytes least significant end shader
 pub static EXTRACT_WORD_FROM_BYTES_LE_FUNCS: Lazy<String> =
     Lazy::new(|| include_str!("wgsl/cuzk/extract_word_from_bytes_le.template.wgsl").to_string());
@@ -17,9 +29,18 @@ pub static BARRETT_FUNCS: Lazy<String> =
 /// Curve operations shader
 pub static EC_FUNCS: Lazy<String> =
     Lazy::new(|| include_str!("wgsl/curve/ec.template.wgsl").to_string());
+/// Curve operations shader for curves over the quadratic extension field
+pub static EC2_FUNCS: Lazy<String> =
+    Lazy::new(|| include_str!("wgsl/curve/ec2.template.wgsl").to_string());
+/// Twisted Edwards curve operations shader
+pub static TWISTED_EC_FUNCS: Lazy<String> =
+    Lazy::new(|| include_str!("wgsl/curve/twisted_ec.template.wgsl").to_string());
 /// Field operations shader
 pub static FIELD_FUNCS: Lazy<String> =
     Lazy::new(|| include_str!("wgsl/field/field.template.wgsl").to_string());
+/// Quadratic extension field operations shader
+pub static FIELD2_FUNCS: Lazy<String> =
+    Lazy::new(|| include_str!("wgsl/field/field2.template.wgsl").to_string());
 /// Big integer operations shader
 pub static BIGINT_FUNCS: Lazy<String> =
     Lazy::new(|| include_str!("wgsl/bigint/bigint.template.wgsl").to_string());
@@ -36,6 +57,9 @@ pub static SMVP_SHADER: Lazy<String> =
 /// Batch product reduction shader
 pub static BPR_SHADER: Lazy<String> =
     Lazy::new(|| include_str!("wgsl/cuzk/bpr.template.wgsl").to_string());
+/// Window sum and Horner shader
+pub static HORNER_SHADER: Lazy<String> =
+    Lazy::new(|| include_str!("wgsl/cuzk/horner.template.wgsl").to_string());
 /// Test field shader
 pub static TEST_FIELD_SHADER: Lazy<String> =
     Lazy::new(|| include_str!("wgsl/test/test_field.wgsl").to_string());
@@ -43,13 +67,38 @@ pub static TEST_FIELD_SHADER: Lazy<String> =
 pub static TEST_POINT_SHADER: Lazy<String> =
     Lazy::new(|| include_str!("wgsl/test/test_point.wgsl").to_string());
 
-use crate::cuzk::utils::{calc_bitwidth, gen_mu_limbs, gen_one_limbs, gen_p_limbs, gen_rinv_limbs};
+use crate::cuzk::curve::{CurveModel, GpuCurve};
+use crate::cuzk::error::MsmError;
+use crate::cuzk::glv::{gen_glv_limbs, Glv, GLV_LIMB_BITS};
+use crate::cuzk::utils::{
+    calc_bitwidth, gen_a_limbs, gen_b_limbs, gen_beta_limbs, gen_d_limbs, gen_mu_limbs,
+    gen_one_limbs, gen_p_limbs, gen_rinv_limbs,
+};
 
-use super::{
-    msm::{P, PARAMS},
-    utils::{gen_p_limbs_plus_one, gen_r_limbs, gen_zero_limbs},
+use super::utils::{
+    compute_misc_params, compute_sqrt_params, gen_exponent_limbs, gen_mont_one_limbs,
+    gen_p_limbs_plus_one, gen_p_minus_two_limbs, gen_r_limbs, gen_sqrt_z_limbs, gen_zero_limbs,
 };
 
+/// Curve parameters the shaders are specialised on
+struct CurveParams {
+    p: BigUint,
+    /// Base field components of the curve constant `a`
+    a: Vec<BigUint>,
+    /// Base field components of the curve constant `b`
+    b: Vec<BigUint>,
+    /// The twisted Edwards constant `d`
+    d: BigUint,
+    model: CurveModel,
+    coord_bytes: usize,
+    ext_degree: usize,
+    /// Montgomery radix of the raw coordinates uploaded by
+    /// `points_to_raw_bytes`, if the curve writes them in Montgomery form
+    raw_radix: Option<BigUint>,
+    /// The GLV endomorphism of the curve, if it has one
+    glv: Option<Glv>,
+}
+
 /// Shader manager
 pub struct ShaderManager {
     word_size: usize,
@@ -59,161 +108,416 @@ pub struct ShaderManager {
     index_shift: usize,
     p_limbs: String,
     p_limbs_plus_one: String,
+    p_minus_two_limbs: String,
+    p_minus_one_half_limbs: String,
+    sqrt_s: u32,
+    sqrt_exponent_limbs: String,
+    sqrt_z_limbs: String,
     zero_limbs: String,
     one_limbs: String,
     r_limbs: String,
+    raw_to_mont_limbs: Option<String>,
+    raw_input: bool,
+    glv: Option<Glv>,
+    glv_input: bool,
+    batch_affine: bool,
+    mont_one_limbs: String,
     slack: usize,
     w_mask: usize,
     n0: u32,
     mu_limbs: String,
     rinv_limbs: String,
+    a_limbs: String,
+    a_c1_limbs: String,
+    b_limbs: String,
+    b_c1_limbs: String,
+    a_is_zero: bool,
+    a_is_minus_three: bool,
+    a_is_minus_one: bool,
+    d_limbs: String,
+    is_ext2: bool,
+    is_edwards: bool,
+    num_halves: usize,
+    coord_u32s: usize,
+    component_u32s: usize,
 }
 
 impl ShaderManager {
-    /// Create a new shader manager
+    /// Create a new shader manager for BN254
     pub fn new(word_size: usize, chunk_size: usize, input_size: usize) -> Self {
-        let p_bit_length = calc_bitwidth(&P);
-        let num_words = PARAMS.num_words;
-        let r = PARAMS.r.clone();
-        let rinv = PARAMS.rinv.clone();
-        println!("P: {P:?}");
-        println!("P limbs: {}", gen_p_limbs(&P, num_words, word_size));
+        Self::for_curve::<G1Affine>(word_size, chunk_size, input_size)
+    }
+
+    /// Create a new shader manager for the curve `C`
+    pub fn for_curve<C: GpuCurve>(word_size: usize, chunk_size: usize, input_size: usize) -> Self {
+        let curve = CurveParams {
+            p: C::base_modulus(),
+            a: components::<C>(&C::curve_a()),
+            b: components::<C>(&C::curve_b()),
+            d: C::edwards_d(),
+            model: C::MODEL,
+            coord_bytes: C::COORD_BYTES,
+            ext_degree: C::EXT_DEGREE,
+            raw_radix: C::RAW_MONTGOMERY
+                .then(|| BigUint::from(1u32) << (8 * C::COORD_BYTES / C::EXT_DEGREE)),
+            glv: C::glv(),
+        };
+        Self::with_curve_params(curve, word_size, chunk_size, input_size)
+    }
+
+    /// Create a new shader manager for the twisted Edwards curve
+    /// `ax^2 + y^2 = 1 + dx^2y^2` over the prime field of modulus `p`, for
+    /// curves that have no `GpuCurve` implementation.
+    pub fn for_twisted_edwards(
+        p: &BigUint,
+        a: &BigUint,
+        d: &BigUint,
+        word_size: usize,
+        chunk_size: usize,
+        input_size: usize,
+    ) -> Self {
+        let curve = CurveParams {
+            p: p.clone(),
+            a: vec![a % p],
+            b: vec![BigUint::ZERO],
+            d: d % p,
+            model: CurveModel::TwistedEdwards,
+            coord_bytes: calc_bitwidth(p).div_ceil(32) * 4,
+            ext_degree: 1,
+            raw_radix: None,
+            glv: None,
+        };
+        Self::with_curve_params(curve, word_size, chunk_size, input_size)
+    }
+
+    /// Read the point coordinates as the raw Montgomery form written by
+    /// `points_to_raw_bytes` instead of the canonical form written by
+    /// `points_to_bytes`. This saves the conversion out of Montgomery form
+    /// on the CPU, and costs nothing on the GPU, where the coordinates are
+    /// multiplied by a different constant.
+    ///
+    /// Curves without `GpuCurve::RAW_MONTGOMERY`, and shader managers not
+    /// created by `for_curve`, keep reading canonical coordinates, which is
+    /// also what `points_to_raw_bytes` falls back to for them.
+    pub fn set_raw_montgomery_input(&mut self, raw: bool) {
+        self.raw_input = raw;
+    }
+
+    /// Split every scalar into two halves with the GLV endomorphism of the
+    /// curve in the decompose shaders, which then also map every point
+    /// through the endomorphism into the second half of the point buffers.
+    ///
+    /// Curves without `GpuCurve::glv`, and shader managers not created by
+    /// `for_curve`, ignore this.
+    pub fn set_glv(&mut self, glv: bool) {
+        self.glv_input = glv;
+    }
+
+    /// Add the `batch_affine` entry point to the SMVP shader, which sums the
+    /// buckets in affine coordinates and shares one field inversion per step
+    /// across the workgroup with Montgomery's trick.
+    ///
+    /// Only short Weierstrass curves over the base field support it. Others
+    /// ignore this, and their SMVP shader only has the `main` entry point.
+    pub fn set_batch_affine(&mut self, batch_affine: bool) {
+        self.batch_affine = batch_affine;
+    }
+
+    /// Whether the SMVP shader gets the `batch_affine` entry point
+    fn batch_affine(&self) -> bool {
+        self.batch_affine && !self.is_ext2 && !self.is_edwards
+    }
+
+    /// The endomorphism the decompose shaders split scalars with, if enabled
+    fn glv(&self) -> Option<&Glv> {
+        self.glv.as_ref().filter(|_| self.glv_input)
+    }
+
+    /// The curve constants shared by every shader that includes the field
+    /// and curve functions
+    fn curve_data(&self) -> serde_json::Value {
+        json!({
+            "word_size": self.word_size,
+            "num_words": self.num_words,
+            "p_limbs": self.p_limbs,
+            "p_limbs_plus_one": self.p_limbs_plus_one,
+            "p_minus_two_limbs": self.p_minus_two_limbs,
+            "p_minus_one_half_limbs": self.p_minus_one_half_limbs,
+            "sqrt_s": self.sqrt_s,
+            "sqrt_exponent_limbs": self.sqrt_exponent_limbs,
+            "sqrt_z_limbs": self.sqrt_z_limbs,
+            "zero_limbs": self.zero_limbs,
+            "one_limbs": self.one_limbs,
+            "r_limbs": self.r_limbs,
+            "w_mask": self.w_mask,
+            "num_words_mul_two": self.num_words * 2,
+            "num_words_plus_one": self.num_words + 1,
+            "n0": self.n0,
+            "mu_limbs": self.mu_limbs,
+            "slack": self.slack,
+            "rinv_limbs": self.rinv_limbs,
+            "a_limbs": self.a_limbs,
+            "a_c1_limbs": self.a_c1_limbs,
+            "b_limbs": self.b_limbs,
+            "b_c1_limbs": self.b_c1_limbs,
+            "a_is_zero": self.a_is_zero,
+            "a_is_minus_three": self.a_is_minus_three,
+            "a_is_minus_one": self.a_is_minus_one,
+            "d_limbs": self.d_limbs,
+            "mont_one_limbs": self.mont_one_limbs,
+            "is_edwards": self.is_edwards,
+            "is_ext2": self.is_ext2,
+            "num_halves": self.num_halves,
+            "coord_u32s": self.coord_u32s,
+            "component_u32s": self.component_u32s,
+        })
+    }
+
+    /// Add the GLV constants to the data of a decompose shader
+    fn add_glv_data(&self, data: &mut serde_json::Value) {
+        let data = data.as_object_mut().unwrap();
+        data.insert("glv".to_string(), json!(self.glv().is_some()));
+        let Some(glv) = self.glv() else {
+            return;
+        };
+        let num_limbs = glv.num_limbs();
+        let limbs = |value| gen_glv_limbs(value, num_limbs);
+        data.extend([
+            ("glv_limbs".to_string(), json!(num_limbs)),
+            (
+                "glv_shift_limbs".to_string(),
+                json!(glv.shift / GLV_LIMB_BITS),
+            ),
+            ("glv_g1_limbs".to_string(), json!(limbs(&glv.g1.abs()))),
+            ("glv_g1_negative".to_string(), json!(glv.g1.is_negative())),
+            ("glv_g2_limbs".to_string(), json!(limbs(&glv.g2.abs()))),
+            ("glv_g2_negative".to_string(), json!(glv.g2.is_negative())),
+            ("glv_a1_limbs".to_string(), json!(limbs(&glv.v1.0))),
+            ("glv_b1_limbs".to_string(), json!(limbs(&glv.v1.1))),
+            ("glv_a2_limbs".to_string(), json!(limbs(&glv.v2.0))),
+            ("glv_b2_limbs".to_string(), json!(limbs(&glv.v2.1))),
+            (
+                "beta_limbs".to_string(),
+                json!(gen_beta_limbs(&glv.beta, self.num_words, self.word_size)),
+            ),
+        ]);
+    }
+
+    /// The constant that takes an uploaded coordinate to Montgomery form
+    fn to_mont_limbs(&self) -> &str {
+        match &self.raw_to_mont_limbs {
+            Some(limbs) if self.raw_input => limbs,
+            _ => &self.r_limbs,
+        }
+    }
+
+    fn with_curve_params(
+        curve: CurveParams,
+        word_size: usize,
+        chunk_size: usize,
+        input_size: usize,
+    ) -> Self {
+        let CurveParams {
+            p,
+            a,
+            b,
+            d,
+            model,
+            coord_bytes,
+            ext_degree,
+            raw_radix,
+            glv,
+        } = curve;
+        let params = compute_misc_params(&p, word_size);
+        let p_bit_length = calc_bitwidth(&p);
+        let num_words = params.num_words;
+        let r = params.r.clone();
+        let rinv = params.rinv.clone();
+        println!("P: {p:?}");
+        println!("P limbs: {}", gen_p_limbs(&p, num_words, word_size));
         println!("W_MASK: {:?}", (1 << word_size) - 1);
         println!("R limbs: {}", gen_r_limbs(&r, num_words, word_size));
+        let sqrt_params = compute_sqrt_params(&p);
+        let component_bytes = coord_bytes / ext_degree;
+        let gen_c1_limbs = |c: &[BigUint], gen: fn(&BigUint, usize, usize) -> String| {
+            c.get(1)
+                .map(|c1| gen(&(c1 * &r % &p), num_words, word_size))
+                .unwrap_or_default()
+        };
         Self {
             word_size,
             chunk_size,
             input_size,
             num_words,
             index_shift: 1 << (chunk_size - 1),
-            p_limbs: gen_p_limbs(&P, num_words, word_size),
-            p_limbs_plus_one: gen_p_limbs_plus_one(&P, num_words, word_size),
+            p_limbs: gen_p_limbs(&p, num_words, word_size),
+            p_limbs_plus_one: gen_p_limbs_plus_one(&p, num_words, word_size),
+            p_minus_two_limbs: gen_p_minus_two_limbs(&p, num_words, word_size),
+            p_minus_one_half_limbs: gen_exponent_limbs(&((&p - 1u32) >> 1), num_words, word_size),
+            sqrt_s: sqrt_params.s,
+            sqrt_exponent_limbs: gen_exponent_limbs(
+                &((&sqrt_params.q - 1u32) >> 1),
+                num_words,
+                word_size,
+            ),
+            sqrt_z_limbs: gen_sqrt_z_limbs(&(&sqrt_params.z * &r % &p), num_words, word_size),
             zero_limbs: gen_zero_limbs(num_words),
             one_limbs: gen_one_limbs(num_words),
             slack: num_words * word_size - p_bit_length,
             w_mask: (1 << word_size) - 1,
-            n0: PARAMS.n0,
+            n0: params.n0,
             r_limbs: gen_r_limbs(&r, num_words, word_size),
-            mu_limbs: gen_mu_limbs(&P, num_words, word_size),
+            // Multiplying a raw coordinate xR' by R / R' gives xR.
+            raw_to_mont_limbs: raw_radix.map(|raw_radix| {
+                let raw_radix_inv = raw_radix.modinv(&p).expect("Radix not invertible");
+                gen_r_limbs(&(&r * raw_radix_inv % &p), num_words, word_size)
+            }),
+            raw_input: false,
+            glv,
+            glv_input: false,
+            batch_affine: false,
+            mont_one_limbs: gen_mont_one_limbs(&r, num_words, word_size),
+            mu_limbs: gen_mu_limbs(&p, num_words, word_size),
             rinv_limbs: gen_rinv_limbs(&rinv, num_words, word_size),
+            a_limbs: gen_a_limbs(&(&a[0] * &r % &p), num_words, word_size),
+            a_c1_limbs: gen_c1_limbs(&a, gen_a_limbs),
+            b_limbs: gen_b_limbs(&(&b[0] * &r % &p), num_words, word_size),
+            b_c1_limbs: gen_c1_limbs(&b, gen_b_limbs),
+            d_limbs: gen_d_limbs(&(&d * &r % &p), num_words, word_size),
+            a_is_zero: a.iter().all(|c| *c == BigUint::ZERO),
+            a_is_minus_three: a.len() == 1 && a[0] == &p - 3u32,
+            a_is_minus_one: a.len() == 1 && a[0] == &p - 1u32,
+            is_ext2: ext_degree == 2,
+            is_edwards: model == CurveModel::TwistedEdwards,
+            // Coordinate components and scalars are both unpacked into 16-bit
+            // halves, so the arrays must fit the larger of the two. Scalars
+            // are always 32 bytes.
+            num_halves: component_bytes.max(32) / 2,
+            coord_u32s: coord_bytes / 4,
+            component_u32s: component_bytes / 4,
+        }
+    }
+
+    /// Curve operations for the coordinate field of the curve
+    fn ec_funcs(&self) -> &'static str {
+        if self.is_edwards {
+            TWISTED_EC_FUNCS.as_str()
+        } else if self.is_ext2 {
+            EC2_FUNCS.as_str()
+        } else {
+            EC_FUNCS.as_str()
         }
     }
 
     /// Generate the transpose shader
-    pub fn gen_transpose_shader(&self, workgroup_size: usize) -> String {
+    pub fn gen_transpose_shader(&self, workgroup_size: usize) -> Result<String, MsmError> {
         let mut handlebars = Handlebars::new();
-        handlebars
-            .register_template_string("transpose", TRANSPOSE_SHADER.as_str())
-            .unwrap();
+        handlebars.register_template_string("transpose", TRANSPOSE_SHADER.as_str())?;
         let data = json!({
             "workgroup_size": workgroup_size,
         });
-        handlebars.render("transpose", &data).unwrap()
+        Ok(handlebars.render("transpose", &data)?)
     }
 
     /// Generate the sparse matrix-vector product shader
-    pub fn gen_smvp_shader(&self, workgroup_size: usize, num_csr_cols: usize) -> String {
+    pub fn gen_smvp_shader(
+        &self,
+        workgroup_size: usize,
+        num_csr_cols: usize,
+    ) -> Result<String, MsmError> {
         println!("num_csr_cols: {num_csr_cols:?}");
         println!("workgroup_size: {workgroup_size:?}");
         let mut handlebars = Handlebars::new();
-        handlebars
-            .register_template_string("smvp", SMVP_SHADER.as_str())
-            .unwrap();
-
-        handlebars
-            .register_template_string("structs", STRUCTS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("ec_funcs", EC_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("field_funcs", FIELD_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string(
-                "montgomery_product_funcs",
-                MONTGOMERY_PRODUCT_FUNCS.as_str(),
-            )
-            .unwrap();
-        handlebars
-            .register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())
-            .unwrap();
+        handlebars.register_template_string("smvp", SMVP_SHADER.as_str())?;
 
-        let data = json!({
-            "word_size": self.word_size,
-            "num_words": self.num_words,
-            "num_columns": num_csr_cols,
-            "workgroup_size": workgroup_size,
-            "n0": self.n0,
-            "p_limbs": self.p_limbs,
-            "p_limbs_plus_one": self.p_limbs_plus_one,
-            "zero_limbs": self.zero_limbs,
-            "one_limbs": self.one_limbs,
-            "r_limbs": self.r_limbs,
-            "w_mask": self.w_mask,
-            "index_shift": self.index_shift,
-            "half_num_columns": num_csr_cols / 2,
-            "num_words_mul_two": self.num_words * 2,
-            "num_words_plus_one": self.num_words + 1,
-            "mu_limbs": self.mu_limbs,
-            "slack": self.slack,
-            "rinv_limbs": self.rinv_limbs,
-            "input_size": self.input_size,
-        });
-        handlebars.render("smvp", &data).unwrap()
+        handlebars.register_template_string("structs", STRUCTS.as_str())?;
+        handlebars.register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())?;
+        handlebars.register_template_string("ec_funcs", self.ec_funcs())?;
+        handlebars.register_template_string("field2_funcs", FIELD2_FUNCS.as_str())?;
+        handlebars.register_template_string("field_funcs", FIELD_FUNCS.as_str())?;
+        handlebars.register_template_string(
+            "montgomery_product_funcs",
+            MONTGOMERY_PRODUCT_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())?;
+
+        let mut data = self.curve_data();
+        merge_data(
+            &mut data,
+            json!({
+                "num_columns": num_csr_cols,
+                "workgroup_size": workgroup_size,
+                "index_shift": self.index_shift,
+                "half_num_columns": num_csr_cols / 2,
+                "input_size": self.input_size,
+                "batch_affine": self.batch_affine(),
+            }),
+        );
+        Ok(handlebars.render("smvp", &data)?)
     }
 
     /// Generate the batch product reduction shader
-    pub fn gen_bpr_shader(&self, workgroup_size: usize) -> String {
+    pub fn gen_bpr_shader(&self, workgroup_size: usize) -> Result<String, MsmError> {
         let mut handlebars = Handlebars::new();
-        handlebars
-            .register_template_string("bpr", BPR_SHADER.as_str())
-            .unwrap();
-
-        handlebars
-            .register_template_string("structs", STRUCTS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("ec_funcs", EC_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("field_funcs", FIELD_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string(
-                "montgomery_product_funcs",
-                MONTGOMERY_PRODUCT_FUNCS.as_str(),
-            )
-            .unwrap();
-        handlebars
-            .register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())
-            .unwrap();
-        let data = json!({
-            "workgroup_size": workgroup_size,
-            "word_size": self.word_size,
-            "num_words": self.num_words,
-            "n0": self.n0,
-            "p_limbs": self.p_limbs,
-            "p_limbs_plus_one": self.p_limbs_plus_one,
-            "zero_limbs": self.zero_limbs,
-            "one_limbs": self.one_limbs,
-            "r_limbs": self.r_limbs,
-            "w_mask": self.w_mask,
-            "index_shift": self.index_shift,
-            "num_words_mul_two": self.num_words * 2,
-            "num_words_plus_one": self.num_words + 1,
-            "mu_limbs": self.mu_limbs,
-            "slack": self.slack,
-            "rinv_limbs": self.rinv_limbs,
-            "input_size": self.input_size,
-        });
-        handlebars.render("bpr", &data).unwrap()
+        handlebars.register_template_string("bpr", BPR_SHADER.as_str())?;
+
+        handlebars.register_template_string("structs", STRUCTS.as_str())?;
+        handlebars.register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())?;
+        handlebars.register_template_string("ec_funcs", self.ec_funcs())?;
+        handlebars.register_template_string("field2_funcs", FIELD2_FUNCS.as_str())?;
+        handlebars.register_template_string("field_funcs", FIELD_FUNCS.as_str())?;
+        handlebars.register_template_string(
+            "montgomery_product_funcs",
+            MONTGOMERY_PRODUCT_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())?;
+        let mut data = self.curve_data();
+        merge_data(
+            &mut data,
+            json!({
+                "workgroup_size": workgroup_size,
+                "index_shift": self.index_shift,
+                "input_size": self.input_size,
+            }),
+        );
+        Ok(handlebars.render("bpr", &data)?)
+    }
+
+    /// Generate the shader that sums the `num_g_points` g points of each
+    /// subtask and combines the window sums with Horner's method
+    pub fn gen_horner_shader(
+        &self,
+        workgroup_size: usize,
+        num_subtasks: usize,
+        num_g_points: usize,
+    ) -> Result<String, MsmError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_template_string("horner", HORNER_SHADER.as_str())?;
+
+        handlebars.register_template_string("structs", STRUCTS.as_str())?;
+        handlebars.register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())?;
+        handlebars.register_template_string("ec_funcs", self.ec_funcs())?;
+        handlebars.register_template_string("field2_funcs", FIELD2_FUNCS.as_str())?;
+        handlebars.register_template_string("field_funcs", FIELD_FUNCS.as_str())?;
+        handlebars.register_template_string(
+            "montgomery_product_funcs",
+            MONTGOMERY_PRODUCT_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())?;
+        let mut data = self.curve_data();
+        merge_data(
+            &mut data,
+            json!({
+                "workgroup_size": workgroup_size,
+                "num_subtasks": num_subtasks,
+                "chunk_size": self.chunk_size,
+                "num_g_points": num_g_points,
+                "num_coords": if self.is_edwards { 4 } else { 3 },
+                "index_shift": self.index_shift,
+                "input_size": self.input_size,
+            }),
+        );
+        Ok(handlebars.render("horner", &data)?)
     }
 
     /// Generate the decompose scalars shader
@@ -223,155 +527,165 @@ impl ShaderManager {
         num_y_workgroups: usize,
         num_subtasks: usize,
         num_columns: usize,
-    ) -> String {
+    ) -> Result<String, MsmError> {
         println!("num_columns: {num_columns:?}");
         println!("num_y_workgroups: {num_y_workgroups:?}");
         println!("num_subtasks: {num_subtasks:?}");
         println!("workgroup_size: {workgroup_size:?}");
         let mut handlebars = Handlebars::new();
-        handlebars
-            .register_template_string("decomp_scalars", DECOMPOSE_SCALARS_SHADER.as_str())
-            .unwrap();
-
-        handlebars
-            .register_template_string("structs", STRUCTS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("field_funcs", FIELD_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string(
-                "montgomery_product_funcs",
-                MONTGOMERY_PRODUCT_FUNCS.as_str(),
-            )
-            .unwrap();
-        handlebars
-            .register_template_string(
-                "extract_word_from_bytes_le_funcs",
-                EXTRACT_WORD_FROM_BYTES_LE_FUNCS.as_str(),
-            )
-            .unwrap();
-        handlebars
-            .register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())
-            .unwrap();
-        let data = json!({
+        handlebars.register_template_string("decomp_scalars", DECOMPOSE_SCALARS_SHADER.as_str())?;
+
+        handlebars.register_template_string("structs", STRUCTS.as_str())?;
+        handlebars.register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())?;
+        handlebars.register_template_string("field_funcs", FIELD_FUNCS.as_str())?;
+        handlebars.register_template_string(
+            "montgomery_product_funcs",
+            MONTGOMERY_PRODUCT_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string(
+            "extract_word_from_bytes_le_funcs",
+            EXTRACT_WORD_FROM_BYTES_LE_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())?;
+        handlebars.register_template_string("glv_funcs", GLV_FUNCS.as_str())?;
+        let mut data = self.curve_data();
+        merge_data(
+            &mut data,
+            json!({
+                "workgroup_size": workgroup_size,
+                "chunk_size": self.chunk_size,
+                "num_y_workgroups": num_y_workgroups,
+                "num_subtasks": num_subtasks,
+                "num_columns": num_columns,
+                "index_shift": self.index_shift,
+                "to_mont_limbs": self.to_mont_limbs(),
+            }),
+        );
+        self.add_glv_data(&mut data);
+        Ok(handlebars.render("decomp_scalars", &data)?)
+    }
+
+    /// Generate the shader that converts points to Montgomery form without
+    /// decomposing any scalars
+    pub fn gen_convert_point_coords_shader(
+        &self,
+        workgroup_size: usize,
+        num_y_workgroups: usize,
+    ) -> Result<String, MsmError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_template_string(
+            "convert_point_coords",
+            CONVERT_POINT_COORDS_SHADER.as_str(),
+        )?;
+
+        handlebars.register_template_string("structs", STRUCTS.as_str())?;
+        handlebars.register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())?;
+        handlebars.register_template_string("field_funcs", FIELD_FUNCS.as_str())?;
+        handlebars.register_template_string(
+            "montgomery_product_funcs",
+            MONTGOMERY_PRODUCT_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string(
+            "extract_word_from_bytes_le_funcs",
+            EXTRACT_WORD_FROM_BYTES_LE_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())?;
+        let mut data = self.curve_data();
+        merge_data(
+            &mut data,
+            json!({
+                "workgroup_size": workgroup_size,
+                "num_y_workgroups": num_y_workgroups,
+                "to_mont_limbs": self.to_mont_limbs(),
+            }),
+        );
+        self.add_glv_data(&mut data);
+        Ok(handlebars.render("convert_point_coords", &data)?)
+    }
+
+    /// Generate the shader that decomposes scalars only, for points that are
+    /// already resident on the GPU
+    pub fn gen_decomp_scalars_only_shader(
+        &self,
+        workgroup_size: usize,
+        num_y_workgroups: usize,
+        num_subtasks: usize,
+        num_columns: usize,
+    ) -> Result<String, MsmError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_template_string(
+            "decomp_scalars_only",
+            DECOMPOSE_SCALARS_ONLY_SHADER.as_str(),
+        )?;
+        handlebars.register_template_string(
+            "extract_word_from_bytes_le_funcs",
+            EXTRACT_WORD_FROM_BYTES_LE_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string("glv_funcs", GLV_FUNCS.as_str())?;
+        let mut data = json!({
             "workgroup_size": workgroup_size,
-            "word_size": self.word_size,
             "chunk_size": self.chunk_size,
-            "num_words": self.num_words,
             "num_y_workgroups": num_y_workgroups,
             "num_subtasks": num_subtasks,
             "num_columns": num_columns,
-            "n0": self.n0,
-            "p_limbs": self.p_limbs,
-            "p_limbs_plus_one": self.p_limbs_plus_one,
-            "zero_limbs": self.zero_limbs,
-            "one_limbs": self.one_limbs,
-            "slack": self.slack,
-            "w_mask": self.w_mask,
-            "index_shift": self.index_shift,
-            "num_words_mul_two": self.num_words * 2,
-            "num_words_plus_one": self.num_words + 1,
-            "r_limbs": self.r_limbs,
-            "mu_limbs": self.mu_limbs,
-            "slack": self.slack,
-            "rinv_limbs": self.rinv_limbs,
+            "num_halves": self.num_halves,
         });
-        handlebars.render("decomp_scalars", &data).unwrap()
+        self.add_glv_data(&mut data);
+        Ok(handlebars.render("decomp_scalars_only", &data)?)
     }
 
     /// Generate the test field shader
-    pub fn gen_test_field_shader(&self) -> String {
+    pub fn gen_test_field_shader(&self) -> Result<String, MsmError> {
         let mut handlebars = Handlebars::new();
-        handlebars
-            .register_template_string("test_field", TEST_FIELD_SHADER.as_str())
-            .unwrap();
-
-        handlebars
-            .register_template_string("structs", STRUCTS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("field_funcs", FIELD_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string(
-                "montgomery_product_funcs",
-                MONTGOMERY_PRODUCT_FUNCS.as_str(),
-            )
-            .unwrap();
-        handlebars
-            .register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())
-            .unwrap();
+        handlebars.register_template_string("test_field", TEST_FIELD_SHADER.as_str())?;
 
-        let data = json!({
-            "word_size": self.word_size,
-            "num_words": self.num_words,
-            "p_limbs": self.p_limbs,
-            "p_limbs_plus_one": self.p_limbs_plus_one,
-            "zero_limbs": self.zero_limbs,
-            "one_limbs": self.one_limbs,
-            "r_limbs": self.r_limbs,
-            "w_mask": self.w_mask,
-            "num_words_mul_two": self.num_words * 2,
-            "num_words_plus_one": self.num_words + 1,
-            "n0": self.n0,
-            "mu_limbs": self.mu_limbs,
-            "slack": self.slack,
-            "rinv_limbs": self.rinv_limbs,
-        });
-        handlebars.render("test_field", &data).unwrap()
+        handlebars.register_template_string("structs", STRUCTS.as_str())?;
+        handlebars.register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())?;
+        handlebars.register_template_string("field_funcs", FIELD_FUNCS.as_str())?;
+        handlebars.register_template_string(
+            "montgomery_product_funcs",
+            MONTGOMERY_PRODUCT_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())?;
+
+        let data = self.curve_data();
+        Ok(handlebars.render("test_field", &data)?)
     }
 
     /// Generate the test point shader
-    pub fn gen_test_point_shader(&self) -> String {
+    pub fn gen_test_point_shader(&self) -> Result<String, MsmError> {
         let mut handlebars = Handlebars::new();
-        handlebars
-            .register_template_string("test_point", TEST_POINT_SHADER.as_str())
-            .unwrap();
-
-        handlebars
-            .register_template_string("structs", STRUCTS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("field_funcs", FIELD_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string(
-                "montgomery_product_funcs",
-                MONTGOMERY_PRODUCT_FUNCS.as_str(),
-            )
-            .unwrap();
-        handlebars
-            .register_template_string("ec_funcs", EC_FUNCS.as_str())
-            .unwrap();
-        handlebars
-            .register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())
-            .unwrap();
-        let data = json!({
-            "word_size": self.word_size,
-            "num_words": self.num_words,
-            "p_limbs": self.p_limbs,
-            "p_limbs_plus_one": self.p_limbs_plus_one,
-            "zero_limbs": self.zero_limbs,
-            "one_limbs": self.one_limbs,
-            "r_limbs": self.r_limbs,
-            "w_mask": self.w_mask,
-            "num_words_mul_two": self.num_words * 2,
-            "num_words_plus_one": self.num_words + 1,
-            "n0": self.n0,
-            "mu_limbs": self.mu_limbs,
-            "slack": self.slack,
-            "rinv_limbs": self.rinv_limbs,
-        });
-        handlebars.render("test_point", &data).unwrap()
+        handlebars.register_template_string("test_point", TEST_POINT_SHADER.as_str())?;
+
+        handlebars.register_template_string("structs", STRUCTS.as_str())?;
+        handlebars.register_template_string("bigint_funcs", BIGINT_FUNCS.as_str())?;
+        handlebars.register_template_string("field_funcs", FIELD_FUNCS.as_str())?;
+        handlebars.register_template_string(
+            "montgomery_product_funcs",
+            MONTGOMERY_PRODUCT_FUNCS.as_str(),
+        )?;
+        handlebars.register_template_string("ec_funcs", self.ec_funcs())?;
+        handlebars.register_template_string("field2_funcs", FIELD2_FUNCS.as_str())?;
+        handlebars.register_template_string("barrett_funcs", BARRETT_FUNCS.as_str())?;
+        let data = self.curve_data();
+        Ok(handlebars.render("test_point", &data)?)
+    }
+}
+
+/// Split a curve constant packed as by `GpuCurve::curve_a` into its base
+/// field components.
+fn components<C: GpuCurve>(packed: &BigUint) -> Vec<BigUint> {
+    let mut bytes = packed.to_bytes_le();
+    bytes.resize(C::COORD_BYTES, 0);
+    bytes
+        .chunks(C::COORD_BYTES / C::EXT_DEGREE)
+        .map(BigUint::from_bytes_le)
+        .collect()
+}
+
+/// Add the stage-specific keys of a shader to the data from `curve_data`.
+fn merge_data(data: &mut serde_json::Value, stage_data: serde_json::Value) {
+    if let serde_json::Value::Object(stage_data) = stage_data {
+        data.as_object_mut().unwrap().extend(stage_data);
     }
 }
@@ -1,8 +1,10 @@
-use ff::PrimeField;
 use group::{prime::PrimeCurveAffine, Group};
-use halo2curves::bn256::{Fr, G1, G1Affine};
+use halo2curves::bn256::{Fr, G1Affine, G1};
 
-use crate::cuzk::utils::to_words_le_from_field;
+pub use crate::cuzk::verify::{
+    cpu_glv_window_sum, cpu_window_sum, decompose_scalars_glv, decompose_scalars_signed,
+    glv_points, glv_signed_digits,
+};
 
 /// Rust implementation of serial transpose algorithm from
 /// https://synergy.cs.vt.edu/pubs/papers/wang-transposition-ics16.pdf.
@@ -117,47 +119,26 @@ pub fn cpu_transpose(
     (all_csc_col_ptr, all_csc_row_idx, all_csc_vals)
 }
 
-/// Decompose scalars signed
-pub fn decompose_scalars_signed<F: PrimeField>(
-    scalars: &[F],
-    num_words: usize,
-    word_size: usize,
-) -> Vec<Vec<i32>> {
-    let l = 1 << word_size;
-    let shift = 1 << (word_size - 1);
-
-    let mut as_limbs: Vec<Vec<i32>> = Vec::new();
-
-    for scalar in scalars {
-        let limbs = to_words_le_from_field(scalar, num_words, word_size);
-        let mut signed_slices: Vec<i32> = vec![0; limbs.len()];
-
-        let mut carry = 0;
-        for i in 0..limbs.len() {
-            signed_slices[i] = limbs[i] as i32 + carry;
-            if signed_slices[i] >= l / 2 {
-                signed_slices[i] = -(l - signed_slices[i]);
-                if signed_slices[i] == -0 {
-                    signed_slices[i] = 0;
-                }
-                carry = 1;
-            } else {
-                carry = 0;
-            }
-        }
-
-        // We do not need to handle the case where the final carry equals 1, as the highest word of the field modulus (0x12ab) is smaller than 2^{16-1}
-        if carry == 1 {
-            panic!("final carry is 1");
+/// Sort the positions within every column of a transposed CSC matrix.
+///
+/// The GPU transpose scatters the elements of a column in whatever order its
+/// threads run, while `cpu_transpose` keeps them in input order, so GPU
+/// output must be sorted before it is compared against the CPU.
+pub fn sort_csc_columns(
+    all_csc_col_ptr: &[i32],
+    all_csc_val_idxs: &mut [i32],
+    n: usize,
+    num_subtasks: usize,
+    input_size: usize,
+) {
+    for subtask_idx in 0..num_subtasks {
+        let col_ptr = &all_csc_col_ptr[subtask_idx * (n + 1)..(subtask_idx + 1) * (n + 1)];
+        let val_idxs =
+            &mut all_csc_val_idxs[subtask_idx * input_size..(subtask_idx + 1) * input_size];
+        for col in col_ptr.windows(2) {
+            val_idxs[col[0] as usize..col[1] as usize].sort_unstable();
         }
-        as_limbs.push(signed_slices.iter().map(|x| x + shift).collect());
-    }
-    let mut result: Vec<Vec<i32>> = Vec::new();
-    for i in 0..num_words {
-        let t = as_limbs.iter().map(|limbs| limbs[i]).collect();
-        result.push(t);
     }
-    result
 }
 
 /**
@@ -284,10 +265,7 @@ pub fn parallel_bucket_reduction(buckets: &[G1], num_threads: usize) -> Vec<G1>
 }
 
 /// The first part of the parallel bucket reduction algo
-pub fn parallel_bucket_reduction_1(
-    buckets: &[G1],
-    num_threads: usize,
-) -> (Vec<G1>, Vec<G1>) {
+pub fn parallel_bucket_reduction_1(buckets: &[G1], num_threads: usize) -> (Vec<G1>, Vec<G1>) {
     let buckets_per_thread = buckets.len() / num_threads;
     let mut g_points: Vec<G1> = vec![];
     let mut m_points: Vec<G1> = vec![];
@@ -335,4 +313,4 @@ pub fn parallel_bucket_reduction_2(
         result.push(g);
     }
     result
-}
\ No newline at end of file
+}
@@ -1,8 +1,7 @@
-use crate::cuzk::msm::{P, calc_num_words};
 use ff::{Field, PrimeField};
 use halo2curves::CurveAffine;
 use num_bigint::{BigInt, BigUint, Sign};
-use num_traits::One;
+use num_traits::{Num, One};
 #[cfg(target_arch = "wasm32")]
 use web_sys::console;
 
@@ -169,12 +168,13 @@ pub fn from_words_le_without_assertion<F: PrimeField>(
         let exponent = (num_words - i - 1) * word_size;
         let limb = limbs[num_words - i - 1];
         val += BigUint::from(2u32).pow(exponent as u32) * BigUint::from(limb);
-        if val == *P {
-            val = BigUint::ZERO;
-        }
     }
-    let bytes = val.to_bytes_le();
-    
+    // The GPU may return values in [p, 2^(num_words * word_size)), e.g. p
+    // itself for zero.
+    let modulus =
+        BigUint::from_str_radix(F::MODULUS.trim_start_matches("0x"), 16).expect("Invalid modulus");
+    let bytes = (val % modulus).to_bytes_le();
+
     bytes_to_field(&bytes)
 }
 
@@ -216,6 +216,32 @@ pub fn gen_p_limbs_plus_one(p: &BigUint, num_words: usize, word_size: usize) ->
     r
 }
 
+/// Generate the GPU representation of an exponent for `montgomery_pow_wide`
+pub fn gen_exponent_limbs(e: &BigUint, num_words: usize, word_size: usize) -> String {
+    let limbs = to_words_le(e, num_words, word_size);
+    let mut r = String::new();
+    for (i, limb) in limbs.iter().enumerate() {
+        r += &format!("    e.limbs[{i}u] = {limb}u;\n");
+    }
+    r
+}
+
+/// Generate the GPU representation of the Fermat inversion exponent `p - 2`
+pub fn gen_p_minus_two_limbs(p: &BigUint, num_words: usize, word_size: usize) -> String {
+    gen_exponent_limbs(&(p - 2u32), num_words, word_size)
+}
+
+/// Generate the GPU representation of the Tonelli-Shanks root of unity `z`
+/// in Montgomery form
+pub fn gen_sqrt_z_limbs(z_mont: &BigUint, num_words: usize, word_size: usize) -> String {
+    let limbs = to_words_le(z_mont, num_words, word_size);
+    let mut r = String::new();
+    for (i, limb) in limbs.iter().enumerate() {
+        r += &format!("    z.limbs[{i}u] = {limb}u;\n");
+    }
+    r
+}
+
 /// Generate the GPU representation of zero
 pub fn gen_zero_limbs(num_words: usize) -> String {
     let mut r = String::new();
@@ -237,6 +263,16 @@ pub fn gen_one_limbs(num_words: usize) -> String {
     r
 }
 
+/// Generate the GPU representation of one in Montgomery form, as a
+/// comma-separated list of limbs
+pub fn gen_mont_one_limbs(r: &BigUint, num_words: usize, word_size: usize) -> String {
+    to_words_le(r, num_words, word_size)
+        .iter()
+        .map(|limb| format!("{limb}u"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Generate the GPU representation of the Montgomery radix
 pub fn gen_r_limbs(r: &BigUint, num_words: usize, word_size: usize) -> String {
     let limbs = to_words_le(r, num_words, word_size);
@@ -257,6 +293,47 @@ pub fn gen_rinv_limbs(rinv: &BigUint, num_words: usize, word_size: usize) -> Str
     r
 }
 
+/// Generate the GPU representation of the GLV constant `beta`
+pub fn gen_beta_limbs(beta: &BigUint, num_words: usize, word_size: usize) -> String {
+    let limbs = to_words_le(beta, num_words, word_size);
+    let mut r = String::new();
+    for (i, limb) in limbs.iter().enumerate() {
+        r += &format!("    beta.limbs[{i}u] = {limb}u;\n");
+    }
+    r
+}
+
+/// Generate the GPU representation of the curve constant `a` in Montgomery form
+pub fn gen_a_limbs(a_mont: &BigUint, num_words: usize, word_size: usize) -> String {
+    let limbs = to_words_le(a_mont, num_words, word_size);
+    let mut r = String::new();
+    for (i, limb) in limbs.iter().enumerate() {
+        r += &format!("    a.limbs[{i}u] = {limb}u;\n");
+    }
+    r
+}
+
+/// Generate the GPU representation of the curve constant `b` in Montgomery form
+pub fn gen_b_limbs(b_mont: &BigUint, num_words: usize, word_size: usize) -> String {
+    let limbs = to_words_le(b_mont, num_words, word_size);
+    let mut r = String::new();
+    for (i, limb) in limbs.iter().enumerate() {
+        r += &format!("    b.limbs[{i}u] = {limb}u;\n");
+    }
+    r
+}
+
+/// Generate the GPU representation of the twisted Edwards constant `d` in
+/// Montgomery form
+pub fn gen_d_limbs(d_mont: &BigUint, num_words: usize, word_size: usize) -> String {
+    let limbs = to_words_le(d_mont, num_words, word_size);
+    let mut r = String::new();
+    for (i, limb) in limbs.iter().enumerate() {
+        r += &format!("    d.limbs[{i}u] = {limb}u;\n");
+    }
+    r
+}
+
 /// Generate the Montgomery magic number
 pub fn gen_mu(p: &BigUint) -> BigUint {
     let mut x = 1u32;
@@ -356,10 +433,48 @@ pub struct MiscParams {
     pub rinv: BigUint,
 }
 
+/// Tonelli-Shanks parameters of the field with modulus `p`
+#[derive(Debug)]
+pub struct SqrtParams {
+    /// The two-adicity `s` of `p - 1 = 2^s * q`
+    pub s: u32,
+    /// The odd part `q` of `p - 1`
+    pub q: BigUint,
+    /// `c^q` for the smallest quadratic non-residue `c`, a generator of the
+    /// `2^s`-th roots of unity
+    pub z: BigUint,
+}
+
+/// Compute the Tonelli-Shanks parameters of the field with odd prime
+/// modulus `p`
+pub fn compute_sqrt_params(p: &BigUint) -> SqrtParams {
+    let p_minus_one = p - 1u32;
+    let s = p_minus_one.trailing_zeros().expect("Modulus must be odd") as u32;
+    let q = &p_minus_one >> s;
+    let legendre_exponent = &p_minus_one >> 1;
+    let non_residue = (2u32..)
+        .map(BigUint::from)
+        .find(|c| c.modpow(&legendre_exponent, p) == p_minus_one)
+        .unwrap();
+    let z = non_residue.modpow(&q, p);
+    SqrtParams { s, q, z }
+}
+
+/// Number of `word_size`-bit limbs of an element of the field with modulus
+/// `p`.
+///
+/// At least one bit of slack is kept above the modulus, since `bigint_add`
+/// drops its final carry and the sum of two reduced elements must still fit.
+/// A full-width modulus such as secp256k1's therefore gets an extra limb when
+/// its bit length is a multiple of `word_size`.
+pub fn calc_num_words_for(p: &BigUint, word_size: usize) -> usize {
+    calc_bitwidth(p) / word_size + 1
+}
+
 /// Compute miscellaneous parameters for the WebGPU shader
 pub fn compute_misc_params(p: &BigUint, word_size: usize) -> MiscParams {
     assert!(word_size > 0);
-    let num_words = calc_num_words(word_size);
+    let num_words = calc_num_words_for(p, word_size);
     let r = BigUint::one() << (num_words * word_size);
     let res = calc_rinv_and_n0(p, &r, word_size as u32);
     let rinv = res.0;
@@ -385,11 +500,10 @@ pub fn debug(s: &str) {
 #[cfg(test)]
 mod tests {
     use halo2curves::bn256::{Fq, Fr};
-    use num_traits::Num;
     use rand::thread_rng;
 
     use super::*;
-    use crate::cuzk::msm::{PARAMS, WORD_SIZE};
+    use crate::cuzk::msm::{calc_num_words, P, PARAMS, WORD_SIZE};
     use crate::sample_scalars;
 
     #[test]
@@ -406,6 +520,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compute_sqrt_params() {
+        // BN254 has p = 3 mod 4, so z is -1
+        let params = compute_sqrt_params(&P);
+        assert_eq!(params.s, 1);
+        assert_eq!(params.q, (&*P - 1u32) >> 1);
+        assert_eq!(params.z, &*P - 1u32);
+
+        // Pallas has a two-adicity of 32
+        let p = <halo2curves::pasta::PallasAffine as crate::GpuCurve>::base_modulus();
+        let params = compute_sqrt_params(&p);
+        assert_eq!(params.s, 32);
+        assert_eq!(&params.q << 32, &p - 1u32);
+        let half_order = BigUint::one() << 31;
+        assert_eq!(params.z.modpow(&half_order, &p), &p - 1u32);
+    }
+
     #[test]
     fn test_gen_p_limbs() {
         let p = P.clone();
@@ -422,6 +553,18 @@ mod tests {
         println!("{}", r_limbs);
     }
 
+    #[test]
+    fn test_gen_mont_one_limbs() {
+        let num_words = calc_num_words(WORD_SIZE);
+        let limbs = gen_mont_one_limbs(&PARAMS.r, num_words, WORD_SIZE);
+        let expected = to_words_le(&PARAMS.r, num_words, WORD_SIZE);
+        assert_eq!(limbs.split(", ").count(), num_words);
+        assert_eq!(
+            limbs.split(", ").next(),
+            Some(format!("{}u", expected[0]).as_str())
+        );
+    }
+
     #[test]
     fn test_field_to_u8_vec_for_gpu() {
         // random
@@ -449,4 +592,38 @@ mod tests {
         ];
         assert_eq!(limbs, expected);
     }
+
+    #[test]
+    fn test_calc_num_words_for() {
+        for word_size in 13..17 {
+            assert_eq!(calc_num_words_for(&P, word_size), calc_num_words(word_size));
+        }
+
+        // secp256k1 fills all 256 bits, so 16-bit limbs need a 17th limb.
+        let secp256k1_p = BigUint::from_str_radix(
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+            16,
+        )
+        .unwrap();
+        assert_eq!(calc_num_words_for(&secp256k1_p, 13), 20);
+        assert_eq!(calc_num_words_for(&secp256k1_p, 16), 17);
+    }
+
+    #[test]
+    fn test_from_words_le_without_assertion_reduces() {
+        // The Montgomery product may leave p itself, which decodes to zero.
+        let p = BigUint::from_str_radix(
+            halo2curves::bls12381::Fq::MODULUS.trim_start_matches("0x"),
+            16,
+        )
+        .unwrap();
+        let num_words = calc_num_words_for(&p, WORD_SIZE);
+        let limbs = to_words_le(&(&p + 5u32), num_words, WORD_SIZE)
+            .into_iter()
+            .map(|limb| limb as u16)
+            .collect::<Vec<_>>();
+        let value: halo2curves::bls12381::Fq =
+            from_words_le_without_assertion(&limbs, num_words, WORD_SIZE);
+        assert_eq!(value, halo2curves::bls12381::Fq::from(5u64));
+    }
 }
@@ -0,0 +1,213 @@
+//! CPU recomputation of single windows of an MSM, used by the self-check
+//! of [`MsmContext`](crate::cuzk::context::MsmContext).
+
+use ff::PrimeField;
+use group::Group;
+use halo2curves::CurveAffine;
+use num_bigint::BigUint;
+
+use crate::cuzk::curve::GpuCurve;
+use crate::cuzk::error::MsmError;
+use crate::cuzk::plan::MsmPlan;
+use crate::cuzk::utils::{to_words_le, to_words_le_from_field};
+
+/// Recompute window `window` of the pass planned by `plan` over the padded
+/// `points` and `scalars` on the CPU, and check it against `window_sums` as
+/// read back from the GPU.
+pub fn check_window_sum<C: GpuCurve>(
+    plan: &MsmPlan,
+    points: &[C],
+    scalars: &[C::Scalar],
+    window_sums: &[C::Curve],
+    window: usize,
+) -> Result<(), MsmError> {
+    let window_sum = if plan.glv {
+        cpu_glv_window_sum
+    } else {
+        cpu_window_sum
+    };
+    let expected = window_sum(points, scalars, window, plan.num_subtasks, plan.chunk_size);
+    if window_sums[window] != expected {
+        return Err(MsmError::VerificationFailed { window });
+    }
+    Ok(())
+}
+
+/// Decompose scalars signed
+pub fn decompose_scalars_signed<F: PrimeField>(
+    scalars: &[F],
+    num_words: usize,
+    word_size: usize,
+) -> Vec<Vec<i32>> {
+    let l = 1 << word_size;
+    let shift = 1 << (word_size - 1);
+
+    let mut as_limbs: Vec<Vec<i32>> = Vec::new();
+
+    for scalar in scalars {
+        let limbs = to_words_le_from_field(scalar, num_words, word_size);
+        let mut signed_slices: Vec<i32> = vec![0; limbs.len()];
+
+        let mut carry = 0;
+        for i in 0..limbs.len() {
+            signed_slices[i] = limbs[i] as i32 + carry;
+            if signed_slices[i] >= l / 2 {
+                signed_slices[i] = -(l - signed_slices[i]);
+                if signed_slices[i] == -0 {
+                    signed_slices[i] = 0;
+                }
+                carry = 1;
+            } else {
+                carry = 0;
+            }
+        }
+
+        // We do not need to handle the case where the final carry equals 1, as the highest word of the field modulus (0x12ab) is smaller than 2^{16-1}
+        if carry == 1 {
+            panic!("final carry is 1");
+        }
+        as_limbs.push(signed_slices.iter().map(|x| x + shift).collect());
+    }
+    let mut result: Vec<Vec<i32>> = Vec::new();
+    for i in 0..num_words {
+        let t = as_limbs.iter().map(|limbs| limbs[i]).collect();
+        result.push(t);
+    }
+    result
+}
+
+/// Compute the sum of a single window of an MSM on the CPU, ie. the value the
+/// SMVP and BPR stages produce for subtask `window` before Horner's method.
+pub fn cpu_window_sum<C: CurveAffine>(
+    points: &[C],
+    scalars: &[C::Scalar],
+    window: usize,
+    num_subtasks: usize,
+    chunk_size: usize,
+) -> C::Curve {
+    let digits = &decompose_scalars_signed(scalars, num_subtasks, chunk_size)[window];
+    window_sum_from_digits(points, digits, chunk_size)
+}
+
+/// Sum the points of a window by the signed digits, offset by
+/// `2^(chunk_size - 1)`, that index their buckets.
+fn window_sum_from_digits<C: CurveAffine>(
+    points: &[C],
+    digits: &[i32],
+    chunk_size: usize,
+) -> C::Curve {
+    let shift = 1 << (chunk_size - 1);
+    let mut buckets = vec![C::Curve::identity(); shift as usize + 1];
+    for (point, digit) in points.iter().zip(digits) {
+        let digit = digit - shift;
+        if digit > 0 {
+            buckets[digit as usize] += point.to_curve();
+        } else if digit < 0 {
+            buckets[(-digit) as usize] -= point.to_curve();
+        }
+    }
+
+    let mut m = C::Curve::identity();
+    let mut g = C::Curve::identity();
+    for bucket in buckets.iter().skip(1).rev() {
+        m += bucket;
+        g += m;
+    }
+    g
+}
+
+/// Signed `chunk_size`-bit digits of `magnitude`, or of `-magnitude` if
+/// `negative`, as computed by `glv_digits` in `glv.template.wgsl`.
+///
+/// The magnitude of a negative half is recoded into digits in `(-s, s]`
+/// before they are negated, so that every digit lies in `[-s, s)`.
+pub fn glv_signed_digits(
+    magnitude: &BigUint,
+    negative: bool,
+    num_subtasks: usize,
+    chunk_size: usize,
+) -> Vec<i32> {
+    let l = 1i32 << chunk_size;
+    let s = l / 2;
+    let mut carry = 0;
+    to_words_le(magnitude, num_subtasks, chunk_size)
+        .into_iter()
+        .map(|word| {
+            let mut digit = word as i32 + carry;
+            if digit > s || (digit == s && !negative) {
+                digit -= l;
+                carry = 1;
+            } else {
+                carry = 0;
+            }
+            if negative {
+                -digit
+            } else {
+                digit
+            }
+        })
+        .collect()
+}
+
+/// Split every scalar with the GLV endomorphism of `C` and decompose both
+/// halves into signed digits offset by `2^(chunk_size - 1)`.
+///
+/// The result has one row per subtask in the layout of the chunks written by
+/// the GLV decompose shaders: the digits of every `k1`, followed by those of
+/// every `k2`.
+pub fn decompose_scalars_glv<C: GpuCurve>(
+    scalars: &[C::Scalar],
+    num_subtasks: usize,
+    chunk_size: usize,
+) -> Vec<Vec<i32>> {
+    let glv = C::glv().expect("The curve has no GLV endomorphism");
+    let shift = 1 << (chunk_size - 1);
+    let halves = scalars
+        .iter()
+        .map(|scalar| glv.decompose(&BigUint::from_bytes_le(scalar.to_repr().as_ref())))
+        .collect::<Vec<_>>();
+    let digits = (0..2)
+        .flat_map(|half| {
+            halves.iter().map(move |halves| {
+                let (magnitude, negative) = &halves[half];
+                glv_signed_digits(magnitude, *negative, num_subtasks, chunk_size)
+            })
+        })
+        .collect::<Vec<_>>();
+    (0..num_subtasks)
+        .map(|i| digits.iter().map(|digits| digits[i] + shift).collect())
+        .collect()
+}
+
+/// The points written by the GLV decompose shaders: `points`, followed by
+/// their images `(beta * x, y)` under the endomorphism of `C`.
+pub fn glv_points<C: GpuCurve>(points: &[C]) -> Vec<C> {
+    let glv = C::glv().expect("The curve has no GLV endomorphism");
+    let beta = C::base_from_bytes(&glv.beta.to_bytes_le());
+    let images = points.iter().map(|point| {
+        let coordinates = point.coordinates();
+        if bool::from(coordinates.is_none()) {
+            return *point;
+        }
+        let coordinates = coordinates.unwrap();
+        C::from_xy(*coordinates.x() * beta, *coordinates.y()).unwrap()
+    });
+    points
+        .iter()
+        .copied()
+        .chain(images.collect::<Vec<_>>())
+        .collect()
+}
+
+/// [`cpu_window_sum`] for a pass that splits the scalars with the GLV
+/// endomorphism of `C`.
+pub fn cpu_glv_window_sum<C: GpuCurve>(
+    points: &[C],
+    scalars: &[C::Scalar],
+    window: usize,
+    num_subtasks: usize,
+    chunk_size: usize,
+) -> C::Curve {
+    let digits = &decompose_scalars_glv::<C>(scalars, num_subtasks, chunk_size)[window];
+    window_sum_from_digits(&glv_points(points), digits, chunk_size)
+}
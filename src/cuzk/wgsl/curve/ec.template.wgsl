@@ -7,6 +7,31 @@ fn is_inf(p: Point) -> bool {
     return field_eq(p.z, ZERO);
 }
 
+/// One in Montgomery form, used as the z coordinate of affine points.
+fn coord_one() -> BigInt {
+    return get_r();
+}
+
+/// Lift an affine point in Montgomery form to Jacobian coordinates.
+fn point_from_affine(x: BigInt, y: BigInt) -> Point {
+    return Point(x, y, coord_one());
+}
+
+/// The curve constant a in Montgomery form.
+fn get_a() -> BigInt {
+    var a: BigInt;
+{{{ a_limbs }}}
+    return a;
+}
+
+/// The curve constant b in Montgomery form.
+fn get_b() -> BigInt {
+    var b: BigInt;
+{{{ b_limbs }}}
+    return b;
+}
+
+{{#if a_is_zero}}
 fn point_double(p: Point) -> Point {
     // https://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html#doubling-dbl-2009-l
     var p1x = p.x;
@@ -32,6 +57,69 @@ fn point_double(p: Point) -> Point {
     var z3 = montgomery_product(&p1y_shift, &p1z);
     return Point(x3, y3, z3);
 }
+{{else if a_is_minus_three}}
+fn point_double(p: Point) -> Point {
+    // https://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-3.html#doubling-dbl-2001-b
+    var p1x = p.x;
+    var p1y = p.y;
+    var p1z = p.z;
+    var delta = montgomery_square(&p1z);
+    var gamma = montgomery_square(&p1y);
+    var beta = montgomery_product(&p1x, &gamma);
+    var X1_sub_delta = field_sub(&p1x, &delta);
+    var X1_p_delta = field_add(&p1x, &delta);
+    var prod = montgomery_product(&X1_sub_delta, &X1_p_delta);
+    var prod_shift = field_small_scalar_shift(1, prod);
+    var alpha = field_add(&prod_shift, &prod);
+    var alpha_sq = montgomery_square(&alpha);
+    var beta_shift_3 = field_small_scalar_shift(3, beta);
+    var x3 = field_sub(&alpha_sq, &beta_shift_3);
+    var Y1_p_Z1 = field_add(&p1y, &p1z);
+    var Y1_p_Z1_sq = montgomery_square(&Y1_p_Z1);
+    var gamma_p_delta = field_add(&gamma, &delta);
+    var z3 = field_sub(&Y1_p_Z1_sq, &gamma_p_delta);
+    var beta_shift_2 = field_small_scalar_shift(2, beta);
+    var beta_4_sub_x3 = field_sub(&beta_shift_2, &x3);
+    var alpha_mul = montgomery_product(&alpha, &beta_4_sub_x3);
+    var gamma_sq = montgomery_square(&gamma);
+    var gamma_sq_shift = field_small_scalar_shift(3, gamma_sq);
+    var y3 = field_sub(&alpha_mul, &gamma_sq_shift);
+    return Point(x3, y3, z3);
+}
+{{else}}
+fn point_double(p: Point) -> Point {
+    // https://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian.html#doubling-dbl-2007-bl
+    var p1x = p.x;
+    var p1y = p.y;
+    var p1z = p.z;
+    var XX = montgomery_square(&p1x);
+    var YY = montgomery_square(&p1y);
+    var YYYY = montgomery_square(&YY);
+    var ZZ = montgomery_square(&p1z);
+    var X1plusYY = field_add(&p1x, &YY);
+    var X1plusYY_sq = montgomery_square(&X1plusYY);
+    var XX_p_YYYY = field_add(&XX, &YYYY);
+    var S = field_small_scalar_shift(1, field_sub(&X1plusYY_sq, &XX_p_YYYY));
+    var a = get_a();
+    var ZZ_sq = montgomery_square(&ZZ);
+    var a_ZZ_sq = montgomery_product(&a, &ZZ_sq);
+    var XX_shift = field_small_scalar_shift(1, XX);
+    var XX_3 = field_add(&XX_shift, &XX);
+    var M = field_add(&XX_3, &a_ZZ_sq);
+    var M_sq = montgomery_square(&M);
+    var S_shift = field_small_scalar_shift(1, S);
+    var x3 = field_sub(&M_sq, &S_shift);
+    var S_sub_x3 = field_sub(&S, &x3);
+    var M_mul_S_sub_x3 = montgomery_product(&M, &S_sub_x3);
+    var YYYY_shift = field_small_scalar_shift(3, YYYY);
+    var y3 = field_sub(&M_mul_S_sub_x3, &YYYY_shift);
+    var Y1plusZ1 = field_add(&p1y, &p1z);
+    var Y1plusZ1_sq = montgomery_square(&Y1plusZ1);
+    var YY_p_ZZ = field_add(&YY, &ZZ);
+    var z3 = field_sub(&Y1plusZ1_sq, &YY_p_ZZ);
+    return Point(x3, y3, z3);
+}
+{{/if}}
 
 fn point_add(p: Point, q: Point) -> Point {
     // https://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html#addition-add-2007-bl
@@ -85,6 +173,52 @@ fn point_add(p: Point, q: Point) -> Point {
     return Point(nx, ny, nz);
 }
 
+/// Add the affine point (x, y), in Montgomery form, to p. With Z2 = 1 this
+/// takes 7M + 4S instead of the 11M + 5S of point_add.
+fn point_add_mixed(p: Point, x: BigInt, y: BigInt) -> Point {
+    // https://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html#addition-madd-2007-bl
+    if (field_eq(p.z, ZERO)) {
+        return point_from_affine(x, y);
+    }
+    var p1x = p.x;
+    var p1y = p.y;
+    var p1z = p.z;
+    var q1x = x;
+    var q1y = y;
+
+    var Z1Z1 = montgomery_square(&p1z);
+    var U2 = montgomery_product(&q1x, &Z1Z1);
+    var Z1Z1Z1 = montgomery_product(&Z1Z1, &p1z);
+    var S2 = montgomery_product(&q1y, &Z1Z1Z1);
+    if (field_eq(p1x, U2)) {
+        if (field_eq(p1y, S2)) {
+            return point_double(p);
+        } else {
+            return POINT_IDENTITY;
+        }
+    }
+
+    var H = field_sub(&U2, &p1x);
+    var HH = montgomery_square(&H);
+    var I = field_small_scalar_shift(2, HH);
+    var J = montgomery_product(&H, &I);
+    var R = field_small_scalar_shift(1, field_sub(&S2, &p1y));
+    var V = montgomery_product(&p1x, &I);
+    var R_sq = montgomery_square(&R);
+    var V_shift = field_small_scalar_shift(1, V);
+    var J_p_V = field_add(&J, &V_shift);
+    var nx = field_sub(&R_sq, &J_p_V);
+    var V_sub_nx = field_sub(&V, &nx);
+    var R_prod_V_sub_nx = montgomery_product(&R, &V_sub_nx);
+    var shift_1_Y1_J = field_small_scalar_shift(1, montgomery_product(&p1y, &J));
+    var ny = field_sub(&R_prod_V_sub_nx, &shift_1_Y1_J);
+    var p1z_p_H = field_add(&p1z, &H);
+    var p1z_p_H_sq = montgomery_square(&p1z_p_H);
+    var Z1Z1_p_HH = field_add(&Z1Z1, &HH);
+    var nz = field_sub(&p1z_p_H_sq, &Z1Z1_p_HH);
+    return Point(nx, ny, nz);
+}
+
 fn scalar_mul(p: Point, k: BigInt) -> Point {
     var r: Point = POINT_IDENTITY;
     var t: Point = p;
@@ -0,0 +1,186 @@
+{{> field2_funcs }}
+
+/// Jacobian arithmetic for curves y^2 = x^3 + b over Fq2, such as BN254 G2.
+/// The formulas are the same as in ec.template.wgsl, with the base field
+/// operations replaced by their Fq2 counterparts.
+
+const POINT_IDENTITY: Point = Point(FQ2_ZERO, Fq2(ONE, ZERO), FQ2_ZERO);
+
+fn is_inf(p: Point) -> bool {
+    return field2_is_zero(p.z);
+}
+
+/// One in Montgomery form, used as the z coordinate of affine points.
+fn coord_one() -> Fq2 {
+    return Fq2(get_r(), ZERO);
+}
+
+/// Lift an affine point in Montgomery form to Jacobian coordinates.
+fn point_from_affine(x: Fq2, y: Fq2) -> Point {
+    return Point(x, y, coord_one());
+}
+
+/// The curve constant a in Montgomery form.
+fn get_a() -> Fq2 {
+    var a: BigInt;
+{{{ a_limbs }}}
+    var a0 = a;
+{{{ a_c1_limbs }}}
+    return Fq2(a0, a);
+}
+
+/// The curve constant b in Montgomery form.
+fn get_b() -> Fq2 {
+    var b: BigInt;
+{{{ b_limbs }}}
+    var b0 = b;
+{{{ b_c1_limbs }}}
+    return Fq2(b0, b);
+}
+
+fn point_double(p: Point) -> Point {
+    // https://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html#doubling-dbl-2009-l
+    var p1x = p.x;
+    var p1y = p.y;
+    var p1z = p.z;
+    var A = field2_sqr(&p1x);
+    var B = field2_sqr(&p1y);
+    var C = field2_sqr(&B);
+    var X1plusB = field2_add(&p1x, &B);
+    var X1plusB_sq = field2_sqr(&X1plusB);
+    var A_p_C = field2_add(&A, &C);
+    var D = field2_small_scalar_shift(1, field2_sub(&X1plusB_sq, &A_p_C));
+    var A_shift = field2_small_scalar_shift(1, A);
+    var E = field2_add(&A_shift, &A);
+    var F = field2_sqr(&E);
+    var D_shift = field2_small_scalar_shift(1, D);
+    var x3 = field2_sub(&F, &D_shift);
+    var C_shift = field2_small_scalar_shift(3, C);
+    var D_sub_x3 = field2_sub(&D, &x3);
+    var E_mul_D_sub_x3 = field2_mul(&E, &D_sub_x3);
+    var y3 = field2_sub(&E_mul_D_sub_x3, &C_shift);
+    var p1y_shift = field2_small_scalar_shift(1, p1y);
+    var z3 = field2_mul(&p1y_shift, &p1z);
+    return Point(x3, y3, z3);
+}
+
+fn point_add(p: Point, q: Point) -> Point {
+    // https://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html#addition-add-2007-bl
+    if (is_inf(p)) {
+        return q;
+    }
+    if (is_inf(q)) {
+        return p;
+    }
+    var p1x = p.x;
+    var p1y = p.y;
+    var p1z = p.z;
+    var q1x = q.x;
+    var q1y = q.y;
+    var q1z = q.z;
+
+    var Z1Z1 = field2_sqr(&p1z);
+    var Z2Z2 = field2_sqr(&q1z);
+    var U1 = field2_mul(&p1x, &Z2Z2);
+    var U2 = field2_mul(&q1x, &Z1Z1);
+    var Z2Z2Z2 = field2_mul(&Z2Z2, &q1z);
+    var Z1Z1Z1 = field2_mul(&Z1Z1, &p1z);
+    var S1 = field2_mul(&p1y, &Z2Z2Z2);
+    var S2 = field2_mul(&q1y, &Z1Z1Z1);
+    if (field2_eq(U1, U2)) {
+        if (field2_eq(S1, S2)) {
+            return point_double(p);
+        } else {
+            return POINT_IDENTITY;
+        }
+    }
+
+    var H = field2_sub(&U2, &U1);
+    var H_sq = field2_sqr(&H);
+    var I = field2_small_scalar_shift(2, H_sq);
+    var J = field2_mul(&H, &I);
+    var R = field2_small_scalar_shift(1, field2_sub(&S2, &S1));
+    var V = field2_mul(&U1, &I);
+    var R_sq = field2_sqr(&R);
+    var V_shift = field2_small_scalar_shift(1, V);
+    var J_p_V = field2_add(&J, &V_shift);
+    var nx = field2_sub(&R_sq, &J_p_V);
+    var V_sub_nx = field2_sub(&V, &nx);
+    var R_prod_V_sub_nx = field2_mul(&R, &V_sub_nx);
+    var S1_J = field2_mul(&S1, &J);
+    var shift_1_S1_J = field2_small_scalar_shift(1, S1_J);
+    var ny = field2_sub(&R_prod_V_sub_nx, &shift_1_S1_J);
+    var Z1Z1_p_Z2Z2 = field2_add(&Z1Z1, &Z2Z2);
+    var p1z_p_q1z = field2_add(&p1z, &q1z);
+    var p1z_p_q1z_sq = field2_sqr(&p1z_p_q1z);
+    var sub_p1z_p_q1z_sq_Z1Z1_p_Z2Z2 = field2_sub(&p1z_p_q1z_sq, &Z1Z1_p_Z2Z2);
+    var nz = field2_mul(&H, &sub_p1z_p_q1z_sq_Z1Z1_p_Z2Z2);
+    return Point(nx, ny, nz);
+}
+
+/// The mixed addition of ec.template.wgsl over Fq2.
+fn point_add_mixed(p: Point, x: Fq2, y: Fq2) -> Point {
+    // https://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html#addition-madd-2007-bl
+    if (is_inf(p)) {
+        return point_from_affine(x, y);
+    }
+    var p1x = p.x;
+    var p1y = p.y;
+    var p1z = p.z;
+    var q1x = x;
+    var q1y = y;
+
+    var Z1Z1 = field2_sqr(&p1z);
+    var U2 = field2_mul(&q1x, &Z1Z1);
+    var Z1Z1Z1 = field2_mul(&Z1Z1, &p1z);
+    var S2 = field2_mul(&q1y, &Z1Z1Z1);
+    if (field2_eq(p1x, U2)) {
+        if (field2_eq(p1y, S2)) {
+            return point_double(p);
+        } else {
+            return POINT_IDENTITY;
+        }
+    }
+
+    var H = field2_sub(&U2, &p1x);
+    var HH = field2_sqr(&H);
+    var I = field2_small_scalar_shift(2, HH);
+    var J = field2_mul(&H, &I);
+    var R = field2_small_scalar_shift(1, field2_sub(&S2, &p1y));
+    var V = field2_mul(&p1x, &I);
+    var R_sq = field2_sqr(&R);
+    var V_shift = field2_small_scalar_shift(1, V);
+    var J_p_V = field2_add(&J, &V_shift);
+    var nx = field2_sub(&R_sq, &J_p_V);
+    var V_sub_nx = field2_sub(&V, &nx);
+    var R_prod_V_sub_nx = field2_mul(&R, &V_sub_nx);
+    var Y1_J = field2_mul(&p1y, &J);
+    var shift_1_Y1_J = field2_small_scalar_shift(1, Y1_J);
+    var ny = field2_sub(&R_prod_V_sub_nx, &shift_1_Y1_J);
+    var p1z_p_H = field2_add(&p1z, &H);
+    var p1z_p_H_sq = field2_sqr(&p1z_p_H);
+    var Z1Z1_p_HH = field2_add(&Z1Z1, &HH);
+    var nz = field2_sub(&p1z_p_H_sq, &Z1Z1_p_HH);
+    return Point(nx, ny, nz);
+}
+
+fn negate_point(point: Point) -> Point {
+    return Point(point.x, field2_neg(point.y), point.z);
+}
+
+/// The double-and-add of ec.template.wgsl over Fq2.
+fn double_and_add(point: Point, scalar: u32) -> Point {
+    var result: Point = POINT_IDENTITY;
+
+    var s = scalar;
+    var temp = point;
+
+    while (s != 0u) {
+        if ((s & 1u) == 1u) {
+            result = point_add(result, temp);
+        }
+        temp = point_double(temp);
+        s = s >> 1u;
+    }
+    return result;
+}
@@ -1,18 +1,71 @@
+/// One in Montgomery form.
+const MONT_ONE: BigInt = BigInt(
+    array<u32, {{ num_words }}>({{ mont_one_limbs }})
+);
 
-fn double_point(p1: Point) -> Point {
+/// The neutral element (0, 1) in extended coordinates. Unlike the Jacobian
+/// identity, it is an ordinary point that the complete addition law handles
+/// without special cases.
+const POINT_IDENTITY: Point = Point(ZERO, MONT_ONE, ZERO, MONT_ONE);
+
+fn is_inf(p: Point) -> bool {
+    return field_eq(p.x, ZERO) && field_eq(p.y, p.z);
+}
+
+/// One in Montgomery form, used as the z coordinate of affine points.
+fn coord_one() -> BigInt {
+    return MONT_ONE;
+}
+
+/// Lift an affine point in Montgomery form to extended coordinates.
+fn point_from_affine(x: BigInt, y: BigInt) -> Point {
+    var px = x;
+    var py = y;
+    var t = montgomery_product(&px, &py);
+    return Point(x, y, t, coord_one());
+}
+
+/// The curve constant a in Montgomery form.
+fn get_a() -> BigInt {
+    var a: BigInt;
+{{{ a_limbs }}}
+    return a;
+}
+
+/// The curve constant d in Montgomery form.
+fn get_edwards_d() -> BigInt {
+    var d: BigInt;
+{{{ d_limbs }}}
+    return d;
+}
+
+/// Multiply a field element by the curve constant a.
+fn mul_by_a(x: BigInt) -> BigInt {
+    var v = x;
+{{#if a_is_minus_one}}
+    var p = get_p();
+    return field_sub(&p, &v);
+{{else}}
+    var a = get_a();
+    return montgomery_product(&a, &v);
+{{/if}}
+}
+
+/// dbl-2008-hwcd https://eprint.iacr.org/2008/522.pdf section 3.3, p6 (4M + 4S + 1D)
+/// https://hyperelliptic.org/EFD/g1p/auto-twisted-extended.html#doubling-dbl-2008-hwcd.
+fn point_double(p1: Point) -> Point {
     var p1x = p1.x;
     var p1y = p1.y;
     var p1z = p1.z;
 
-    var a = montgomery_product(&p1x, &p1x);
-    var b = montgomery_product(&p1y, &p1y);
+    var a = montgomery_square(&p1x);
+    var b = montgomery_square(&p1y);
     var a_p_b = field_add(&a, &b);
-    var z1_m_z1 = montgomery_product(&p1z, &p1z);
+    var z1_m_z1 = montgomery_square(&p1z);
     var c = field_add(&z1_m_z1, &z1_m_z1);
-    var p = get_p();
-    var d = field_sub(&p, &a);
+    var d = mul_by_a(a);
     var x1_m_y1 = field_add(&p1x, &p1y);
-    var x1y1_m_x1y1 = montgomery_product(&x1_m_y1, &x1_m_y1);
+    var x1y1_m_x1y1 = montgomery_square(&x1_m_y1);
     var e = field_sub(&x1y1_m_x1y1, &a_p_b);
     var g = field_add(&d, &b);
     var f = field_sub(&g, &c);
@@ -26,7 +79,9 @@ fn double_point(p1: Point) -> Point {
 
 /// add-2008-hwcd https://eprint.iacr.org/2008/522.pdf section 3.1, p5 (9M + 2D)
 /// https://hyperelliptic.org/EFD/g1p/auto-twisted-extended.html#addition-add-2008-hwcd.
-fn add_points(p1: Point, p2: Point) -> Point {
+/// The formula is complete when a is a square and d is not, so it needs no
+/// identity or doubling checks.
+fn point_add(p1: Point, p2: Point) -> Point {
     var p1x = p1.x;
     var p2x = p2.x;
     var p1y = p1.y;
@@ -49,13 +104,78 @@ fn add_points(p1: Point, p2: Point) -> Point {
     e = field_sub(&e, &a_p_b);
     var f = field_sub(&d, &c);
     var g = field_add(&d, &c);
-    var p = get_p();
-    var a_neg = field_sub(&p, &a);
-    var h = field_sub(&b, &a_neg);
+    var a_a = mul_by_a(a);
+    var h = field_sub(&b, &a_a);
+    var added_x = montgomery_product(&e, &f);
+    var added_y = montgomery_product(&g, &h);
+    var added_t = montgomery_product(&e, &h);
+    var added_z = montgomery_product(&f, &g);
+
+    return Point(added_x, added_y, added_t, added_z);
+}
+
+/// add-2008-hwcd with Z2 = 1, adding the affine point (x, y) in Montgomery
+/// form to p1. Computing T2 = xy here replaces the multiplication by Z2, so
+/// it saves one multiplication over point_from_affine and point_add.
+fn point_add_mixed(p1: Point, x: BigInt, y: BigInt) -> Point {
+    var p1x = p1.x;
+    var p2x = x;
+    var p1y = p1.y;
+    var p2y = y;
+    var p1t = p1.t;
+    var p2t = montgomery_product(&p2x, &p2y);
+    var d = p1.z;
+
+    var a = montgomery_product(&p1x, &p2x);
+    var b = montgomery_product(&p1y, &p2y);
+    var a_p_b = field_add(&a, &b);
+    var t2 = montgomery_product(&p1t, &p2t);
+    var EDWARDS_D = get_edwards_d();
+    var c = montgomery_product(&EDWARDS_D, &t2);
+    var xpy = field_add(&p1x, &p1y);
+    var xpy2 = field_add(&p2x, &p2y);
+    var e = montgomery_product(&xpy, &xpy2);
+    e = field_sub(&e, &a_p_b);
+    var f = field_sub(&d, &c);
+    var g = field_add(&d, &c);
+    var a_a = mul_by_a(a);
+    var h = field_sub(&b, &a_a);
     var added_x = montgomery_product(&e, &f);
     var added_y = montgomery_product(&g, &h);
     var added_t = montgomery_product(&e, &h);
     var added_z = montgomery_product(&f, &g);
 
     return Point(added_x, added_y, added_t, added_z);
-}
\ No newline at end of file
+}
+
+/// Point negation only involves multiplying the X and T coordinates by -1 in
+/// the field.
+fn negate_point(point: Point) -> Point {
+    var p = get_p();
+    var x = point.x;
+    var t = point.t;
+    var neg_x: BigInt;
+    var neg_t: BigInt;
+    bigint_sub(&p, &x, &neg_x);
+    bigint_sub(&p, &t, &neg_t);
+    return Point(neg_x, point.y, neg_t, point.z);
+}
+
+/// This double-and-add code is adapted from the ZPrize test harness:
+/// https://github.com/demox-labs/webgpu-msm/blob/main/src/reference/webgpu/wgsl/Curve.ts#L78.
+fn double_and_add(point: Point, scalar: u32) -> Point {
+    /// Set result to the neutral element.
+    var result: Point = POINT_IDENTITY;
+
+    var s = scalar;
+    var temp = point;
+
+    while (s != 0u) {
+        if ((s & 1u) == 1u) {
+            result = point_add(result, temp);
+        }
+        temp = point_double(temp);
+        s = s >> 1u;
+    }
+    return result;
+}
@@ -8,31 +8,77 @@
 /// Used as input buffers for the bucket sums from SMVP, but also repurposed to
 /// store the m points.
 @group(0) @binding(0)
-var<storage, read_write> bucket_sum_x: array<BigInt>;
+var<storage, read_write> bucket_sum_x: array<Coord>;
 @group(0) @binding(1)
-var<storage, read_write> bucket_sum_y: array<BigInt>;
+var<storage, read_write> bucket_sum_y: array<Coord>;
 @group(0) @binding(2)
-var<storage, read_write> bucket_sum_z: array<BigInt>;
+var<storage, read_write> bucket_sum_z: array<Coord>;
 
 /// Output buffers to store the g points.
 @group(0) @binding(3)
-var<storage, read_write> g_points_x: array<BigInt>;
+var<storage, read_write> g_points_x: array<Coord>;
 @group(0) @binding(4)
-var<storage, read_write> g_points_y: array<BigInt>;
+var<storage, read_write> g_points_y: array<Coord>;
 @group(0) @binding(5)
-var<storage, read_write> g_points_z: array<BigInt>;
+var<storage, read_write> g_points_z: array<Coord>;
 
+{{#if is_edwards}}
+@group(0) @binding(6)
+var<storage, read_write> bucket_sum_t: array<Coord>;
+@group(0) @binding(7)
+var<storage, read_write> g_points_t: array<Coord>;
+
+// Unfiform storage buffer.
+@group(0) @binding(8)
+var<uniform> params: vec3<u32>;
+{{else}}
 // Unfiform storage buffer.
 @group(0) @binding(6)
 var<uniform> params: vec3<u32>;
+{{/if}}
 
 
 fn load_bucket_sum(idx: u32) -> Point {
+{{#if is_edwards}}
+    return Point(
+        bucket_sum_x[idx],
+        bucket_sum_y[idx],
+        bucket_sum_t[idx],
+        bucket_sum_z[idx]
+    );
+{{else}}
     return Point(
         bucket_sum_x[idx],
         bucket_sum_y[idx],
         bucket_sum_z[idx]
     );
+{{/if}}
+}
+
+fn store_bucket_sum(idx: u32, point: Point) {
+    bucket_sum_x[idx] = point.x;
+    bucket_sum_y[idx] = point.y;
+    bucket_sum_z[idx] = point.z;
+{{#if is_edwards}}
+    bucket_sum_t[idx] = point.t;
+{{/if}}
+}
+
+fn load_g_point(idx: u32) -> Point {
+{{#if is_edwards}}
+    return Point(g_points_x[idx], g_points_y[idx], g_points_t[idx], g_points_z[idx]);
+{{else}}
+    return Point(g_points_x[idx], g_points_y[idx], g_points_z[idx]);
+{{/if}}
+}
+
+fn store_g_point(idx: u32, point: Point) {
+    g_points_x[idx] = point.x;
+    g_points_y[idx] = point.y;
+    g_points_z[idx] = point.z;
+{{#if is_edwards}}
+    g_points_t[idx] = point.t;
+{{/if}}
 }
 
 @compute
@@ -74,14 +120,10 @@ fn stage_1(@builtin(global_invocation_id) global_id: vec3<u32>) {
         g = point_add(g, m);
     }
 
-    bucket_sum_x[idx] = m.x;
-    bucket_sum_y[idx] = m.y;
-    bucket_sum_z[idx] = m.z;
+    store_bucket_sum(idx, m);
 
     let t = (subtask_idx / num_subtasks_per_bpr) * (num_threads_per_subtask * num_subtasks_per_bpr) + thread_id;
-    g_points_x[t] = g.x;
-    g_points_y[t] = g.y;
-    g_points_z[t] = g.z;
+    store_g_point(t, g);
 
     // {{{ recompile }}}
 }
@@ -114,19 +156,13 @@ fn stage_2(@builtin(global_invocation_id) global_id: vec3<u32>) {
     var m = load_bucket_sum(idx);
 
     let t = (subtask_idx / num_subtasks_per_bpr) * (num_threads_per_subtask * num_subtasks_per_bpr) + thread_id;
-    var g = Point(
-        g_points_x[t],
-        g_points_y[t],
-        g_points_z[t],
-    );
+    var g = load_g_point(t);
 
     /// Perform scalar mul on m and add the result to g.
     let s = buckets_per_thread * (num_threads_per_subtask - (thread_id % num_threads_per_subtask) - 1u);
     g = point_add(g, double_and_add(m, s));
 
-    g_points_x[t] = g.x;
-    g_points_y[t] = g.y;
-    g_points_z[t] = g.z;
+    store_g_point(t, g);
 
     {{{ recompile }}}
 }
\ No newline at end of file
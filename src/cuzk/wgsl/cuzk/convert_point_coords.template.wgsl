@@ -0,0 +1,103 @@
+{{> structs }}
+{{> bigint_funcs }}
+{{> field_funcs }}
+{{> barrett_funcs }}
+{{> montgomery_product_funcs }}
+{{ > extract_word_from_bytes_le_funcs }}
+{{#if glv}}
+
+/// The cube root of unity that scales x, as a canonical field element.
+fn get_beta() -> BigInt {
+    var beta: BigInt;
+{{{ beta_limbs }}}
+    return beta;
+}
+{{/if}}
+
+/// Number of u32s in one serialized coordinate.
+const COORD_U32S = {{ coord_u32s }}u;
+
+/// Number of u32s in one serialized base field component of a coordinate.
+const COMPONENT_U32S = {{ component_u32s }}u;
+
+/// Input storage buffers.
+@group(0) @binding(0)
+var<storage, read> coords: array<u32>;
+
+/// Output storage buffers.
+@group(0) @binding(1)
+var<storage, read_write> point_x: array<Coord>;
+@group(0) @binding(2)
+var<storage, read_write> point_y: array<Coord>;
+
+/// Uniform storage buffer.
+@group(0) @binding(3)
+var<uniform> input_size: u32;
+
+/// The constant that takes an uploaded component to Montgomery form: R for
+/// canonical components, or R / R' for components uploaded in a host
+/// Montgomery form of radix R'.
+fn get_to_mont() -> BigInt {
+    var r: BigInt;
+{{{ to_mont_limbs }}}
+    return r;
+}
+
+/// Read the base field component serialized at coords[offset] and convert it
+/// to Montgomery form.
+fn component_to_mont(offset: u32) -> BigInt {
+    /// Store the component as 16-bit halves, most significant half first,
+    /// for easier indexing.
+    var bytes: array<u32, {{ num_halves }}>;
+    for (var i = 0u; i < COMPONENT_U32S; i++) {
+        let word = coords[offset + i];
+        bytes[NUM_HALVES - 1u - (i * 2)] = word & 65535u;
+        bytes[NUM_HALVES - 1u - (i * 2) - 1] = word >> 16u;
+    }
+
+    /// Convert the halves to a BigInt with word_size limbs.
+    var bigint: BigInt;
+    for (var i = 0u; i < NUM_WORDS; i ++) {
+        bigint.limbs[i] = extract_word_from_bytes_le(bytes, i, WORD_SIZE);
+    }
+
+    var r = get_to_mont();
+    return field_mul(&bigint, &r);
+}
+
+/// The point conversion half of decompose_scalars.template.wgsl. It is run
+/// once per base set in fixed-base mode, so that later MSMs only need to
+/// decompose the scalars.
+@compute
+@workgroup_size({{ workgroup_size }})
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let gidx = global_id.x;
+    let gidy = global_id.y;
+    let id = gidx * {{ num_y_workgroups }} + gidy;
+
+    /// Convert the x and y coordinates to Montgomery form.
+    let offset = id * 2u * COORD_U32S;
+{{#if is_ext2}}
+    point_x[id] = Fq2(
+        component_to_mont(offset),
+        component_to_mont(offset + COMPONENT_U32S)
+    );
+    point_y[id] = Fq2(
+        component_to_mont(offset + COORD_U32S),
+        component_to_mont(offset + COORD_U32S + COMPONENT_U32S)
+    );
+{{else}}
+    point_x[id] = component_to_mont(offset);
+    point_y[id] = component_to_mont(offset + COORD_U32S);
+{{/if}}
+{{#if glv}}
+
+    /// The endomorphism maps (x, y) to (beta * x, y), in the second half of
+    /// the point buffers.
+    let num_points = input_size / 2u;
+    var x = point_x[id];
+    var beta = get_beta();
+    point_x[id + num_points] = field_mul(&x, &beta);
+    point_y[id + num_points] = point_y[id];
+{{/if}}
+}
@@ -4,6 +4,22 @@
 {{> barrett_funcs }}
 {{> montgomery_product_funcs }}
 {{ > extract_word_from_bytes_le_funcs }}
+{{#if glv}}
+{{> glv_funcs }}
+
+/// The cube root of unity that scales x, as a canonical field element.
+fn get_beta() -> BigInt {
+    var beta: BigInt;
+{{{ beta_limbs }}}
+    return beta;
+}
+{{/if}}
+
+/// Number of u32s in one serialized coordinate.
+const COORD_U32S = {{ coord_u32s }}u;
+
+/// Number of u32s in one serialized base field component of a coordinate.
+const COMPONENT_U32S = {{ component_u32s }}u;
 
 /// Input storage buffers.
 @group(0) @binding(0)
@@ -13,9 +29,9 @@ var<storage, read> scalars: array<u32>;
 
 /// Output storage buffers.
 @group(0) @binding(2)
-var<storage, read_write> point_x: array<BigInt>;
+var<storage, read_write> point_x: array<Coord>;
 @group(0) @binding(3)
-var<storage, read_write> point_y: array<BigInt>;
+var<storage, read_write> point_y: array<Coord>;
 @group(0) @binding(4)
 var<storage, read_write> chunks: array<u32>;
 
@@ -27,6 +43,37 @@ const NUM_SUBTASKS = {{ num_subtasks }}u;
 /// Scalar chunk bitwidth.
 const CHUNK_SIZE = {{ chunk_size }}u;
 
+/// The constant that takes an uploaded component to Montgomery form: R for
+/// canonical components, or R / R' for components uploaded in a host
+/// Montgomery form of radix R'.
+fn get_to_mont() -> BigInt {
+    var r: BigInt;
+{{{ to_mont_limbs }}}
+    return r;
+}
+
+/// Read the base field component serialized at coords[offset] and convert it
+/// to Montgomery form.
+fn component_to_mont(offset: u32) -> BigInt {
+    /// Store the component as 16-bit halves, most significant half first,
+    /// for easier indexing.
+    var bytes: array<u32, {{ num_halves }}>;
+    for (var i = 0u; i < COMPONENT_U32S; i++) {
+        let word = coords[offset + i];
+        bytes[NUM_HALVES - 1u - (i * 2)] = word & 65535u;
+        bytes[NUM_HALVES - 1u - (i * 2) - 1] = word >> 16u;
+    }
+
+    /// Convert the halves to a BigInt with word_size limbs.
+    var bigint: BigInt;
+    for (var i = 0u; i < NUM_WORDS; i ++) {
+        bigint.limbs[i] = extract_word_from_bytes_le(bytes, i, WORD_SIZE);
+    }
+
+    var r = get_to_mont();
+    return field_mul(&bigint, &r);
+}
+
 @compute
 @workgroup_size({{ workgroup_size }})
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
@@ -36,55 +83,60 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 
     let INPUT_SIZE = input_size;
 
-    // Store the x and y coordinates as byte arrays ([x8, y8, x8, y8]) 
-    /// for easier indexing, where id = [0, ..., num_points].
-    var x_bytes: array<u32, 16>;
-    var y_bytes: array<u32, 16>;
-    for (var i = 0u; i < 8u; i++) {
-        let offset = id * 16u;
-
-        let x = coords[offset + i];
-        x_bytes[15 - (i * 2)] = x & 65535u;
-        x_bytes[15 - (i * 2) - 1] = x >> 16u;
-
-        let y = coords[offset + 8 + i];
-        y_bytes[15 - (i * 2)] = y & 65535u;
-        y_bytes[15 - (i * 2) - 1] = y >> 16u;
-    }
-
-    /// Convert the byte arrays to BigInts with word_size limbs.
-    var x_bigint: BigInt;
-    var y_bigint: BigInt;
-    for (var i = 0u; i < NUM_WORDS - 1u; i ++) {
-        x_bigint.limbs[i] = extract_word_from_bytes_le(x_bytes, i, WORD_SIZE);
-        y_bigint.limbs[i] = extract_word_from_bytes_le(y_bytes, i, WORD_SIZE);
-    }
-
-    let shift = (((NUM_WORDS * WORD_SIZE - 256u) + 16u) - WORD_SIZE);
-    x_bigint.limbs[NUM_WORDS - 1u] = x_bytes[0] >> shift;
-    y_bigint.limbs[NUM_WORDS - 1u] = y_bytes[0] >> shift;
-
-    /// Convert x and y coordinates to Montgomery form.
-    var r = get_r();
-    point_x[id] = field_mul(&x_bigint, &r);
-    point_y[id] = field_mul(&y_bigint, &r);
+    /// Convert the x and y coordinates to Montgomery form.
+    let offset = id * 2u * COORD_U32S;
+{{#if is_ext2}}
+    point_x[id] = Fq2(
+        component_to_mont(offset),
+        component_to_mont(offset + COMPONENT_U32S)
+    );
+    point_y[id] = Fq2(
+        component_to_mont(offset + COORD_U32S),
+        component_to_mont(offset + COORD_U32S + COMPONENT_U32S)
+    );
+{{else}}
+    point_x[id] = component_to_mont(offset);
+    point_y[id] = component_to_mont(offset + COORD_U32S);
+{{/if}}
+{{#if glv}}
+
+    /// The endomorphism maps (x, y) to (beta * x, y), in the second half of
+    /// the point buffers.
+    var x = point_x[id];
+    var beta = get_beta();
+    point_x[id + INPUT_SIZE / 2u] = field_mul(&x, &beta);
+    point_y[id + INPUT_SIZE / 2u] = point_y[id];
+{{/if}}
 
     /// Decompose scalars.
-    var scalar_bytes: array<u32, 16>;
+    /// The halves above the 32 scalar bytes stay zero.
+    var scalar_bytes: array<u32, {{ num_halves }}>;
     for (var i = 0u; i < 8u; i++) {
         let s = scalars[id * 8 + i];
         let hi = s >> 16u;
         let lo = s & 65535u;
-        scalar_bytes[15 - (i * 2)] = lo;
-        scalar_bytes[15 - (i * 2) - 1] = hi;
+        scalar_bytes[NUM_HALVES - 1u - (i * 2)] = lo;
+        scalar_bytes[NUM_HALVES - 1u - (i * 2) - 1] = hi;
     }
 
+{{#if glv}}
+    /// Split the scalar in two with the GLV endomorphism. The digits of k1
+    /// go to the column of the point, and those of k2 to the column of its
+    /// image, INPUT_SIZE / 2 columns further.
+    let halves = glv_split(scalar_bytes);
+    let k1_digits = glv_digits(halves[0]);
+    let k2_digits = glv_digits(halves[1]);
+    for (var i = 0u; i < NUM_SUBTASKS; i++) {
+        let offset = i * INPUT_SIZE;
+        chunks[id + offset] = k1_digits[i];
+        chunks[id + INPUT_SIZE / 2u + offset] = k2_digits[i];
+    }
+{{else}}
     /// Extract scalar chunks and store them in chunks_arr.
     var chunks_arr: array<u32, {{ num_subtasks }}>;
     for (var i = 0u; i < NUM_SUBTASKS; i++) {
         chunks_arr[i] = extract_word_from_bytes_le(scalar_bytes, i, CHUNK_SIZE);
     }
-    chunks_arr[NUM_SUBTASKS - 1] = scalar_bytes[0] >> (((NUM_SUBTASKS * CHUNK_SIZE - 256u) + 16u) - CHUNK_SIZE);
 
     /// Iterate through chunks_arr to compute the signed indices.
     let l = {{ num_columns }}u;
@@ -110,4 +162,5 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         /// sign of the original index.
         chunks[id + offset] = u32(signed_slices[i]) + s;
     }
+{{/if}}
 }
@@ -0,0 +1,88 @@
+{{ > extract_word_from_bytes_le_funcs }}
+{{#if glv}}
+{{> glv_funcs }}
+{{/if}}
+
+/// Input storage buffers.
+@group(0) @binding(0)
+var<storage, read> scalars: array<u32>;
+
+/// Output storage buffers.
+@group(0) @binding(1)
+var<storage, read_write> chunks: array<u32>;
+
+/// Uniform storage buffer.
+@group(0) @binding(2)
+var<uniform> input_size: u32;
+const NUM_SUBTASKS = {{ num_subtasks }}u;
+
+/// Scalar chunk bitwidth.
+const CHUNK_SIZE = {{ chunk_size }}u;
+
+/// The scalar decomposition half of decompose_scalars.template.wgsl, used
+/// in fixed-base mode where the points are already resident on the GPU.
+@compute
+@workgroup_size({{ workgroup_size }})
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let gidx = global_id.x;
+    let gidy = global_id.y;
+    let id = gidx * {{ num_y_workgroups }} + gidy;
+
+    let INPUT_SIZE = input_size;
+
+    /// Decompose scalars.
+    /// The halves above the 32 scalar bytes stay zero.
+    var scalar_bytes: array<u32, {{ num_halves }}>;
+    for (var i = 0u; i < 8u; i++) {
+        let s = scalars[id * 8 + i];
+        let hi = s >> 16u;
+        let lo = s & 65535u;
+        scalar_bytes[NUM_HALVES - 1u - (i * 2)] = lo;
+        scalar_bytes[NUM_HALVES - 1u - (i * 2) - 1] = hi;
+    }
+
+{{#if glv}}
+    /// Split the scalar in two with the GLV endomorphism. The digits of k1
+    /// go to the column of the point, and those of k2 to the column of its
+    /// image, INPUT_SIZE / 2 columns further.
+    let halves = glv_split(scalar_bytes);
+    let k1_digits = glv_digits(halves[0]);
+    let k2_digits = glv_digits(halves[1]);
+    for (var i = 0u; i < NUM_SUBTASKS; i++) {
+        let offset = i * INPUT_SIZE;
+        chunks[id + offset] = k1_digits[i];
+        chunks[id + INPUT_SIZE / 2u + offset] = k2_digits[i];
+    }
+{{else}}
+    /// Extract scalar chunks and store them in chunks_arr.
+    var chunks_arr: array<u32, {{ num_subtasks }}>;
+    for (var i = 0u; i < NUM_SUBTASKS; i++) {
+        chunks_arr[i] = extract_word_from_bytes_le(scalar_bytes, i, CHUNK_SIZE);
+    }
+
+    /// Iterate through chunks_arr to compute the signed indices.
+    let l = {{ num_columns }}u;
+    let s = l / 2u;
+
+    var signed_slices: array<i32, {{ num_subtasks }}>;
+    var carry = 0u;
+    for (var i = 0u; i < NUM_SUBTASKS; i ++) {
+        signed_slices[i] = i32(chunks_arr[i] + carry);
+        if (signed_slices[i] >= i32(s)) {
+            signed_slices[i] = (i32(l) - signed_slices[i]) * -1i;
+            carry = 1u;
+        } else {
+            carry = 0u;
+        }
+    }
+
+    for (var i = 0u; i < NUM_SUBTASKS; i++) {
+        let offset = i * INPUT_SIZE;
+
+        /// Note that we add s (half_num_columns) to the bucket index so we
+        /// don't store negative values, while retaining information about the
+        /// sign of the original index.
+        chunks[id + offset] = u32(signed_slices[i]) + s;
+    }
+{{/if}}
+}
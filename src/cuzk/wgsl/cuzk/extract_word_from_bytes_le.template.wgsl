@@ -1,25 +1,27 @@
+/// Number of 16-bit halves in the byte arrays passed to
+/// extract_word_from_bytes_le.
+const NUM_HALVES = {{ num_halves }}u;
+
+/// Extract the word_idx-th chunk_size-bit word of an integer stored as 16-bit
+/// halves, most significant half first. Bits past the end of the input are
+/// read as zero, so the top word may be only partially filled.
 fn extract_word_from_bytes_le(
-    input: array<u32, 16>,
+    input: array<u32, {{ num_halves }}>,
     word_idx: u32,
     chunk_size: u32
 ) -> u32 {
-    var word = 0u;
-    let start_byte_idx = 15u - ((word_idx * chunk_size + chunk_size) / 16u);
-    let end_byte_idx = 15u - ((word_idx * chunk_size) / 16u);
+    let start_bit = word_idx * chunk_size;
+    let lo_half = start_bit / 16u;
+    let hi_half = (start_bit + chunk_size - 1u) / 16u;
+    let offset = start_bit % 16u;
 
-    let start_byte_offset = (word_idx * chunk_size + chunk_size) % 16u;
-    let end_byte_offset = (word_idx * chunk_size) % 16u;
-
-    var mask = 0u;
-    if (start_byte_offset > 0u) {
-        mask = (2u << (start_byte_offset - 1u)) - 1u;
+    var word = 0u;
+    if (lo_half < NUM_HALVES) {
+        word = input[NUM_HALVES - 1u - lo_half] >> offset;
     }
-    if (start_byte_idx == end_byte_idx) {
-        word = (input[start_byte_idx] & mask) >> end_byte_offset;
-    } else {
-        word = (input[start_byte_idx] & mask) << (16u - end_byte_offset);
-        word += input[end_byte_idx] >> end_byte_offset;
+    if (hi_half != lo_half && hi_half < NUM_HALVES) {
+        word |= input[NUM_HALVES - 1u - hi_half] << (16u - offset);
     }
 
-    return word;
-}
\ No newline at end of file
+    return word & ((1u << chunk_size) - 1u);
+}
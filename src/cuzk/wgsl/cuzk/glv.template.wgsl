@@ -0,0 +1,140 @@
+/// Number of 16-bit limbs of the two's complement integers the GLV
+/// decomposition works on.
+const GLV_LIMBS = {{ glv_limbs }}u;
+
+/// Precision of GLV_G1 and GLV_G2, in 16-bit limbs.
+const GLV_SHIFT_LIMBS = {{ glv_shift_limbs }}u;
+
+alias GlvInt = array<u32, {{ glv_limbs }}>;
+
+/// Magnitudes and signs of the rounding constants of the lattice basis.
+const GLV_G1 = GlvInt({{{ glv_g1_limbs }}});
+const GLV_G1_NEGATIVE = {{ glv_g1_negative }};
+const GLV_G2 = GlvInt({{{ glv_g2_limbs }}});
+const GLV_G2_NEGATIVE = {{ glv_g2_negative }};
+
+/// The short lattice basis (a1, b1), (a2, b2).
+const GLV_A1 = GlvInt({{{ glv_a1_limbs }}});
+const GLV_B1 = GlvInt({{{ glv_b1_limbs }}});
+const GLV_A2 = GlvInt({{{ glv_a2_limbs }}});
+const GLV_B2 = GlvInt({{{ glv_b2_limbs }}});
+
+/// a * b modulo 2^(16 * GLV_LIMBS).
+fn glv_mul(a: GlvInt, b: GlvInt) -> GlvInt {
+    var x = a;
+    var y = b;
+    var res: GlvInt;
+    for (var i = 0u; i < GLV_LIMBS; i++) {
+        if (x[i] == 0u) {
+            continue;
+        }
+        var carry = 0u;
+        for (var j = 0u; i + j < GLV_LIMBS; j++) {
+            /// At most (2^16 - 1) + (2^16 - 1)^2 + (2^16 - 1) < 2^32.
+            let t = res[i + j] + x[i] * y[j] + carry;
+            res[i + j] = t & 65535u;
+            carry = t >> 16u;
+        }
+    }
+    return res;
+}
+
+/// a - b modulo 2^(16 * GLV_LIMBS).
+fn glv_sub(a: GlvInt, b: GlvInt) -> GlvInt {
+    var x = a;
+    var y = b;
+    var res: GlvInt;
+    var borrow = 0u;
+    for (var i = 0u; i < GLV_LIMBS; i++) {
+        let t = x[i] + 65536u - y[i] - borrow;
+        res[i] = t & 65535u;
+        borrow = 1u - (t >> 16u);
+    }
+    return res;
+}
+
+/// Whether the sign bit of a is set.
+fn glv_is_negative(a: GlvInt) -> bool {
+    var x = a;
+    return (x[GLV_LIMBS - 1u] >> 15u) == 1u;
+}
+
+/// round(g * k / 2^(16 * GLV_SHIFT_LIMBS)) for g = +-magnitude, with the
+/// magnitude of the result rounded down.
+fn glv_round(magnitude: GlvInt, negative: bool, k: GlvInt) -> GlvInt {
+    var product = glv_mul(magnitude, k);
+    var res: GlvInt;
+    for (var i = 0u; i + GLV_SHIFT_LIMBS < GLV_LIMBS; i++) {
+        res[i] = product[i + GLV_SHIFT_LIMBS];
+    }
+    if (negative) {
+        return glv_sub(GlvInt(), res);
+    }
+    return res;
+}
+
+/// One half of a GLV decomposition, as 16-bit halves, most significant half
+/// first, for extract_word_from_bytes_le.
+struct GlvHalf {
+    magnitude: array<u32, {{ num_halves }}>,
+    negative: bool
+}
+
+fn glv_half(a: GlvInt) -> GlvHalf {
+    var half: GlvHalf;
+    half.negative = glv_is_negative(a);
+    var x = a;
+    if (half.negative) {
+        x = glv_sub(GlvInt(), a);
+    }
+    for (var i = 0u; i < NUM_HALVES && i < GLV_LIMBS; i++) {
+        half.magnitude[NUM_HALVES - 1u - i] = x[i];
+    }
+    return half;
+}
+
+/// Split the scalar stored as 16-bit halves, most significant half first,
+/// into k1 + k2 * lambda with k1 = k - c1 * a1 - c2 * a2 and
+/// k2 = -c1 * b1 - c2 * b2, where c1 and c2 round the coordinates of (k, 0)
+/// in the lattice basis.
+fn glv_split(scalar_bytes: array<u32, {{ num_halves }}>) -> array<GlvHalf, 2> {
+    var bytes = scalar_bytes;
+    var k: GlvInt;
+    for (var i = 0u; i < NUM_HALVES && i < GLV_LIMBS; i++) {
+        k[i] = bytes[NUM_HALVES - 1u - i];
+    }
+
+    let c1 = glv_round(GLV_G1, GLV_G1_NEGATIVE, k);
+    let c2 = glv_round(GLV_G2, GLV_G2_NEGATIVE, k);
+    let k1 = glv_sub(glv_sub(k, glv_mul(c1, GLV_A1)), glv_mul(c2, GLV_A2));
+    let k2 = glv_sub(glv_sub(GlvInt(), glv_mul(c1, GLV_B1)), glv_mul(c2, GLV_B2));
+    return array<GlvHalf, 2>(glv_half(k1), glv_half(k2));
+}
+
+/// The signed CHUNK_SIZE-bit digits of a GLV half, offset by half the number
+/// of columns.
+///
+/// The digits of a negative half are those of its magnitude, negated. Its
+/// magnitude is recoded into digits in (-s, s] instead of [-s, s), so that
+/// the negated digits stay in the [-s, s) range of the bucket indices.
+fn glv_digits(half: GlvHalf) -> array<u32, {{ num_subtasks }}> {
+    let l = {{ num_columns }}u;
+    let s = l / 2u;
+
+    var digits: array<u32, {{ num_subtasks }}>;
+    var carry = 0u;
+    for (var i = 0u; i < NUM_SUBTASKS; i++) {
+        var digit = i32(extract_word_from_bytes_le(half.magnitude, i, CHUNK_SIZE) + carry);
+        if (digit > i32(s) || (digit == i32(s) && !half.negative)) {
+            digit -= i32(l);
+            carry = 1u;
+        } else {
+            carry = 0u;
+        }
+        if (half.negative) {
+            digit = -digit;
+        }
+        digits[i] = u32(digit + i32(s));
+    }
+    return digits;
+}
@@ -0,0 +1,133 @@
+{{> structs }}
+{{> montgomery_product_funcs }}
+{{> field_funcs }}
+{{> barrett_funcs }}
+{{> bigint_funcs }}
+{{> ec_funcs }}
+
+/// Number of subtasks, i.e. scalar windows.
+const NUM_SUBTASKS = {{ num_subtasks }}u;
+
+/// Number of bits per scalar window.
+const CHUNK_SIZE = {{ chunk_size }}u;
+
+/// Number of g points the BPR shader writes per subtask.
+const NUM_G_POINTS = {{ num_g_points }}u;
+
+/// Number of coordinates of a Point.
+const NUM_COORDS = {{ num_coords }}u;
+
+/// Input buffers holding the g points from the BPR shader.
+@group(0) @binding(0)
+var<storage, read> g_points_x: array<Coord>;
+@group(0) @binding(1)
+var<storage, read> g_points_y: array<Coord>;
+@group(0) @binding(2)
+var<storage, read> g_points_z: array<Coord>;
+{{#if is_edwards}}
+@group(0) @binding(3)
+var<storage, read> g_points_t: array<Coord>;
+
+/// The window sums, in Montgomery form, as NUM_COORDS consecutive
+/// coordinates per subtask.
+@group(0) @binding(4)
+var<storage, read_write> windows: array<Coord>;
+
+/// The MSM result, converted out of Montgomery form.
+@group(0) @binding(5)
+var<storage, read_write> result: array<Coord>;
+{{else}}
+
+/// The window sums, in Montgomery form, as NUM_COORDS consecutive
+/// coordinates per subtask.
+@group(0) @binding(3)
+var<storage, read_write> windows: array<Coord>;
+
+/// The MSM result, converted out of Montgomery form.
+@group(0) @binding(4)
+var<storage, read_write> result: array<Coord>;
+{{/if}}
+
+fn load_g_point(idx: u32) -> Point {
+{{#if is_edwards}}
+    return Point(g_points_x[idx], g_points_y[idx], g_points_t[idx], g_points_z[idx]);
+{{else}}
+    return Point(g_points_x[idx], g_points_y[idx], g_points_z[idx]);
+{{/if}}
+}
+
+fn load_window(idx: u32) -> Point {
+    let offset = idx * NUM_COORDS;
+{{#if is_edwards}}
+    return Point(windows[offset], windows[offset + 1u], windows[offset + 3u], windows[offset + 2u]);
+{{else}}
+    return Point(windows[offset], windows[offset + 1u], windows[offset + 2u]);
+{{/if}}
+}
+
+fn store_window(idx: u32, point: Point) {
+    let offset = idx * NUM_COORDS;
+    windows[offset] = point.x;
+    windows[offset + 1u] = point.y;
+    windows[offset + 2u] = point.z;
+{{#if is_edwards}}
+    windows[offset + 3u] = point.t;
+{{/if}}
+}
+
+fn from_mont(x: BigInt) -> BigInt {
+    var a = x;
+    var one = ONE;
+    return montgomery_product(&a, &one);
+}
+
+fn coord_from_mont(c: Coord) -> Coord {
+{{#if is_ext2}}
+    return Fq2(from_mont(c.c0), from_mont(c.c1));
+{{else}}
+    return from_mont(c);
+{{/if}}
+}
+
+/// Sum the g points of each subtask into its window sum.
+@compute
+@workgroup_size({{ workgroup_size }})
+fn window_sums(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let subtask_idx = global_id.x;
+    if (subtask_idx >= NUM_SUBTASKS) {
+        return;
+    }
+
+    var sum = POINT_IDENTITY;
+    let offset = subtask_idx * NUM_G_POINTS;
+    for (var i = 0u; i < NUM_G_POINTS; i++) {
+        sum = point_add(sum, load_g_point(offset + i));
+    }
+    store_window(subtask_idx, sum);
+
+    {{{ recompile }}}
+}
+
+/// Combine the window sums with Horner's method (Formula 3 of the cuZK
+/// paper) and convert the result out of Montgomery form, so that only one
+/// point has to be read back.
+@compute
+@workgroup_size(1)
+fn horner() {
+    var acc = load_window(NUM_SUBTASKS - 1u);
+    for (var i = NUM_SUBTASKS - 1u; i > 0u; i--) {
+        for (var j = 0u; j < CHUNK_SIZE; j++) {
+            acc = point_double(acc);
+        }
+        acc = point_add(acc, load_window(i - 1u));
+    }
+
+    result[0] = coord_from_mont(acc.x);
+    result[1] = coord_from_mont(acc.y);
+    result[2] = coord_from_mont(acc.z);
+{{#if is_edwards}}
+    result[3] = coord_from_mont(acc.t);
+{{/if}}
+
+    {{{ recompile }}}
+}
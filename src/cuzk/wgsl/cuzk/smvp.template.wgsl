@@ -11,21 +11,47 @@ var<storage, read> row_ptr: array<u32>;
 @group(0) @binding(1)
 var<storage, read> val_idx: array<u32>;
 @group(0) @binding(2)
-var<storage, read> new_point_x: array<BigInt>;
+var<storage, read> new_point_x: array<Coord>;
 @group(0) @binding(3)
-var<storage, read> new_point_y: array<BigInt>;
+var<storage, read> new_point_y: array<Coord>;
 
 /// Output storage buffers.
 @group(0) @binding(4)
-var<storage, read_write> bucket_x: array<BigInt>;
+var<storage, read_write> bucket_x: array<Coord>;
 @group(0) @binding(5)
-var<storage, read_write> bucket_y: array<BigInt>;
+var<storage, read_write> bucket_y: array<Coord>;
 @group(0) @binding(6)
-var<storage, read_write> bucket_z: array<BigInt>;
+var<storage, read_write> bucket_z: array<Coord>;
+
+{{#if is_edwards}}
+@group(0) @binding(7)
+var<storage, read_write> bucket_t: array<Coord>;
 
+/// Uniform storage buffer.
+@group(0) @binding(8)
+var<uniform> params: vec4<u32>;
+{{else}}
 /// Uniform storage buffer.
 @group(0) @binding(7)
 var<uniform> params: vec4<u32>;
+{{/if}}
+
+fn load_bucket(idx: u32) -> Point {
+{{#if is_edwards}}
+    return Point(bucket_x[idx], bucket_y[idx], bucket_t[idx], bucket_z[idx]);
+{{else}}
+    return Point(bucket_x[idx], bucket_y[idx], bucket_z[idx]);
+{{/if}}
+}
+
+fn store_bucket(idx: u32, point: Point) {
+    bucket_x[idx] = point.x;
+    bucket_y[idx] = point.y;
+    bucket_z[idx] = point.z;
+{{#if is_edwards}}
+    bucket_t[idx] = point.t;
+{{/if}}
+}
 
 
 @compute
@@ -62,6 +88,24 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
             row_idx = 0u;
         }
 
+        /// Recover the bucket index. Since we've added half_num_columns to
+        /// each scalar chunk in convert_point_coords_and_decompose_scalars.template.wgsl,
+        /// we know if the original bucket index is negative if it is less than
+        /// half_num_columns.
+        var bucket_idx = 0u;
+        if (h > row_idx) {
+            bucket_idx = h - row_idx;
+        } else {
+            bucket_idx = row_idx - h;
+        }
+
+        /// Bucket 0 holds the zero scalar chunks, including those of the
+        /// zero padding. It does not contribute to the result, so skip
+        /// summing its points.
+        if (bucket_idx == 0u) {
+            continue;
+        }
+
         let row_begin = row_ptr[rp_offset + row_idx];
         let row_end = row_ptr[rp_offset + row_idx + 1u];
         var sum = inf;
@@ -70,48 +114,245 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         for (var k = row_begin; k < row_end; k ++) {
             let idx = val_idx[(subtask_idx + subtask_offset) * input_size + k];
 
-            var x = new_point_x[idx];
-            var y = new_point_y[idx];
-            var z = get_r();
-
-            let pt = Point(x, y, z);
-            sum = point_add(sum, pt);
+            sum = point_add_mixed(sum, new_point_x[idx], new_point_y[idx]);
         }
 
-        /// Negate the point if the recovered bucket index is negative.
-        /// Since we've added half_num_columns to each scalar chunk in
-        /// convert_point_coords_and_decompose_scalars.template.wgsl, we know if
-        /// the original bucket index is negative if it is less than
-        /// half_num_columns.
-        var bucket_idx = 0u;
+        /// Negate the point if the original bucket index is negative.
         if (h > row_idx) {
-            bucket_idx = h - row_idx;
             sum = negate_point(sum);
-        } else {
-            bucket_idx = row_idx - h;
         }
 
         let bi = id + subtask_offset * h;
-        if (bucket_idx > 0u) {
-            /// Store the result in buckets[thread_id]. Each thread must use
-            /// a unique storage location (thread_id) to prevent race conditions.
-            if (j == 1) {
-                /// Since the point has been set, add to it.
-                let bucket = Point(
-                    bucket_x[bi],
-                    bucket_y[bi],
-                    bucket_z[bi]
-                );
-                sum = point_add(bucket, sum);
+        /// Store the result in buckets[thread_id]. Each thread must use
+        /// a unique storage location (thread_id) to prevent race conditions.
+        if (j == 1) {
+            /// Since the point has been set, add to it.
+            sum = point_add(load_bucket(bi), sum);
+        }
+
+        /// Set the point. Since no point has been set when j == 0, we can just
+        /// overwrite the data.
+        store_bucket(bi, sum);
+    }
+
+    {{{ recompile }}}
+}
+
+{{#if batch_affine}}
+const WORKGROUP_SIZE = {{ workgroup_size }}u;
+
+/// What a row does with its next point in a batch-affine step.
+const OP_NONE = 0u;
+const OP_SET = 1u;
+const OP_ADD = 2u;
+const OP_DOUBLE = 3u;
+const OP_INF = 4u;
+
+/// Prefix and suffix products of the slope denominators of the workgroup.
+var<workgroup> products: array<BigInt, {{ workgroup_size }}>;
+
+/// The inverse of the product of all the denominators of a step.
+var<workgroup> inv_total: BigInt;
+
+/// The length of the longest row of the workgroup, i.e. the number of steps.
+var<workgroup> max_row_len: atomic<u32>;
+var<workgroup> num_steps: u32;
+
+/// Sums the same buckets as main, but keeps the bucket sums in affine
+/// coordinates, adding one point to each of the two rows of a thread per
+/// step. An affine addition needs the inverse of its slope denominator, so
+/// the denominators of the whole workgroup are inverted at once with
+/// Montgomery's trick: a prefix and a suffix product scan around a single
+/// field inversion, which one thread computes for the workgroup.
+@compute
+@workgroup_size({{ workgroup_size }})
+fn batch_affine(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+) {
+    let input_size = params[0];
+    let num_y_workgroups = params[1];
+    let num_z_workgroups = params[2];
+    let subtask_offset = params[3];
+
+    let gidx = global_id.x;
+    let gidy = global_id.y;
+    let gidz = global_id.z;
+    let id = (gidx * num_y_workgroups + gidy) * num_z_workgroups + gidz;
+    let lid = local_id.x;
+
+    let num_columns = {{ num_columns }}u;
+    let h = {{ half_num_columns }}u;
+    let subtask_idx = (id / h);
+    let rp_offset = (subtask_idx + subtask_offset) * (num_columns + 1u);
+    let vi_offset = (subtask_idx + subtask_offset) * input_size;
+
+    /// The same two rows as in main. The second row of the first thread of a
+    /// subtask is bucket 0, which is left empty.
+    var row_idxs = array<u32, 2>((id % h) + h, h - (id % h));
+    if (id % h == 0u) {
+        row_idxs[0] = 0u;
+    }
+    var row_begins: array<u32, 2>;
+    var row_lens: array<u32, 2>;
+    for (var j = 0u; j < 2u; j ++) {
+        if (row_idxs[j] != h) {
+            row_begins[j] = row_ptr[rp_offset + row_idxs[j]];
+            row_lens[j] = row_ptr[rp_offset + row_idxs[j] + 1u] - row_begins[j];
+        }
+    }
+
+    atomicMax(&max_row_len, max(row_lens[0], row_lens[1]));
+    workgroupBarrier();
+    if (lid == 0u) {
+        num_steps = atomicLoad(&max_row_len);
+    }
+    let steps = workgroupUniformLoad(&num_steps);
+
+    var acc_x: array<BigInt, 2>;
+    var acc_y: array<BigInt, 2>;
+    var acc_inf = array<bool, 2>(true, true);
+
+    for (var s = 0u; s < steps; s ++) {
+        var ops = array<u32, 2>(OP_NONE, OP_NONE);
+        var pt_x: array<BigInt, 2>;
+        var pt_y: array<BigInt, 2>;
+        var dens = array<BigInt, 2>(get_r(), get_r());
+
+        for (var j = 0u; j < 2u; j ++) {
+            if (s >= row_lens[j]) {
+                continue;
+            }
+            let idx = val_idx[vi_offset + row_begins[j] + s];
+            var x2 = new_point_x[idx];
+            var y2 = new_point_y[idx];
+            var x1 = acc_x[j];
+            var y1 = acc_y[j];
+            pt_x[j] = x2;
+            pt_y[j] = y2;
+            if (acc_inf[j]) {
+                ops[j] = OP_SET;
+            } else if (!field_eq(x1, x2)) {
+                ops[j] = OP_ADD;
+                dens[j] = field_sub(&x2, &x1);
+            } else if (field_eq(y1, y2) && !field_eq(y1, ZERO)) {
+                ops[j] = OP_DOUBLE;
+                dens[j] = field_add(&y1, &y1);
+            } else {
+                ops[j] = OP_INF;
             }
+        }
+
+        var d0 = dens[0];
+        var d1 = dens[1];
+        var q = montgomery_product(&d0, &d1);
+
+        /// Inclusive prefix products of the workgroup.
+        products[lid] = q;
+        workgroupBarrier();
+        for (var offset = 1u; offset < WORKGROUP_SIZE; offset <<= 1u) {
+            var t = get_r();
+            if (lid >= offset) {
+                t = products[lid - offset];
+            }
+            workgroupBarrier();
+            var cur = products[lid];
+            products[lid] = montgomery_product(&cur, &t);
+            workgroupBarrier();
+        }
+        var prefix = get_r();
+        if (lid > 0u) {
+            prefix = products[lid - 1u];
+        }
+        if (lid == WORKGROUP_SIZE - 1u) {
+            var total = products[lid];
+            inv_total = montgomery_inverse(&total);
+        }
+        workgroupBarrier();
 
-            /// Set the point. Since no point has been set when j == 0, we can just
-            /// overwrite the data.
-            bucket_x[bi] = sum.x;
-            bucket_y[bi] = sum.y;
-            bucket_z[bi] = sum.z;
+        /// Inclusive suffix products of the workgroup.
+        products[lid] = q;
+        workgroupBarrier();
+        for (var offset = 1u; offset < WORKGROUP_SIZE; offset <<= 1u) {
+            var t = get_r();
+            if (lid + offset < WORKGROUP_SIZE) {
+                t = products[lid + offset];
+            }
+            workgroupBarrier();
+            var cur = products[lid];
+            products[lid] = montgomery_product(&cur, &t);
+            workgroupBarrier();
+        }
+        var suffix = get_r();
+        if (lid + 1u < WORKGROUP_SIZE) {
+            suffix = products[lid + 1u];
+        }
+
+        /// The inverse of q is the inverse of the total times the products
+        /// of every other thread, and each denominator's inverse is that
+        /// times the other denominator.
+        var inv = inv_total;
+        var inv_prefix = montgomery_product(&inv, &prefix);
+        var inv_q = montgomery_product(&inv_prefix, &suffix);
+        var invs = array<BigInt, 2>(
+            montgomery_product(&inv_q, &d1),
+            montgomery_product(&inv_q, &d0),
+        );
+
+        for (var j = 0u; j < 2u; j ++) {
+            if (ops[j] == OP_SET) {
+                acc_x[j] = pt_x[j];
+                acc_y[j] = pt_y[j];
+                acc_inf[j] = false;
+            } else if (ops[j] == OP_INF) {
+                acc_inf[j] = true;
+            } else if (ops[j] != OP_NONE) {
+                var x1 = acc_x[j];
+                var y1 = acc_y[j];
+                var x2 = pt_x[j];
+                var y2 = pt_y[j];
+                var num: BigInt;
+                if (ops[j] == OP_ADD) {
+                    num = field_sub(&y2, &y1);
+                } else {
+                    /// The tangent slope (3x^2 + a) / 2y.
+                    var xx = montgomery_square(&x1);
+                    var xx_2 = field_add(&xx, &xx);
+                    var xx_3 = field_add(&xx_2, &xx);
+                    var a = get_a();
+                    num = field_add(&xx_3, &a);
+                }
+                var den_inv = invs[j];
+                var lambda = montgomery_product(&num, &den_inv);
+                var lambda_sq = montgomery_square(&lambda);
+                var x1_p_x2 = field_add(&x1, &x2);
+                var x3 = field_sub(&lambda_sq, &x1_p_x2);
+                var x1_sub_x3 = field_sub(&x1, &x3);
+                var lambda_mul = montgomery_product(&lambda, &x1_sub_x3);
+                acc_x[j] = x3;
+                acc_y[j] = field_sub(&lambda_mul, &y1);
+            }
+        }
+
+        /// The next step overwrites the products the other threads read.
+        workgroupBarrier();
+    }
+
+    /// Negate the sum of the negative row, and combine the two rows into
+    /// the bucket slot of the thread.
+    var sum = POINT_IDENTITY;
+    for (var j = 0u; j < 2u; j ++) {
+        if (acc_inf[j]) {
+            continue;
+        }
+        var pt = point_from_affine(acc_x[j], acc_y[j]);
+        if (h > row_idxs[j]) {
+            pt = negate_point(pt);
         }
+        sum = point_add(sum, pt);
     }
+    store_bucket(id + subtask_offset * h, sum);
 
     {{{ recompile }}}
 }
+{{/if}}
@@ -10,33 +10,38 @@ var<storage, read_write> all_csc_val_idxs: array<u32>;
 
 /// Intermediate storage buffer.
 @group(0) @binding(3)
-var<storage, read_write> all_curr: array<u32>;
+var<storage, read_write> all_curr: array<atomic<u32>>;
 
 /// Uniform storage buffer. Used instead of shader template constants
 /// to avoid recompiling the shader if either value changes.
 @group(0) @binding(4)
 var<uniform> params: vec3<u32>;
 
-/// Serial transpose algo adapted from Wang et al, 2016, "Parallel
-/// Transposition of Sparse Data Structures".
+const WORKGROUP_SIZE = {{ workgroup_size }}u;
+
+/// Per-thread column totals of the prefix scan.
+var<workgroup> partial_sums: array<u32, {{ workgroup_size }}>;
+
+/// Parallel counting sort transpose, after the serial algorithm of Wang et
+/// al, 2016, "Parallel Transposition of Sparse Data Structures".
 /// https://synergy.cs.vt.edu/pubs/papers/wang-transposition-ics16.pdf
 
-/// This shader implements a serial transpose algorithm, but each thread in
-/// the workgroup handles the CSR matrix for a subtask, so num_subtasks
-/// transpositions happen in parallel.
+/// The three passes are dispatched in turn, with the subtask in the z
+/// dimension. histogram and scatter run one thread per nonzero element, and
+/// scan runs one workgroup per subtask.
+
+/// Note that there is no csr_row_ptr input buffer, since every row of the
+/// CSR matrix is full. We also do not rearrange the nonzero elements
+/// in-place, but instead output an array of their new positions
+/// (all_csc_val_idxs). The order of the positions within a column depends on
+/// the order in which the scatter threads run.
 
-/// Note that there is no csr_row_ptr input buffer; rather, we calculate it
-/// on the fly using calc_start_end. We also do not rearrange the nonzero
-/// elements in-place, but instead output an array of their new positions
-/// (all_csc_val_idxs).
+/// "Count the number of nonzero elements in each column" (Wang et al, 2016).
 @compute
 @workgroup_size({{ workgroup_size }})
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {    
-    /// Subtask id.
-    let subtask_idx = global_id.x;
-
-    /// Number of rows.
-    let m = params[0];
+fn histogram(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let j = global_id.x;
+    let subtask_idx = global_id.z;
 
     /// Number of columns.
     let n = params[1];
@@ -44,33 +49,92 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     /// Input size.
     let input_size = params[2];
 
-    /// Index offsets depending on the subtask ID (aka thread ID) and the slice length.
+    if (j >= input_size) {
+        return;
+    }
+
     let ccp_offset = subtask_idx * (n + 1u);
     let cci_offset = subtask_idx * input_size;
-    let curr_offset = subtask_idx * n;
+    atomicAdd(&all_csc_col_ptr[ccp_offset + all_csr_col_idx[cci_offset + j] + 1u], 1u);
+
+    {{{ recompile }}}
+}
 
-    /// "Count the number of nonzero elements in each column" (Wang et al, 2016).
-    for (var j = 0u; j < input_size; j++) {
-        atomicAdd(&all_csc_col_ptr[ccp_offset + all_csr_col_idx[cci_offset + j] + 1u], 1u);
+/// Prefix sum, aka cumulative/incremental sum, of the column counts of a
+/// subtask. Every thread sums a contiguous block of columns, the workgroup
+/// scans the block totals, and every thread then writes the running sums of
+/// its block.
+@compute
+@workgroup_size({{ workgroup_size }})
+fn scan(
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    let lid = local_id.x;
+    let subtask_idx = workgroup_id.z;
+
+    /// Number of columns.
+    let n = params[1];
+
+    let ccp_offset = subtask_idx * (n + 1u);
+    let block_size = (n + WORKGROUP_SIZE - 1u) / WORKGROUP_SIZE;
+    let start = min(lid * block_size, n);
+    let end = min(start + block_size, n);
+
+    var total = 0u;
+    for (var i = start; i < end; i++) {
+        total += atomicLoad(&all_csc_col_ptr[ccp_offset + i + 1u]);
     }
+    partial_sums[lid] = total;
+    workgroupBarrier();
 
-    /// Prefix sum, aka cumulative/incremental sum.
-    for (var i = 1u; i < n + 1u; i++) {
-        var incremental_sum = atomicLoad(&all_csc_col_ptr[ccp_offset + i - 1u]);
-        atomicAdd(&all_csc_col_ptr[ccp_offset + i], incremental_sum);
+    /// Inclusive Hillis-Steele scan of the block totals.
+    for (var offset = 1u; offset < WORKGROUP_SIZE; offset <<= 1u) {
+        var t = 0u;
+        if (lid >= offset) {
+            t = partial_sums[lid - offset];
+        }
+        workgroupBarrier();
+        partial_sums[lid] += t;
+        workgroupBarrier();
     }
 
-    /// "Traverse the nonzero elements again and move them to their final
-    /// positions determined by the column offsets in csc_col_ptr and their
-    /// current relative positions in curr." (Wang et al, 2016).
-    var val = 0u;
-    for (var j = 0u; j < input_size; j++) {
-        var loc = atomicLoad(&all_csc_col_ptr[ccp_offset + all_csr_col_idx[cci_offset + j]]);
-        loc += all_curr[curr_offset + all_csr_col_idx[cci_offset + j]];
-        all_curr[curr_offset + all_csr_col_idx[cci_offset + j]]++;
-        all_csc_val_idxs[cci_offset + loc] = val;
-        val++;
+    var sum = partial_sums[lid] - total;
+    for (var i = start; i < end; i++) {
+        sum += atomicLoad(&all_csc_col_ptr[ccp_offset + i + 1u]);
+        atomicStore(&all_csc_col_ptr[ccp_offset + i + 1u], sum);
     }
 
     {{{ recompile }}}
-}
\ No newline at end of file
+}
+
+/// "Traverse the nonzero elements again and move them to their final
+/// positions determined by the column offsets in csc_col_ptr and their
+/// current relative positions in curr." (Wang et al, 2016).
+@compute
+@workgroup_size({{ workgroup_size }})
+fn scatter(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let j = global_id.x;
+    let subtask_idx = global_id.z;
+
+    /// Number of columns.
+    let n = params[1];
+
+    /// Input size.
+    let input_size = params[2];
+
+    if (j >= input_size) {
+        return;
+    }
+
+    let ccp_offset = subtask_idx * (n + 1u);
+    let cci_offset = subtask_idx * input_size;
+    let curr_offset = subtask_idx * n;
+
+    let col = all_csr_col_idx[cci_offset + j];
+    let loc = atomicLoad(&all_csc_col_ptr[ccp_offset + col])
+        + atomicAdd(&all_curr[curr_offset + col], 1u);
+    all_csc_val_idxs[cci_offset + loc] = j;
+
+    {{{ recompile }}}
+}
@@ -35,6 +35,37 @@ fn get_rinv() -> BigInt {
     return rinv;
 }
 
+/// The exponent p - 2 of the Fermat inversion.
+fn get_p_minus_two() -> BigInt {
+    var e: BigInt;
+{{{ p_minus_two_limbs }}}
+    return e;
+}
+
+/// The exponent (p - 1) / 2 of the Legendre symbol.
+fn get_p_minus_one_half() -> BigInt {
+    var e: BigInt;
+{{{ p_minus_one_half_limbs }}}
+    return e;
+}
+
+/// The two-adicity s of p - 1 = 2^s * q.
+const SQRT_S = {{ sqrt_s }}u;
+
+/// The Tonelli-Shanks exponent (q - 1) / 2.
+fn get_sqrt_exponent() -> BigInt {
+    var e: BigInt;
+{{{ sqrt_exponent_limbs }}}
+    return e;
+}
+
+/// A generator z of the 2^s-th roots of unity, in Montgomery form.
+fn get_sqrt_z() -> BigInt {
+    var z: BigInt;
+{{{ sqrt_z_limbs }}}
+    return z;
+}
+
 
 
 fn field_add(a: ptr<function, BigInt>, b: ptr<function, BigInt>) -> BigInt { 
@@ -0,0 +1,75 @@
+/// Arithmetic in Fq2 = Fq[u] / (u^2 + 1), on top of the base field functions.
+/// Both components are kept in Montgomery form.
+
+const FQ2_ZERO: Fq2 = Fq2(ZERO, ZERO);
+
+/// field_sub returns p instead of zero for equal inputs. The components of
+/// Fq2 values are kept below p, so that field2_eq compares them exactly.
+fn field2_component_sub(a: ptr<function, BigInt>, b: ptr<function, BigInt>) -> BigInt {
+    if (field_eq(*a, *b)) {
+        return ZERO;
+    }
+    return field_sub(a, b);
+}
+
+fn field2_add(a: ptr<function, Fq2>, b: ptr<function, Fq2>) -> Fq2 {
+    var a0 = (*a).c0;
+    var a1 = (*a).c1;
+    var b0 = (*b).c0;
+    var b1 = (*b).c1;
+    return Fq2(field_add(&a0, &b0), field_add(&a1, &b1));
+}
+
+fn field2_sub(a: ptr<function, Fq2>, b: ptr<function, Fq2>) -> Fq2 {
+    var a0 = (*a).c0;
+    var a1 = (*a).c1;
+    var b0 = (*b).c0;
+    var b1 = (*b).c1;
+    return Fq2(field2_component_sub(&a0, &b0), field2_component_sub(&a1, &b1));
+}
+
+/// Karatsuba multiplication with three base field products:
+/// (a0 + a1 u)(b0 + b1 u) = (a0 b0 - a1 b1) + ((a0 + a1)(b0 + b1) - a0 b0 - a1 b1) u.
+fn field2_mul(a: ptr<function, Fq2>, b: ptr<function, Fq2>) -> Fq2 {
+    var a0 = (*a).c0;
+    var a1 = (*a).c1;
+    var b0 = (*b).c0;
+    var b1 = (*b).c1;
+    var v0 = montgomery_product(&a0, &b0);
+    var v1 = montgomery_product(&a1, &b1);
+    var a_sum = field_add(&a0, &a1);
+    var b_sum = field_add(&b0, &b1);
+    var sum_prod = montgomery_product(&a_sum, &b_sum);
+    var v0_p_v1 = field_add(&v0, &v1);
+    return Fq2(field2_component_sub(&v0, &v1), field2_component_sub(&sum_prod, &v0_p_v1));
+}
+
+/// (a0 + a1 u)^2 = (a0 + a1)(a0 - a1) + 2 a0 a1 u.
+fn field2_sqr(a: ptr<function, Fq2>) -> Fq2 {
+    var a0 = (*a).c0;
+    var a1 = (*a).c1;
+    var a_sum = field_add(&a0, &a1);
+    var a_diff = field2_component_sub(&a0, &a1);
+    var c0 = montgomery_product(&a_sum, &a_diff);
+    var c1 = field_small_scalar_shift(1, montgomery_product(&a0, &a1));
+    return Fq2(c0, c1);
+}
+
+fn field2_small_scalar_shift(l: u32, a: Fq2) -> Fq2 {
+    return Fq2(field_small_scalar_shift(l, a.c0), field_small_scalar_shift(l, a.c1));
+}
+
+fn field2_neg(a: Fq2) -> Fq2 {
+    var zero = ZERO;
+    var a0 = a.c0;
+    var a1 = a.c1;
+    return Fq2(field2_component_sub(&zero, &a0), field2_component_sub(&zero, &a1));
+}
+
+fn field2_eq(a: Fq2, b: Fq2) -> bool {
+    return field_eq(a.c0, b.c0) && field_eq(a.c1, b.c1);
+}
+
+fn field2_is_zero(a: Fq2) -> bool {
+    return field2_eq(a, FQ2_ZERO);
+}
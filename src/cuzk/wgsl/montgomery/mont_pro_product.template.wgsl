@@ -58,4 +58,88 @@ fn montgomery_pow(p: ptr<function, BigInt>, e: u32) -> BigInt {
         res = montgomery_product(&res, p);
     }
     return res;
-}
\ No newline at end of file
+}
+
+/// x^e for x in Montgomery form and an exponent e of up to
+/// NUM_WORDS * WORD_SIZE bits, with square-and-multiply from the most
+/// significant bit of the exponent.
+fn montgomery_pow_wide(x: ptr<function, BigInt>, e: ptr<function, BigInt>) -> BigInt {
+    var res = get_r();
+    for (var i = NUM_WORDS; i > 0u; i --) {
+        let limb = (*e).limbs[i - 1u];
+        for (var j = WORD_SIZE; j > 0u; j --) {
+            res = montgomery_square(&res);
+            if (((limb >> (j - 1u)) & 1u) == 1u) {
+                res = montgomery_product(&res, x);
+            }
+        }
+    }
+    return res;
+}
+
+/// The inverse of x in Montgomery form, as x^(p - 2) by Fermat's little
+/// theorem. The inverse of zero is zero.
+fn montgomery_inverse(x: ptr<function, BigInt>) -> BigInt {
+    var e = get_p_minus_two();
+    return montgomery_pow_wide(x, &e);
+}
+
+/// The Legendre symbol of x in Montgomery form, as x^((p - 1) / 2): 1 if x
+/// is a nonzero square, -1 if it is not a square and 0 if it is zero.
+fn montgomery_legendre(x: ptr<function, BigInt>) -> i32 {
+    var e = get_p_minus_one_half();
+    let l = montgomery_pow_wide(x, &e);
+    if (field_eq(l, ZERO)) {
+        return 0;
+    }
+    if (field_eq(l, get_r())) {
+        return 1;
+    }
+    return -1;
+}
+
+/// A square root of x in Montgomery form, with the Tonelli-Shanks
+/// algorithm. Writes the root to res and returns true if x is a square, and
+/// returns false otherwise. For p = 3 mod 4 this reduces to x^((p + 1) / 4).
+fn montgomery_sqrt(x: ptr<function, BigInt>, res: ptr<function, BigInt>) -> bool {
+    if (field_eq(*x, ZERO)) {
+        *res = ZERO;
+        return true;
+    }
+    let one = get_r();
+
+    /// w = x^((q - 1) / 2), root = x^((q + 1) / 2) and t = x^q.
+    var e = get_sqrt_exponent();
+    var w = montgomery_pow_wide(x, &e);
+    var root = montgomery_product(x, &w);
+    var t = montgomery_product(&root, &w);
+    var c = get_sqrt_z();
+    var m = SQRT_S;
+
+    /// root^2 = x * t stays invariant, while the order of t halves at least
+    /// once per iteration.
+    while (!field_eq(t, one)) {
+        /// The least i with t^(2^i) = 1. Squares have i < m.
+        var i = 0u;
+        var t_pow = t;
+        while (!field_eq(t_pow, one) && i < m) {
+            t_pow = montgomery_square(&t_pow);
+            i ++;
+        }
+        if (i == m) {
+            return false;
+        }
+
+        /// b = c^(2^(m - i - 1)).
+        var b = c;
+        for (var j = i + 1u; j < m; j ++) {
+            b = montgomery_square(&b);
+        }
+        m = i;
+        c = montgomery_square(&b);
+        t = montgomery_product(&t, &c);
+        root = montgomery_product(&root, &b);
+    }
+    *res = root;
+    return true;
+}
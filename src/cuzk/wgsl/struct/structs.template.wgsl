@@ -10,11 +10,34 @@ struct BigIntMediumWide {
     limbs: array<u32, {{ num_words_plus_one }}>
 }
 
+{{#if is_ext2}}
+/// An element c0 + c1 * u of the quadratic extension field.
+struct Fq2 {
+    c0: BigInt,
+    c1: BigInt
+}
+
+alias Coord = Fq2;
+{{else}}
+alias Coord = BigInt;
+{{/if}}
+
+{{#if is_edwards}}
+/// A twisted Edwards point in extended coordinates, with x = X/Z, y = Y/Z
+/// and T = XY/Z.
+struct Point {
+  x: Coord,
+  y: Coord,
+  t: Coord,
+  z: Coord
+}
+{{else}}
 struct Point {
-  x: BigInt,
-  y: BigInt,
-  z: BigInt
+  x: Coord,
+  y: Coord,
+  z: Coord
 }
+{{/if}}
 
 fn bigint_equal(a: BigInt, b: BigInt) -> bool {
     for (var i = 0u; i < {{ num_words }}; i = i + 1u) {
@@ -59,4 +59,73 @@ fn test_barret_mul(
     var x_r = field_mul(&x, &r);
     var rinv = get_rinv();
     result = field_mul(&x_r, &rinv);
-}
\ No newline at end of file
+}
+@compute @workgroup_size(1)
+fn test_field_inv(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>
+) {
+    /// Convert x to Montgomery form and back around the inversion.
+    var r = get_r();
+    var x = a;
+    var x_r = field_mul(&x, &r);
+    var inv = montgomery_inverse(&x_r);
+    var rinv = get_rinv();
+    result = field_mul(&inv, &rinv);
+}
+
+/// Raises a to the exponent b, which is not converted to Montgomery form.
+@compute @workgroup_size(1)
+fn test_field_pow(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>
+) {
+    var r = get_r();
+    var x = a;
+    var x_r = field_mul(&x, &r);
+    var e = b;
+    var pow = montgomery_pow_wide(&x_r, &e);
+    var rinv = get_rinv();
+    result = field_mul(&pow, &rinv);
+}
+
+/// Writes the Legendre symbol of a as 1, p - 1 or 0.
+@compute @workgroup_size(1)
+fn test_field_legendre(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>
+) {
+    var r = get_r();
+    var x = a;
+    var x_r = field_mul(&x, &r);
+    let l = montgomery_legendre(&x_r);
+    if (l == 1) {
+        result = ONE;
+    } else if (l == -1) {
+        var p = get_p();
+        var one = ONE;
+        var minus_one: BigInt;
+        bigint_sub(&p, &one, &minus_one);
+        result = minus_one;
+    } else {
+        result = ZERO;
+    }
+}
+
+/// Writes a square root of a, or zero if a is not a square.
+@compute @workgroup_size(1)
+fn test_field_sqrt(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>
+) {
+    var r = get_r();
+    var x = a;
+    var x_r = field_mul(&x, &r);
+    var root: BigInt;
+    if (montgomery_sqrt(&x_r, &root)) {
+        var rinv = get_rinv();
+        result = field_mul(&root, &rinv);
+    } else {
+        result = ZERO;
+    }
+}
@@ -15,26 +15,34 @@ var<storage, read_write> result: Point;
 @group(0) @binding(3)
 var<uniform> scalar: u32;
 
+/// Convert a coordinate to Montgomery form.
+fn coord_to_mont(c: Coord) -> Coord {
+    var r = get_r();
+{{#if is_ext2}}
+    var c0 = c.c0;
+    var c1 = c.c1;
+    return Fq2(field_mul(&c0, &r), field_mul(&c1, &r));
+{{else}}
+    var x = c;
+    return field_mul(&x, &r);
+{{/if}}
+}
+
+fn point_to_mont(p: Point) -> Point {
+{{#if is_edwards}}
+    return Point(coord_to_mont(p.x), coord_to_mont(p.y), coord_to_mont(p.t), coord_to_mont(p.z));
+{{else}}
+    return Point(coord_to_mont(p.x), coord_to_mont(p.y), coord_to_mont(p.z));
+{{/if}}
+}
+
 @compute @workgroup_size(1)
 fn test_point_add(
     @builtin(global_invocation_id) global_id: vec3<u32>,
     @builtin(local_invocation_id) local_id: vec3<u32>
 ) {
-    var r = get_r();
-    var ax = a.x;
-    var ay = a.y;
-    var az = a.z;
-    var ar_x = field_mul(&ax, &r);
-    var ar_y = field_mul(&ay, &r);
-    var ar_z = field_mul(&az, &r);
-    var p_a = Point(ar_x, ar_y, ar_z);
-    var bx = b.x;
-    var by = b.y;
-    var bz = b.z;
-    var br_x = field_mul(&bx, &r);
-    var br_y = field_mul(&by, &r);
-    var br_z = field_mul(&bz, &r);
-    var p_b = Point(br_x, br_y, br_z);
+    var p_a = point_to_mont(a);
+    var p_b = point_to_mont(b);
     result = point_add(p_a, p_b);
 }
 
@@ -43,14 +51,7 @@ fn test_negate_point(
     @builtin(global_invocation_id) global_id: vec3<u32>,
     @builtin(local_invocation_id) local_id: vec3<u32>
 ) {
-    var r = get_r();    
-    var ax = a.x;
-    var ay = a.y;
-    var az = a.z;
-    var ar_x = field_mul(&ax, &r);
-    var ar_y = field_mul(&ay, &r);
-    var ar_z = field_mul(&az, &r);
-    var p_a = Point(ar_x, ar_y, ar_z);
+    var p_a = point_to_mont(a);
     result = negate_point(p_a);
 }
 
@@ -59,14 +60,7 @@ fn test_double_and_add(
     @builtin(global_invocation_id) global_id: vec3<u32>,
     @builtin(local_invocation_id) local_id: vec3<u32>
 ) {
-    var r = get_r();
-    var ax = a.x;
-    var ay = a.y;
-    var az = a.z;
-    var ar_x = field_mul(&ax, &r);
-    var ar_y = field_mul(&ay, &r);
-    var ar_z = field_mul(&az, &r);
-    var p_a = Point(ar_x, ar_y, ar_z);
+    var p_a = point_to_mont(a);
     result = double_and_add(p_a, scalar);
 }
 
@@ -75,14 +69,28 @@ fn test_point_add_identity(
     @builtin(global_invocation_id) global_id: vec3<u32>,
     @builtin(local_invocation_id) local_id: vec3<u32>
 ) {
-    var r = get_r();
-    var ax = a.x;
-    var ay = a.y;
-    var az = a.z;
-    var ar_x = field_mul(&ax, &r);
-    var ar_y = field_mul(&ay, &r);
-    var ar_z = field_mul(&az, &r);
-    var p_a = Point(ar_x, ar_y, ar_z);
+    var p_a = point_to_mont(a);
     var p_b = POINT_IDENTITY;
     result = point_add(p_a, p_b);
-}
\ No newline at end of file
+}
+
+/// Doubles a first, so that the Jacobian operand of the mixed addition has
+/// z != 1.
+@compute @workgroup_size(1)
+fn test_point_add_mixed(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>
+) {
+    var p_a = point_double(point_to_mont(a));
+    var p_b = point_to_mont(b);
+    result = point_add_mixed(p_a, p_b.x, p_b.y);
+}
+
+@compute @workgroup_size(1)
+fn test_point_add_mixed_identity(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>
+) {
+    var p_a = point_to_mont(a);
+    result = point_add_mixed(POINT_IDENTITY, p_a.x, p_a.y);
+}
@@ -2,6 +2,14 @@
 
 pub mod cuzk;
 
+#[cfg(feature = "arkworks")]
+pub use crate::cuzk::ark::{compute_msm_ark, ArkCurve};
+pub use crate::cuzk::config::{MsmConfig, Workgroups};
+pub use crate::cuzk::curve::{CurveModel, GpuCurve};
+pub use crate::cuzk::error::MsmError;
+pub use crate::cuzk::plan::{CostModel, MsmPlan};
+
+use crate::cuzk::context::MsmContext;
 use crate::cuzk::msm::compute_msm;
 use cuzk::utils::debug;
 use ff::PrimeField;
@@ -9,35 +17,47 @@ use group::{Curve, Group};
 use halo2curves::bn256::Fr;
 use halo2curves::bn256::G1Affine;
 use halo2curves::{msm::msm_best, CurveAffine};
-use rand::thread_rng;
 use js_sys::Array;
-use rand::Rng;          
+use rand::thread_rng;
+use rand::{Rng, RngCore};
 
 use crate::cuzk::utils::field_to_bytes;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen_futures;
 /// Sample random scalars
 pub fn sample_scalars<F: PrimeField>(n: usize) -> Vec<F> {
-    let mut rng = thread_rng();
-    (0..n).map(|_| F::random(&mut rng)).collect::<Vec<_>>()
+    sample_scalars_with_rng(n, &mut thread_rng())
+}
+
+/// Sample random scalars from `rng`, e.g. a seeded `StdRng` to replay a run
+pub fn sample_scalars_with_rng<F: PrimeField>(n: usize, rng: &mut impl RngCore) -> Vec<F> {
+    (0..n).map(|_| F::random(&mut *rng)).collect::<Vec<_>>()
 }
 
 /// Sample random scalars
 pub fn sample_32_bit_scalars<F: PrimeField>(n: usize) -> Vec<F> {
-    let mut rng = thread_rng();
+    sample_32_bit_scalars_with_rng(n, &mut thread_rng())
+}
 
-    (0..n).map(|_| {
-        let random_u32: u32 = rng.gen_range(0..=u32::MAX);
-        F::from(random_u32 as u64)
-    }).collect::<Vec<_>>()
+/// Sample random 32-bit scalars from `rng`
+pub fn sample_32_bit_scalars_with_rng<F: PrimeField>(n: usize, rng: &mut impl RngCore) -> Vec<F> {
+    (0..n)
+        .map(|_| {
+            let random_u32: u32 = rng.gen_range(0..=u32::MAX);
+            F::from(random_u32 as u64)
+        })
+        .collect::<Vec<_>>()
 }
 
 /// Sample random affine points
 pub fn sample_points<C: CurveAffine>(n: usize) -> Vec<C> {
-    let mut rng = thread_rng();
+    sample_points_with_rng(n, &mut thread_rng())
+}
+
+/// Sample random affine points from `rng`
+pub fn sample_points_with_rng<C: CurveAffine>(n: usize, rng: &mut impl RngCore) -> Vec<C> {
     (0..n)
         // .map(|_| C::identity())
-        .map(|_| C::Curve::random(&mut rng).to_affine())
+        .map(|_| C::Curve::random(&mut *rng).to_affine())
         .collect::<Vec<_>>()
 }
 
@@ -52,50 +72,105 @@ pub fn scalars_to_bytes<F: PrimeField>(v: &[F]) -> Vec<u8> {
 }
 
 /// Convert points to bytes as [x0, y0, x1, y1, ...]
-pub fn points_to_bytes<C: CurveAffine>(g: &[C]) -> Vec<u8> {
-    let ps = g.iter()
+pub fn points_to_bytes<C: GpuCurve>(g: &[C]) -> Vec<u8> {
+    let ps = g
+        .iter()
         .flat_map(|affine| {
             let coords = affine.coordinates().unwrap();
-            let x = field_to_bytes(coords.x());
-            let y = field_to_bytes(coords.y());
+            let x = C::base_to_bytes(coords.x());
+            let y = C::base_to_bytes(coords.y());
             [x, y].concat()
         })
         .collect::<Vec<_>>();
     ps
 }
 
+/// Convert points to bytes as [x0, y0, x1, y1, ...], with the coordinates
+/// left in the curve library's internal Montgomery form if the curve sets
+/// `GpuCurve::RAW_MONTGOMERY`. Shaders must be generated with
+/// `ShaderManager::set_raw_montgomery_input`.
+///
+/// Panics on the point at infinity, which has no affine coordinates; the
+/// MSM entry points replace it by the generator with a zero scalar first.
+pub fn points_to_raw_bytes<C: GpuCurve>(g: &[C]) -> Vec<u8> {
+    g.iter()
+        .flat_map(|affine| {
+            let coords = affine.coordinates().unwrap();
+            let x = C::base_to_raw_bytes(coords.x());
+            let y = C::base_to_raw_bytes(coords.y());
+            [x, y].concat()
+        })
+        .collect::<Vec<_>>()
+}
+
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = performance)]
     fn now() -> f64;
 }
 
+/// Backend that computed an MSM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MsmBackend {
+    /// The cuZK pipeline on WebGPU
+    WebGpu,
+    /// `cpu_msm`, used when WebGPU is unavailable or the device was lost
+    Cpu,
+}
+
+/// Run WebGPU MSM computation asynchronously, falling back to `cpu_msm` when
+/// there is no adapter or the device is lost. Returns the backend that
+/// produced the result alongside it.
+pub async fn run_webgpu_msm<C: GpuCurve>(
+    g: &[C],
+    v: &[C::Scalar],
+) -> Result<(C::Curve, MsmBackend), MsmError> {
+    with_cpu_fallback(compute_msm(g, v).await, g, v)
+}
 
+/// [`run_webgpu_msm`] on an existing `MsmContext`, falling back to `cpu_msm`
+/// if its device has been lost.
+pub async fn run_webgpu_msm_with_context<C: GpuCurve>(
+    context: &mut MsmContext,
+    g: &[C],
+    v: &[C::Scalar],
+) -> Result<(C::Curve, MsmBackend), MsmError> {
+    with_cpu_fallback(context.compute_msm(g, v).await, g, v)
+}
 
-/// Run WebGPU MSM computation asynchronously
-pub async fn run_webgpu_msm<C: CurveAffine>(
+fn with_cpu_fallback<C: GpuCurve>(
+    result: Result<C::Curve, MsmError>,
     g: &[C],
     v: &[C::Scalar],
-) -> C::Curve {
-        let result = compute_msm(g, v).await;
-        result
+) -> Result<(C::Curve, MsmBackend), MsmError> {
+    match result {
+        Ok(result) => Ok((result, MsmBackend::WebGpu)),
+        Err(e) if e.is_gpu_unavailable() => {
+            debug(&format!("WebGPU unavailable, falling back to the CPU: {e}"));
+            Ok((cpu_msm(g, v), MsmBackend::Cpu))
+        }
+        Err(e) => Err(e),
+    }
 }
 
 #[wasm_bindgen]
 pub async fn run_webgpu_msm_web(
     sample_size: usize,
     _callback: js_sys::Function,
-) -> Array {
+) -> Result<Array, JsError> {
     let start = now();
     debug(&format!("Testing with sample size: {sample_size}"));
     let points = sample_points::<G1Affine>(sample_size);
     let scalars = sample_scalars::<Fr>(sample_size);
-    debug(&format!("Sampling points and scalars took {} ms", now() - start));
+    debug(&format!(
+        "Sampling points and scalars took {} ms",
+        now() - start
+    ));
 
     let start = now();
-    let result = compute_msm(&points, &scalars).await;
+    let (result, backend) = run_webgpu_msm(&points, &scalars).await?;
     let msm_elapsed = now() - start;
-    debug(&format!("GPU MSM Elapsed: {} ms", msm_elapsed));
+    debug(&format!("{backend:?} MSM Elapsed: {} ms", msm_elapsed));
     let coords = result.to_affine().coordinates().unwrap();
 
     let x_str = format!("{:?}", coords.x());
@@ -105,20 +180,20 @@ pub async fn run_webgpu_msm_web(
     arr.push(&JsValue::from(x_str));
     arr.push(&JsValue::from(y_str));
     arr.push(&JsValue::from(msm_elapsed));
-    arr
+    arr.push(&JsValue::from(format!("{backend:?}")));
+    Ok(arr)
 }
 
 #[wasm_bindgen]
-pub async fn run_cpu_msm_web(
-    sample_size: usize,
-    _callback: js_sys::Function,
-) -> Array {
+pub async fn run_cpu_msm_web(sample_size: usize, _callback: js_sys::Function) -> Array {
     let start = now();
     debug(&format!("Testing with sample size: {sample_size}"));
     let points = sample_points::<G1Affine>(sample_size);
     let scalars = sample_scalars::<Fr>(sample_size);
-    debug(&format!("Sampling points and scalars took {} ms", now() - start));
-
+    debug(&format!(
+        "Sampling points and scalars took {} ms",
+        now() - start
+    ));
 
     let start = now();
     let result = cpu_msm(&points, &scalars);
@@ -136,13 +211,11 @@ pub async fn run_cpu_msm_web(
     arr
 }
 
-
 pub mod tests_wasm_pack {
     use super::*;
 
     use halo2curves::bn256::{Fr, G1Affine};
 
-
     #[wasm_bindgen]
     extern "C" {
         #[wasm_bindgen(js_namespace = performance)]
@@ -159,7 +232,7 @@ pub mod tests_wasm_pack {
         debug(&format!("CPU Elapsed: {} ms", now() - cpu_start));
 
         let result_start = now();
-        let result = run_webgpu_msm::<G1Affine>(&points, &scalars).await;
+        let (result, _) = run_webgpu_msm::<G1Affine>(&points, &scalars).await.unwrap();
         debug(&format!("GPU Elapsed: {} ms", now() - result_start));
 
         debug(&format!("Result: {result:?}"));
@@ -168,10 +241,10 @@ pub mod tests_wasm_pack {
 
     #[test]
     fn test_webgpu_msm_cuzk_cpu() {
-        let input_size =  65537;
+        let input_size = 65537;
         let scalars = sample_scalars::<Fr>(input_size);
         let points = sample_points::<G1Affine>(input_size);
 
-        let result = pollster::block_on(run_webgpu_msm::<G1Affine>(&points, &scalars));
+        let _result = pollster::block_on(run_webgpu_msm::<G1Affine>(&points, &scalars));
     }
 }
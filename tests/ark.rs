@@ -0,0 +1,93 @@
+#![cfg(feature = "arkworks")]
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+    use ark_ff::{BigInteger, PrimeField, UniformRand};
+    use group::prime::PrimeCurveAffine;
+    use halo2curves::bn256;
+    use halo2curves::CurveAffine;
+    use msm_webgpu::cuzk::ark::{
+        ark_field_from_bytes, ark_field_to_bytes, ark_to_gpu_point, gpu_to_ark_point,
+    };
+    use msm_webgpu::cuzk::msm::WORD_SIZE;
+    use msm_webgpu::cuzk::utils::{
+        bytes_to_field, to_words_le_from_field, to_words_le_from_le_bytes,
+    };
+    use msm_webgpu::{compute_msm_ark, ArkCurve, GpuCurve, MsmError};
+    use rand::thread_rng;
+
+    fn sample<G: ArkCurve>(n: usize) -> (Vec<G>, Vec<G::ScalarField>) {
+        let mut rng = thread_rng();
+        let points = (0..n)
+            .map(|_| G::Group::rand(&mut rng).into_affine())
+            .collect::<Vec<_>>();
+        let scalars = (0..n)
+            .map(|_| G::ScalarField::rand(&mut rng))
+            .collect::<Vec<_>>();
+        (points, scalars)
+    }
+
+    fn check_round_trip<G: ArkCurve>() {
+        let (points, _) = sample::<G>(16);
+        for point in points {
+            let gpu_point = ark_to_gpu_point(&point);
+            assert!(bool::from(gpu_point.is_on_curve()));
+            assert_eq!(gpu_to_ark_point::<G>(&gpu_point.into()), point.into_group());
+        }
+        assert!(bool::from(ark_to_gpu_point(&G::zero()).is_identity()));
+    }
+
+    #[test]
+    fn test_ark_bn254_round_trip() {
+        check_round_trip::<ark_bn254::G1Affine>();
+        check_round_trip::<ark_bn254::G2Affine>();
+    }
+
+    #[test]
+    fn test_ark_limb_layout() {
+        let (points, scalars) = sample::<ark_bn254::G1Affine>(16);
+        let num_words = bn256::G1Affine::num_words();
+        for scalar in scalars {
+            assert_eq!(
+                ark_bn254::G1Affine::scalar_to_gpu(&scalar),
+                bytes_to_field(&scalar.into_bigint().to_bytes_le()),
+            );
+        }
+        for point in points {
+            let (x, _) = point.xy().unwrap();
+            let gpu_point = ark_to_gpu_point(&point);
+            let coords = gpu_point.coordinates().unwrap();
+            assert_eq!(
+                to_words_le_from_le_bytes(&ark_field_to_bytes(&x, 32), num_words, WORD_SIZE),
+                to_words_le_from_field(coords.x(), num_words, WORD_SIZE),
+            );
+        }
+    }
+
+    #[test]
+    fn test_ark_field_bytes() {
+        let (points, _) = sample::<ark_bn254::G2Affine>(16);
+        for point in points {
+            let (x, _) = point.xy().unwrap();
+            let bytes = ark_field_to_bytes(&x, 32);
+            assert_eq!(
+                ark_field_from_bytes::<ark_bn254::Fq2>(&bytes, 32).unwrap(),
+                x
+            );
+        }
+        assert!(matches!(
+            ark_field_from_bytes::<ark_bn254::Fq2>(&[0; 96], 32),
+            Err(MsmError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_ark_bn254_msm() {
+        let (mut points, scalars) = sample::<ark_bn254::G1Affine>(1 << 10);
+        points[3] = ark_bn254::G1Affine::zero();
+        let expected = ark_bn254::G1Projective::msm(&points, &scalars).unwrap();
+        let result = pollster::block_on(compute_msm_ark(&points, &scalars)).unwrap();
+        assert_eq!(expected, result);
+    }
+}
@@ -0,0 +1,271 @@
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{G1Affine, G2Affine};
+    use msm_webgpu::{CostModel, MsmConfig, MsmError, MsmPlan, Workgroups};
+    use wgpu::Limits;
+
+    fn workgroups(log_size: usize, config: MsmConfig) -> Result<Workgroups, MsmError> {
+        let plan = MsmPlan::for_input_size::<G1Affine>(1 << log_size).unwrap();
+        config.workgroups(&plan, &Limits::default())
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let w = workgroups(8, MsmConfig::default()).unwrap();
+        assert_eq!(w.chunk_size, 6);
+        assert_eq!(
+            (
+                w.decompose_workgroup_size,
+                w.decompose_num_x_workgroups,
+                w.decompose_num_y_workgroups
+            ),
+            (256, 1, 1)
+        );
+        assert_eq!(
+            (
+                w.smvp_workgroup_size,
+                w.smvp_num_x_workgroups,
+                w.smvp_num_y_workgroups
+            ),
+            (1, 32, 1)
+        );
+        // 43 windows, which only 1 divides
+        assert_eq!(w.smvp_subtasks_per_dispatch, 1);
+        assert_eq!(w.bpr_subtasks_per_dispatch, 1);
+        assert_eq!(w.bpr_workgroup_size, 32);
+
+        let w = workgroups(10, MsmConfig::default()).unwrap();
+        assert_eq!(w.chunk_size, 8);
+        assert_eq!(
+            (
+                w.decompose_workgroup_size,
+                w.decompose_num_x_workgroups,
+                w.decompose_num_y_workgroups
+            ),
+            (64, 4, 4)
+        );
+        assert_eq!(
+            (
+                w.smvp_workgroup_size,
+                w.smvp_num_x_workgroups,
+                w.smvp_num_y_workgroups
+            ),
+            (32, 1, 4)
+        );
+        assert_eq!(w.smvp_subtasks_per_dispatch, 4);
+        assert_eq!(w.bpr_subtasks_per_dispatch, 16);
+        assert_eq!(w.bpr_workgroup_size, 128);
+        assert_eq!(w.window_sums_workgroup_size, 64);
+        assert_eq!(
+            (w.transpose_workgroup_size, w.transpose_num_x_workgroups),
+            (256, 4)
+        );
+
+        let w = workgroups(16, MsmConfig::default()).unwrap();
+        assert_eq!(w.chunk_size, 13);
+        assert_eq!(w.decompose_num_y_workgroups, 32);
+        assert_eq!(w.smvp_num_y_workgroups, 128);
+        // 20 windows
+        assert_eq!(w.smvp_subtasks_per_dispatch, 4);
+        assert_eq!(w.bpr_subtasks_per_dispatch, 4);
+
+        let w = workgroups(18, MsmConfig::default()).unwrap();
+        assert_eq!(w.chunk_size, 16);
+        assert_eq!(
+            (
+                w.decompose_workgroup_size,
+                w.decompose_num_x_workgroups,
+                w.decompose_num_y_workgroups
+            ),
+            (256, 32, 32)
+        );
+        assert_eq!(
+            (
+                w.smvp_workgroup_size,
+                w.smvp_num_x_workgroups,
+                w.smvp_num_y_workgroups
+            ),
+            (256, 64, 2)
+        );
+        assert_eq!(w.bpr_workgroup_size, 256);
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let config = MsmConfig {
+            decompose_workgroup_size: Some(128),
+            decompose_num_x_workgroups: Some(2),
+            smvp_workgroup_size: Some(64),
+            smvp_num_x_workgroups: Some(2),
+            smvp_subtasks_per_dispatch: Some(2),
+            bpr_workgroup_size: Some(64),
+            bpr_subtasks_per_dispatch: Some(8),
+            window_sums_workgroup_size: Some(32),
+            transpose_workgroup_size: Some(100),
+            ..Default::default()
+        };
+        let w = workgroups(10, config).unwrap();
+        assert_eq!(
+            (w.transpose_workgroup_size, w.transpose_num_x_workgroups),
+            (100, 11)
+        );
+        assert_eq!(w.decompose_num_y_workgroups, 4);
+        assert_eq!(w.smvp_num_y_workgroups, 1);
+        assert_eq!(w.smvp_subtasks_per_dispatch, 2);
+        assert_eq!(w.bpr_workgroup_size, 64);
+        assert_eq!(w.bpr_subtasks_per_dispatch, 8);
+        assert_eq!(w.window_sums_workgroup_size, 32);
+    }
+
+    #[test]
+    fn test_config_chunk_size() {
+        let limits = Limits::default();
+        let cost_model = CostModel::default();
+        let config = MsmConfig {
+            chunk_size: Some(11),
+            ..Default::default()
+        };
+        let plan = config
+            .plan::<G1Affine>(1 << 16, &limits, &cost_model)
+            .unwrap();
+        assert_eq!(plan, MsmPlan::new::<G1Affine>(1 << 16, 11));
+
+        let plan = MsmConfig::default()
+            .plan::<G1Affine>(1 << 16, &limits, &cost_model)
+            .unwrap();
+        assert_eq!(plan, MsmPlan::for_input_size::<G1Affine>(1 << 16).unwrap());
+
+        for chunk_size in [0, 1, 17] {
+            let config = MsmConfig {
+                chunk_size: Some(chunk_size),
+                ..Default::default()
+            };
+            let result = config.plan::<G1Affine>(1 << 16, &limits, &cost_model);
+            assert!(matches!(result, Err(MsmError::InvalidConfig(_))));
+        }
+
+        let limits = Limits {
+            max_storage_buffer_binding_size: 1 << 25,
+            ..Default::default()
+        };
+        let config = MsmConfig {
+            chunk_size: Some(16),
+            ..Default::default()
+        };
+        let result = config.plan::<G1Affine>(1 << 18, &limits, &cost_model);
+        assert!(matches!(result, Err(MsmError::LimitsExceeded { .. })));
+    }
+
+    #[test]
+    fn test_config_invalid() {
+        let invalid = [
+            // 3 * 4 threads do not divide 2^10 inputs
+            MsmConfig {
+                decompose_workgroup_size: Some(3),
+                ..Default::default()
+            },
+            MsmConfig {
+                decompose_workgroup_size: Some(0),
+                ..Default::default()
+            },
+            // More threads than buckets
+            MsmConfig {
+                smvp_workgroup_size: Some(256),
+                ..Default::default()
+            },
+            // 3 does not divide 32 windows
+            MsmConfig {
+                smvp_subtasks_per_dispatch: Some(3),
+                ..Default::default()
+            },
+            MsmConfig {
+                bpr_workgroup_size: Some(96),
+                ..Default::default()
+            },
+            MsmConfig {
+                bpr_subtasks_per_dispatch: Some(0),
+                ..Default::default()
+            },
+            MsmConfig {
+                window_sums_workgroup_size: Some(0),
+                ..Default::default()
+            },
+            MsmConfig {
+                transpose_workgroup_size: Some(0),
+                ..Default::default()
+            },
+        ];
+        for config in invalid {
+            let result = workgroups(10, config);
+            assert!(
+                matches!(result, Err(MsmError::InvalidConfig(_))),
+                "{config:?} gave {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_batch_affine() {
+        let config = MsmConfig {
+            batch_affine: true,
+            ..Default::default()
+        };
+        let w = workgroups(10, config).unwrap();
+        assert!(w.batch_affine);
+        assert_eq!(
+            (
+                w.smvp_workgroup_size,
+                w.smvp_num_x_workgroups,
+                w.smvp_num_y_workgroups
+            ),
+            (64, 1, 2)
+        );
+        // Windows with fewer buckets use one workgroup per subtask
+        let w = workgroups(8, config).unwrap();
+        assert_eq!(
+            (
+                w.smvp_workgroup_size,
+                w.smvp_num_x_workgroups,
+                w.smvp_num_y_workgroups
+            ),
+            (32, 1, 1)
+        );
+
+        let limits = Limits::default();
+        let cost_model = CostModel::default();
+        assert!(config
+            .plan::<G1Affine>(1 << 10, &limits, &cost_model)
+            .is_ok());
+        let result = config.plan::<G2Affine>(1 << 10, &limits, &cost_model);
+        assert!(
+            matches!(result, Err(MsmError::InvalidConfig(_))),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_config_limits() {
+        let plan = MsmPlan::for_input_size::<G1Affine>(1 << 10).unwrap();
+        let config = MsmConfig {
+            decompose_workgroup_size: Some(512),
+            decompose_num_x_workgroups: Some(1),
+            ..Default::default()
+        };
+        let result = config.workgroups(&plan, &Limits::default());
+        assert!(matches!(
+            result,
+            Err(MsmError::LimitsExceeded {
+                requested: 512,
+                limit: 256,
+                ..
+            })
+        ));
+
+        let limits = Limits {
+            max_compute_workgroups_per_dimension: 2,
+            ..Default::default()
+        };
+        let result = MsmConfig::default().workgroups(&plan, &limits);
+        assert!(matches!(result, Err(MsmError::LimitsExceeded { .. })));
+    }
+}
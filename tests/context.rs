@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod tests {
+    use group::prime::PrimeCurveAffine;
+    use halo2curves::bn256::{Fr, G1Affine};
+    use msm_webgpu::cuzk::context::MsmContext;
+    use msm_webgpu::{cpu_msm, sample_points, sample_scalars, MsmConfig, MsmError};
+
+    #[test]
+    fn test_msm_context_reuse() {
+        let input_size = 1 << 16;
+        let points = sample_points::<G1Affine>(input_size);
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        for _ in 0..2 {
+            let scalars = sample_scalars::<Fr>(input_size);
+            let expected = cpu_msm(&points, &scalars);
+            let result = pollster::block_on(context.compute_msm(&points, &scalars)).unwrap();
+            assert_eq!(expected, result);
+        }
+        assert_eq!(context.num_cached_pipelines(), 1);
+    }
+
+    #[test]
+    fn test_msm_context_padded_input() {
+        // One more than a power of two, so that almost half of the input is
+        // padding.
+        let input_size = (1 << 16) + 1;
+        let points = sample_points::<G1Affine>(input_size);
+        let scalars = sample_scalars::<Fr>(input_size);
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        let result = pollster::block_on(context.compute_msm(&points, &scalars)).unwrap();
+        assert_eq!(cpu_msm(&points, &scalars), result);
+    }
+
+    #[test]
+    fn test_msm_fixed_base() {
+        let input_size = 1 << 16;
+        let points = sample_points::<G1Affine>(input_size);
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        let bases = pollster::block_on(context.upload_bases(&points)).unwrap();
+        assert_eq!(bases.len(), input_size);
+        for _ in 0..2 {
+            let scalars = sample_scalars::<Fr>(input_size);
+            let expected = cpu_msm(&points, &scalars);
+            let result =
+                pollster::block_on(context.compute_msm_fixed_base(&bases, &scalars)).unwrap();
+            assert_eq!(expected, result);
+        }
+    }
+
+    #[test]
+    fn test_msm_batch() {
+        let input_size = 1 << 16;
+        let points = sample_points::<G1Affine>(input_size);
+        let scalars = (0..3)
+            .map(|_| sample_scalars::<Fr>(input_size))
+            .collect::<Vec<_>>();
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        let results = pollster::block_on(context.compute_msm_batch(&points, &scalars)).unwrap();
+        assert_eq!(results.len(), scalars.len());
+        for (instance, result) in scalars.iter().zip(results) {
+            assert_eq!(cpu_msm(&points, instance), result);
+        }
+    }
+
+    #[test]
+    fn test_msm_context_verify() {
+        let input_size = 1 << 16;
+        let points = sample_points::<G1Affine>(input_size);
+        let scalars = sample_scalars::<Fr>(input_size);
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        context.set_verify(true);
+        let result = pollster::block_on(context.compute_msm(&points, &scalars)).unwrap();
+        assert_eq!(cpu_msm(&points, &scalars), result);
+
+        let bases = pollster::block_on(context.upload_bases(&points)).unwrap();
+        let result = pollster::block_on(context.compute_msm_fixed_base(&bases, &scalars)).unwrap();
+        assert_eq!(cpu_msm(&points, &scalars), result);
+
+        let batch = vec![scalars.clone(), sample_scalars::<Fr>(input_size)];
+        let results = pollster::block_on(context.compute_msm_batch(&points, &batch)).unwrap();
+        for (instance, result) in batch.iter().zip(results) {
+            assert_eq!(cpu_msm(&points, instance), result);
+        }
+    }
+
+    #[test]
+    fn test_msm_glv() {
+        let input_size = 1 << 16;
+        let points = sample_points::<G1Affine>(input_size);
+        let config = MsmConfig {
+            glv: true,
+            ..Default::default()
+        };
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        context.set_config(config);
+        context.set_verify(true);
+        let scalars = sample_scalars::<Fr>(input_size);
+        let result = pollster::block_on(context.compute_msm(&points, &scalars)).unwrap();
+        assert_eq!(cpu_msm(&points, &scalars), result);
+
+        let bases = pollster::block_on(context.upload_bases(&points)).unwrap();
+        let scalars = sample_scalars::<Fr>(input_size);
+        let result = pollster::block_on(context.compute_msm_fixed_base(&bases, &scalars)).unwrap();
+        assert_eq!(cpu_msm(&points, &scalars), result);
+
+        // The bases only hold the endomorphism images with GLV enabled.
+        context.set_config(MsmConfig::default());
+        let result = pollster::block_on(context.compute_msm_fixed_base(&bases, &scalars));
+        assert!(matches!(result, Err(MsmError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_msm_batch_affine() {
+        let input_size = 1 << 16;
+        let points = sample_points::<G1Affine>(input_size);
+        let scalars = sample_scalars::<Fr>(input_size);
+        let config = MsmConfig {
+            batch_affine: true,
+            ..Default::default()
+        };
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        context.set_config(config);
+        context.set_verify(true);
+        let result = pollster::block_on(context.compute_msm(&points, &scalars)).unwrap();
+        assert_eq!(cpu_msm(&points, &scalars), result);
+    }
+
+    #[test]
+    fn test_msm_identity_base() {
+        let input_size = 1 << 10;
+        let mut points = sample_points::<G1Affine>(input_size);
+        points[3] = G1Affine::identity();
+        points[input_size - 1] = G1Affine::identity();
+        let scalars = sample_scalars::<Fr>(input_size);
+        let expected = cpu_msm(&points, &scalars);
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        let result = pollster::block_on(context.compute_msm(&points, &scalars)).unwrap();
+        assert_eq!(expected, result);
+
+        let bases = pollster::block_on(context.upload_bases(&points)).unwrap();
+        let result = pollster::block_on(context.compute_msm_fixed_base(&bases, &scalars)).unwrap();
+        assert_eq!(expected, result);
+    }
+}
@@ -0,0 +1,318 @@
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use halo2curves::bls12381;
+    use halo2curves::bn256::{self, Fq, G1Affine};
+    use halo2curves::grumpkin;
+    use halo2curves::pasta::{PallasAffine, VestaAffine};
+    use halo2curves::secp256k1::Secp256k1Affine;
+    use halo2curves::secp256r1::Secp256r1Affine;
+    use halo2curves::CurveAffine;
+    use msm_webgpu::cuzk::curve::num_subtasks;
+    use msm_webgpu::cuzk::msm::{compute_msm, P, PARAMS};
+    use msm_webgpu::cuzk::shader_manager::ShaderManager;
+    use msm_webgpu::cuzk::utils::field_to_bytes;
+    use msm_webgpu::{
+        cpu_msm, points_to_bytes, points_to_raw_bytes, sample_points, sample_scalars, CurveModel,
+        GpuCurve,
+    };
+    use num_bigint::BigUint;
+
+    fn check_msm<C: GpuCurve>(input_size: usize) {
+        let points = sample_points::<C>(input_size);
+        let scalars = sample_scalars::<C::Scalar>(input_size);
+        let expected = cpu_msm(&points, &scalars);
+        let result = pollster::block_on(compute_msm(&points, &scalars)).unwrap();
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_bn254_gpu_curve() {
+        assert_eq!(G1Affine::base_modulus(), *P);
+        assert_eq!(G1Affine::scalar_bits(), 254);
+        assert_eq!(G1Affine::curve_a(), BigUint::ZERO);
+        assert_eq!(G1Affine::curve_b(), BigUint::from(3u32));
+        assert_eq!(G1Affine::COORD_BYTES, 32);
+        assert_eq!(G1Affine::num_words(), PARAMS.num_words);
+
+        let params = G1Affine::misc_params();
+        assert_eq!(params.n0, PARAMS.n0);
+        assert_eq!(params.r, PARAMS.r);
+        assert_eq!(params.rinv, PARAMS.rinv);
+    }
+
+    #[test]
+    fn test_base_bytes_round_trip() {
+        let x = Fq::from(123456789u64);
+        let bytes = G1Affine::base_to_bytes(&x);
+        assert_eq!(bytes.len(), G1Affine::COORD_BYTES);
+        assert_eq!(bytes, field_to_bytes(&x));
+        assert_eq!(G1Affine::base_from_bytes(&bytes), x);
+    }
+
+    #[test]
+    fn test_num_subtasks() {
+        // BN254 keeps its 256 bits of windows.
+        assert_eq!(num_subtasks(254, 16), 16);
+        assert_eq!(num_subtasks(254, 4), 64);
+        // A 255-bit scalar needs one more window for the final carry.
+        assert_eq!(num_subtasks(255, 16), 17);
+        assert_eq!(num_subtasks(255, 4), 65);
+    }
+
+    #[test]
+    fn test_bls12_381_gpu_curve() {
+        assert_eq!(bls12381::G1Affine::base_modulus().bits(), 381);
+        assert_eq!(bls12381::G1Affine::scalar_bits(), 255);
+        assert_eq!(bls12381::G1Affine::curve_a(), BigUint::ZERO);
+        assert_eq!(bls12381::G1Affine::curve_b(), BigUint::from(4u32));
+        assert_eq!(bls12381::G1Affine::num_words(), 30);
+
+        // The coordinates are uploaded as 48 little-endian bytes.
+        let x = bls12381::Fq::from(0x0102u64);
+        let bytes = bls12381::G1Affine::base_to_bytes(&x);
+        assert_eq!(bytes.len(), bls12381::G1Affine::COORD_BYTES);
+        assert_eq!(&bytes[..3], &[0x02, 0x01, 0x00]);
+        assert_eq!(bls12381::G1Affine::base_from_bytes(&bytes[..2]), x);
+
+        let p = bls12381::G1Affine::base_modulus();
+        let minus_one = -bls12381::Fq::ONE;
+        assert_eq!(
+            BigUint::from_bytes_le(&bls12381::G1Affine::base_to_bytes(&minus_one)),
+            p - 1u32
+        );
+    }
+
+    #[test]
+    fn test_bls12_381_msm() {
+        check_msm::<bls12381::G1Affine>(1 << 10);
+    }
+
+    #[test]
+    fn test_bls12_381_msm_large() {
+        check_msm::<bls12381::G1Affine>((1 << 16) + 3);
+    }
+
+    #[test]
+    fn test_pasta_gpu_curve() {
+        // The two curves form a cycle: the base field of one is the scalar
+        // field of the other.
+        assert_eq!(PallasAffine::base_modulus().bits(), 255);
+        assert_eq!(VestaAffine::base_modulus().bits(), 255);
+        assert_ne!(PallasAffine::base_modulus(), VestaAffine::base_modulus());
+        for (a, b, num_words) in [
+            (
+                PallasAffine::curve_a(),
+                PallasAffine::curve_b(),
+                PallasAffine::num_words(),
+            ),
+            (
+                VestaAffine::curve_a(),
+                VestaAffine::curve_b(),
+                VestaAffine::num_words(),
+            ),
+        ] {
+            assert_eq!(a, BigUint::ZERO);
+            assert_eq!(b, BigUint::from(5u32));
+            assert_eq!(num_words, 20);
+        }
+        assert_eq!(PallasAffine::scalar_bits(), 255);
+        assert_eq!(VestaAffine::scalar_bits(), 255);
+    }
+
+    #[test]
+    fn test_pallas_msm() {
+        check_msm::<PallasAffine>(1 << 10);
+    }
+
+    #[test]
+    fn test_vesta_msm() {
+        check_msm::<VestaAffine>(1 << 10);
+    }
+
+    #[test]
+    fn test_bn254_g2_gpu_curve() {
+        assert_eq!(bn256::G2Affine::base_modulus(), *P);
+        assert_eq!(bn256::G2Affine::scalar_bits(), 254);
+        assert_eq!(bn256::G2Affine::EXT_DEGREE, 2);
+        assert_eq!(bn256::G2Affine::COORD_BYTES, 64);
+        assert_eq!(bn256::G2Affine::coord_words(), 2 * PARAMS.num_words);
+        assert_eq!(bn256::G2Affine::curve_a(), BigUint::ZERO);
+
+        // The components of b are packed c0 first.
+        let b = bn256::G2Affine::b();
+        let packed = bn256::G2Affine::curve_b();
+        let mut bytes = packed.to_bytes_le();
+        bytes.resize(64, 0);
+        assert_eq!(bytes[..32], field_to_bytes(b.c0()));
+        assert_eq!(bytes[32..], field_to_bytes(b.c1()));
+
+        let x = bn256::Fq2::new(Fq::from(7u64), Fq::from(9u64));
+        let bytes = bn256::G2Affine::base_to_bytes(&x);
+        assert_eq!(bytes.len(), bn256::G2Affine::COORD_BYTES);
+        assert_eq!(bn256::G2Affine::base_from_bytes(&bytes), x);
+    }
+
+    #[test]
+    fn test_bn254_g2_msm() {
+        check_msm::<bn256::G2Affine>(1 << 10);
+    }
+
+    #[test]
+    fn test_grumpkin_gpu_curve() {
+        // Grumpkin and BN254 form a cycle.
+        let p = grumpkin::G1Affine::base_modulus();
+        let bn254_scalar_modulus = BigUint::from_bytes_le(&field_to_bytes(&-bn256::Fr::ONE)) + 1u32;
+        assert_eq!(p, bn254_scalar_modulus);
+        assert_eq!(grumpkin::G1Affine::scalar_bits(), 254);
+        assert_eq!(grumpkin::G1Affine::curve_a(), BigUint::ZERO);
+        assert_eq!(grumpkin::G1Affine::curve_b(), p - 17u32);
+        assert_eq!(grumpkin::G1Affine::num_words(), PARAMS.num_words);
+    }
+
+    #[test]
+    fn test_grumpkin_msm() {
+        check_msm::<grumpkin::G1Affine>(1 << 10);
+    }
+
+    #[test]
+    fn test_secp256_gpu_curve() {
+        // Both moduli fill all 256 bits but still leave slack in 20 limbs.
+        for (p, num_words) in [
+            (
+                Secp256k1Affine::base_modulus(),
+                Secp256k1Affine::num_words(),
+            ),
+            (
+                Secp256r1Affine::base_modulus(),
+                Secp256r1Affine::num_words(),
+            ),
+        ] {
+            assert_eq!(p.bits(), 256);
+            assert_eq!(num_words, 20);
+        }
+        assert_eq!(Secp256k1Affine::scalar_bits(), 256);
+        assert_eq!(Secp256r1Affine::scalar_bits(), 256);
+
+        assert_eq!(Secp256k1Affine::curve_a(), BigUint::ZERO);
+        assert_eq!(Secp256k1Affine::curve_b(), BigUint::from(7u32));
+        assert_eq!(
+            Secp256r1Affine::curve_a(),
+            Secp256r1Affine::base_modulus() - 3u32
+        );
+    }
+
+    #[test]
+    fn test_secp256k1_msm() {
+        check_msm::<Secp256k1Affine>(1 << 10);
+    }
+
+    #[test]
+    fn test_secp256r1_msm() {
+        check_msm::<Secp256r1Affine>(1 << 10);
+    }
+
+    #[test]
+    fn test_curve_model() {
+        assert_eq!(G1Affine::MODEL, CurveModel::ShortWeierstrass);
+        assert_eq!(G1Affine::edwards_d(), BigUint::ZERO);
+        assert_eq!(CurveModel::ShortWeierstrass.coord_names(), ["X", "Y", "Z"]);
+        assert_eq!(
+            CurveModel::TwistedEdwards.coord_names(),
+            ["X", "Y", "Z", "T"]
+        );
+    }
+
+    #[test]
+    fn test_twisted_edwards_shaders() {
+        // Baby Jubjub, defined over the BN254 scalar field.
+        let p = BigUint::from_bytes_le(&field_to_bytes(&-bn256::Fr::ONE)) + 1u32;
+        let a = BigUint::from(168700u32);
+        let d = BigUint::from(168696u32);
+        let shader_manager = ShaderManager::for_twisted_edwards(&p, &a, &d, 13, 4, 1 << 10);
+
+        let smvp = shader_manager.gen_smvp_shader(64, 16).unwrap();
+        assert!(smvp.contains("get_edwards_d"));
+        assert!(smvp.contains("bucket_t"));
+        let bpr = shader_manager.gen_bpr_shader(64).unwrap();
+        assert!(bpr.contains("g_points_t"));
+        let test_point = shader_manager.gen_test_point_shader().unwrap();
+        assert!(test_point.contains("coord_to_mont(p.t)"));
+
+        let weierstrass = ShaderManager::new(13, 4, 1 << 10)
+            .gen_smvp_shader(64, 16)
+            .unwrap();
+        assert!(!weierstrass.contains("bucket_t"));
+    }
+
+    #[test]
+    fn test_horner_shader() {
+        let shader_manager = ShaderManager::new(13, 16, 1 << 10);
+        let horner = shader_manager.gen_horner_shader(64, 16, 256).unwrap();
+        assert!(horner.contains("fn window_sums("));
+        assert!(horner.contains("fn horner("));
+        assert!(horner.contains("const NUM_SUBTASKS = 16u;"));
+        assert!(horner.contains("const CHUNK_SIZE = 16u;"));
+        assert!(horner.contains("const NUM_COORDS = 3u;"));
+        assert!(!horner.contains("g_points_t"));
+
+        let p = BigUint::from_bytes_le(&field_to_bytes(&-bn256::Fr::ONE)) + 1u32;
+        let edwards = ShaderManager::for_twisted_edwards(
+            &p,
+            &BigUint::from(168700u32),
+            &BigUint::from(168696u32),
+            13,
+            16,
+            1 << 10,
+        );
+        let horner = edwards.gen_horner_shader(64, 16, 256).unwrap();
+        assert!(horner.contains("g_points_t"));
+        assert!(horner.contains("const NUM_COORDS = 4u;"));
+    }
+
+    fn check_raw_montgomery<C: GpuCurve>() {
+        assert!(C::RAW_MONTGOMERY);
+        let p = C::base_modulus();
+        let component_bytes = C::COORD_BYTES / C::EXT_DEGREE;
+        let radix = BigUint::from(1u32) << (8 * component_bytes);
+        let points = sample_points::<C>(8);
+        let canonical = points_to_bytes(&points);
+        let raw = points_to_raw_bytes(&points);
+        assert_eq!(canonical.len(), raw.len());
+        for (c, r) in canonical
+            .chunks(component_bytes)
+            .zip(raw.chunks(component_bytes))
+        {
+            assert_eq!(
+                BigUint::from_bytes_le(r),
+                BigUint::from_bytes_le(c) * &radix % &p
+            );
+        }
+    }
+
+    #[test]
+    fn test_raw_montgomery_bytes() {
+        check_raw_montgomery::<G1Affine>();
+        check_raw_montgomery::<bn256::G2Affine>();
+        check_raw_montgomery::<bls12381::G1Affine>();
+        check_raw_montgomery::<grumpkin::G1Affine>();
+        check_raw_montgomery::<PallasAffine>();
+        check_raw_montgomery::<VestaAffine>();
+        check_raw_montgomery::<Secp256k1Affine>();
+        check_raw_montgomery::<Secp256r1Affine>();
+    }
+
+    #[test]
+    fn test_raw_montgomery_shaders() {
+        let mut shader_manager = ShaderManager::new(13, 4, 1 << 10);
+        let canonical = shader_manager
+            .gen_convert_point_coords_shader(64, 1)
+            .unwrap();
+        shader_manager.set_raw_montgomery_input(true);
+        let raw = shader_manager
+            .gen_convert_point_coords_shader(64, 1)
+            .unwrap();
+        assert_ne!(canonical, raw);
+        assert!(raw.contains("fn get_to_mont()"));
+    }
+}
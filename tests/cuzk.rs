@@ -1,15 +1,17 @@
-
 #[cfg(test)]
 mod tests {
+    use group::{Curve, Group};
     use halo2curves::bn256::{Fr, G1Affine, G1};
     use msm_webgpu::cuzk::test::utils::*;
-    use msm_webgpu::{cpu_msm, sample_points, sample_scalars};
-    use group::{Curve, Group};
-    use rand::Rng;
+    use msm_webgpu::{
+        cpu_msm, sample_points, sample_points_with_rng, sample_scalars, sample_scalars_with_rng,
+        MsmPlan,
+    };
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
 
     #[test]
     fn test_cuzk() {
-
         // let input_size = rand::thread_rng().gen_range(1 << 16..1 << 20);
         let input_size: usize = (1 << 16) + 4;
         let next_power_of_two = input_size.next_power_of_two();
@@ -18,12 +20,11 @@ mod tests {
 
         let input_size = next_power_of_two;
 
-        let chunk_size = if input_size >= 65536 { 16 } else { 4 };
-        let num_columns = 1 << chunk_size;
-        let num_rows = (input_size + num_columns - 1) / num_columns;
-        let num_chunks_per_scalar = (256 + chunk_size - 1) / chunk_size;
-        let num_subtasks = num_chunks_per_scalar;
-
+        let plan = MsmPlan::for_input_size::<G1Affine>(input_size).unwrap();
+        let chunk_size = plan.chunk_size;
+        let num_columns = plan.num_columns();
+        let num_rows = input_size.div_ceil(num_columns);
+        let num_subtasks = plan.num_subtasks;
 
         let decomposed_scalars = decompose_scalars_signed(&scalars, num_subtasks, chunk_size);
 
@@ -54,7 +55,7 @@ mod tests {
 
             let mut bucket_sum = G1::identity();
             for b in parallel_bucket_reduction(&buckets, 4) {
-                bucket_sum = bucket_sum + b;
+                bucket_sum += b;
             }
 
             assert_eq!(buckets_sum_serial, bucket_sum);
@@ -69,7 +70,7 @@ mod tests {
 
             let mut bucket_sum_2 = G1::identity();
             for b in p_result {
-                bucket_sum_2 = bucket_sum_2 + b;
+                bucket_sum_2 += b;
             }
 
             assert_eq!(buckets_sum_serial, bucket_sum_2);
@@ -81,8 +82,7 @@ mod tests {
         let m = 1 << chunk_size;
         let mut result = bucket_sums[bucket_sums.len() - 1];
         for i in (0..bucket_sums.len() - 1).rev() {
-            result = result * Fr::from(m as u64);
-            result = result + bucket_sums[i];
+            result = result * Fr::from(m as u64) + bucket_sums[i];
         }
 
         let result_affine = result.to_affine();
@@ -93,4 +93,35 @@ mod tests {
         assert_eq!(result_affine.x, expected_affine.x);
         assert_eq!(result_affine.y, expected_affine.y);
     }
+
+    #[test]
+    fn test_cpu_window_sum() {
+        let input_size = 64;
+        let scalars = sample_scalars::<Fr>(input_size);
+        let points = sample_points::<G1Affine>(input_size);
+
+        for chunk_size in [4, 16] {
+            let num_subtasks = 256 / chunk_size;
+            let m = Fr::from(1 << chunk_size);
+            let mut result = G1::identity();
+            for window in (0..num_subtasks).rev() {
+                result = result * m
+                    + cpu_window_sum(&points, &scalars, window, num_subtasks, chunk_size);
+            }
+            assert_eq!(result, cpu_msm(&points, &scalars));
+        }
+    }
+
+    #[test]
+    fn test_sample_with_seeded_rng() {
+        let sample = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (
+                sample_points_with_rng::<G1Affine>(4, &mut rng),
+                sample_scalars_with_rng::<Fr>(4, &mut rng),
+            )
+        };
+        assert_eq!(sample(42), sample(42));
+        assert_ne!(sample(42), sample(43));
+    }
 }
@@ -1,25 +1,22 @@
 use std::{iter::zip, time::Instant};
 
-use halo2curves::CurveAffine;
 use wgpu::CommandEncoderDescriptor;
 
 use msm_webgpu::cuzk::{
     gpu::{get_adapter, get_device, read_from_gpu_test},
-    msm::{P, PARAMS, WORD_SIZE, convert_point_coords_and_decompose_shaders},
+    msm::{convert_point_coords_and_decompose_shaders, P, PARAMS, WORD_SIZE},
     shader_manager::ShaderManager,
     utils::{bytes_to_field, debug, to_biguint_le},
 };
-use msm_webgpu::{points_to_bytes, scalars_to_bytes};
+use msm_webgpu::{points_to_bytes, scalars_to_bytes, GpuCurve, MsmPlan};
 
-async fn decompose_shader<C: CurveAffine>(
-    points: &[C],
-    scalars: &[C::Scalar],
-) -> (Vec<C>, Vec<u8>) {
+async fn decompose_shader<C: GpuCurve>(points: &[C], scalars: &[C::Scalar]) -> (Vec<C>, Vec<u8>) {
     let input_size = scalars.len();
-    let chunk_size = if input_size >= 65536 { 16 } else { 4 };
-    let num_columns = 1 << chunk_size;
+    let plan = MsmPlan::for_input_size::<C>(input_size).unwrap();
+    let chunk_size = plan.chunk_size;
+    let num_columns = plan.num_columns();
     let num_rows = input_size.div_ceil(num_columns);
-    let num_subtasks = 256_usize.div_ceil(chunk_size);
+    let num_subtasks = plan.num_subtasks;
     let num_words = PARAMS.num_words;
     debug(&format!("Input size: {input_size}"));
     debug(&format!("Chunk size: {chunk_size}"));
@@ -35,8 +32,8 @@ async fn decompose_shader<C: CurveAffine>(
 
     let shader_manager = ShaderManager::new(WORD_SIZE, chunk_size, input_size);
 
-    let adapter = get_adapter().await;
-    let (device, queue) = get_device(&adapter).await;
+    let adapter = get_adapter().await.unwrap();
+    let (device, queue) = get_device(&adapter).await.unwrap();
     let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
         label: Some("Decompose Encoder"),
     });
@@ -63,32 +60,36 @@ async fn decompose_shader<C: CurveAffine>(
         c_workgroup_size = 256;
         c_num_x_workgroups = 32;
         c_num_y_workgroups = input_size / c_workgroup_size / c_num_x_workgroups;
-    } 
+    }
 
-    let c_shader = shader_manager.gen_decomp_scalars_shader(
-        c_workgroup_size,
-        c_num_y_workgroups,
-        num_subtasks,
-        num_columns,
-    );
+    let c_shader = shader_manager
+        .gen_decomp_scalars_shader(
+            c_workgroup_size,
+            c_num_y_workgroups,
+            num_subtasks,
+            num_columns,
+        )
+        .unwrap();
 
     // println!("C shader: {}", c_shader);
 
-    let (point_x_sb, point_y_sb, scalar_chunks_sb) = convert_point_coords_and_decompose_shaders(
-        &c_shader,
-        c_num_x_workgroups,
-        c_num_y_workgroups,
-        c_num_z_workgroups,
-        &device,
-        &queue,
-        &mut encoder,
-        &point_bytes,
-        &scalar_bytes,
-        num_subtasks,
-        chunk_size,
-        num_words,
-    )
-    .await;
+    let (point_x_sb, point_y_sb, scalar_chunks_sb) =
+        convert_point_coords_and_decompose_shaders::<C>(
+            &c_shader,
+            c_num_x_workgroups,
+            c_num_y_workgroups,
+            c_num_z_workgroups,
+            &device,
+            &queue,
+            &mut encoder,
+            &point_bytes,
+            &scalar_bytes,
+            num_subtasks,
+            chunk_size,
+            num_words,
+        )
+        .await
+        .unwrap();
     // Map results back from GPU to CPU.
     let data = read_from_gpu_test(
         &device,
@@ -122,15 +123,12 @@ async fn decompose_shader<C: CurveAffine>(
 }
 
 /// Run WebGPU decompose sync
-pub fn run_webgpu_decompose<C: CurveAffine>(
-    points: &[C],
-    scalars: &[C::Scalar],
-) -> (Vec<C>, Vec<u8>) {
+pub fn run_webgpu_decompose<C: GpuCurve>(points: &[C], scalars: &[C::Scalar]) -> (Vec<C>, Vec<u8>) {
     pollster::block_on(run_webgpu_decompose_async(points, scalars))
 }
 
 /// Run WebGPU decompose async
-pub async fn run_webgpu_decompose_async<C: CurveAffine>(
+pub async fn run_webgpu_decompose_async<C: GpuCurve>(
     points: &[C],
     scalars: &[C::Scalar],
 ) -> (Vec<C>, Vec<u8>) {
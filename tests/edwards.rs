@@ -0,0 +1,597 @@
+use std::time::Instant;
+
+use ff::{Field, PrimeField};
+use halo2curves::{bls12381, bn256};
+use num_bigint::BigUint;
+use rand::Rng;
+use wgpu::CommandEncoderDescriptor;
+
+use msm_webgpu::cuzk::{
+    gpu::{
+        create_and_write_storage_buffer, create_and_write_uniform_buffer, create_bind_group,
+        create_bind_group_layout, create_compute_pipeline, create_storage_buffer, execute_pipeline,
+        get_adapter, get_device, read_from_gpu_test,
+    },
+    msm::{to_u8s_for_gpu, WORD_SIZE},
+    shader_manager::ShaderManager,
+    test::utils::{cpu_transpose, decompose_scalars_signed},
+    utils::{
+        bytes_to_field, compute_misc_params, field_to_bytes, to_biguint_le, to_words_le, MiscParams,
+    },
+};
+
+/// Jubjub, defined over the BLS12-381 scalar field, with a = -1
+fn jubjub() -> TwistedEdwards<bls12381::Fr> {
+    TwistedEdwards {
+        a: -bls12381::Fr::ONE,
+        d: -bls12381::Fr::from(10240) * bls12381::Fr::from(10241).invert().unwrap(),
+    }
+}
+
+/// Baby Jubjub, defined over the BN254 scalar field, with a general a
+fn baby_jubjub() -> TwistedEdwards<bn256::Fr> {
+    TwistedEdwards {
+        a: bn256::Fr::from(168700),
+        d: bn256::Fr::from(168696),
+    }
+}
+
+/// An affine point of a twisted Edwards curve
+type Affine<F> = (F, F);
+
+/// The twisted Edwards curve `ax^2 + y^2 = 1 + dx^2y^2` over `F`, with the
+/// affine group law as the CPU reference for the shaders.
+#[derive(Clone, Copy, Debug)]
+struct TwistedEdwards<F> {
+    a: F,
+    d: F,
+}
+
+impl<F: PrimeField> TwistedEdwards<F> {
+    fn identity() -> Affine<F> {
+        (F::ZERO, F::ONE)
+    }
+
+    fn add(&self, p: Affine<F>, q: Affine<F>) -> Affine<F> {
+        let t = self.d * p.0 * q.0 * p.1 * q.1;
+        let x = (p.0 * q.1 + p.1 * q.0) * (F::ONE + t).invert().unwrap();
+        let y = (p.1 * q.1 - self.a * p.0 * q.0) * (F::ONE - t).invert().unwrap();
+        (x, y)
+    }
+
+    fn neg(p: Affine<F>) -> Affine<F> {
+        (-p.0, p.1)
+    }
+
+    fn mul(&self, p: Affine<F>, scalar: i64) -> Affine<F> {
+        let mut result = Self::identity();
+        let mut temp = if scalar < 0 { Self::neg(p) } else { p };
+        let mut s = scalar.unsigned_abs();
+        while s != 0 {
+            if s & 1 == 1 {
+                result = self.add(result, temp);
+            }
+            temp = self.add(temp, temp);
+            s >>= 1;
+        }
+        result
+    }
+
+    /// Sample a point by solving the curve equation for x at a random y
+    fn random_point(&self, rng: &mut impl Rng) -> Affine<F> {
+        loop {
+            let y = F::random(&mut *rng);
+            let y2 = y.square();
+            let x2 = (F::ONE - y2) * (self.a - self.d * y2).invert().unwrap();
+            if let Some(x) = Option::<F>::from(x2.sqrt()) {
+                return (x, y);
+            }
+        }
+    }
+
+    /// The field modulus of the curve
+    fn modulus() -> BigUint {
+        to_biguint(&-F::ONE) + 1u32
+    }
+
+    fn shader_manager(&self, chunk_size: usize, input_size: usize) -> ShaderManager {
+        ShaderManager::for_twisted_edwards(
+            &Self::modulus(),
+            &to_biguint(&self.a),
+            &to_biguint(&self.d),
+            WORD_SIZE,
+            chunk_size,
+            input_size,
+        )
+    }
+}
+
+fn to_biguint<F: PrimeField>(value: &F) -> BigUint {
+    BigUint::from_bytes_le(&field_to_bytes(value))
+}
+
+/// Split field elements into `WORD_SIZE`-bit limbs for the GPU, multiplied
+/// by `factor` modulo the field modulus, e.g. by `r` for Montgomery form.
+fn fields_to_u8_vec_for_gpu<F: PrimeField>(
+    values: &[F],
+    params: &MiscParams,
+    factor: &BigUint,
+) -> Vec<u8> {
+    let p = TwistedEdwards::<F>::modulus();
+    let limbs = values
+        .iter()
+        .flat_map(|value| {
+            to_words_le(
+                &(to_biguint(value) * factor % &p),
+                params.num_words,
+                WORD_SIZE,
+            )
+        })
+        .collect::<Vec<_>>();
+    bytemuck::cast_slice::<u32, u8>(&limbs).to_vec()
+}
+
+/// Convert GPU limbs to field elements, multiplied by `factor` modulo the
+/// field modulus, e.g. by `rinv` to leave Montgomery form.
+fn u8_vec_to_fields<F: PrimeField>(data: &[u8], params: &MiscParams, factor: &BigUint) -> Vec<F> {
+    let p = TwistedEdwards::<F>::modulus();
+    bytemuck::cast_slice::<u8, u32>(data)
+        .chunks(params.num_words)
+        .map(|chunk| {
+            let value = to_biguint_le(chunk, params.num_words, WORD_SIZE as u32) * factor % &p;
+            bytes_to_field(&value.to_bytes_le())
+        })
+        .collect()
+}
+
+/// Convert the extended coordinates `[x, y, t, z]` of a GPU result to an
+/// affine point, checking that `t` matches `x` and `y`.
+fn extended_to_affine<F: PrimeField>(coords: &[F]) -> Affine<F> {
+    let [x, y, t, z] = coords else {
+        panic!("expected 4 coordinates, got {}", coords.len());
+    };
+    assert_eq!(*t * z, *x * y, "t does not match x and y");
+    let z_inv = z.invert().unwrap();
+    (*x * z_inv, *y * z_inv)
+}
+
+async fn point_op<F: PrimeField>(
+    curve: &TwistedEdwards<F>,
+    op: &str,
+    a: Affine<F>,
+    b: Affine<F>,
+    scalar: u32,
+) -> Affine<F> {
+    let shader_manager = curve.shader_manager(4, 1);
+    let params = compute_misc_params(&TwistedEdwards::<F>::modulus(), WORD_SIZE);
+    let num_words = params.num_words;
+
+    // The test point shader takes canonical coordinates in the order of the
+    // fields of Point, i.e. [x, y, t, z].
+    let one = BigUint::from(1u32);
+    let a_bytes = fields_to_u8_vec_for_gpu(&[a.0, a.1, a.0 * a.1, F::ONE], &params, &one);
+    let b_bytes = fields_to_u8_vec_for_gpu(&[b.0, b.1, b.0 * b.1, F::ONE], &params, &one);
+    let scalar_bytes = scalar.to_le_bytes();
+
+    let adapter = get_adapter().await.unwrap();
+    let (device, queue) = get_device(&adapter).await.unwrap();
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("Point Encoder"),
+    });
+
+    let shader_code = shader_manager.gen_test_point_shader().unwrap();
+
+    let a_sb = create_and_write_storage_buffer(Some("A buffer"), &device, &a_bytes);
+    let b_sb = create_and_write_storage_buffer(Some("B buffer"), &device, &b_bytes);
+    let result_sb =
+        create_storage_buffer(Some("Result buffer"), &device, (4 * num_words * 4) as u64);
+    let scalar_sb =
+        create_and_write_uniform_buffer(Some("Scalar buffer"), &device, &queue, &scalar_bytes);
+
+    let bind_group_layout = create_bind_group_layout(
+        Some("Bind group layout"),
+        &device,
+        vec![],
+        vec![&a_sb, &b_sb, &result_sb],
+        vec![&scalar_sb],
+    );
+    let bind_group = create_bind_group(
+        Some("Bind group"),
+        &device,
+        &bind_group_layout,
+        vec![&a_sb, &b_sb, &result_sb, &scalar_sb],
+    );
+    let compute_pipeline = create_compute_pipeline(
+        Some("Point shader"),
+        &device,
+        &bind_group_layout,
+        &shader_code,
+        op,
+    )
+    .await
+    .unwrap();
+
+    execute_pipeline(&mut encoder, compute_pipeline, bind_group, 1, 1, 1).await;
+
+    let data = read_from_gpu_test(&device, &queue, encoder, vec![result_sb]).await;
+    device.destroy();
+
+    extended_to_affine(&u8_vec_to_fields::<F>(&data[0], &params, &params.rinv))
+}
+
+/// Run a point op of the test point shader on a twisted Edwards curve
+fn run_webgpu_edwards_point_op<F: PrimeField>(
+    curve: &TwistedEdwards<F>,
+    op: &str,
+    a: Affine<F>,
+    b: Affine<F>,
+    scalar: u32,
+) -> Affine<F> {
+    let now = Instant::now();
+    let result = pollster::block_on(point_op(curve, op, a, b, scalar));
+    println!("Point op time: {:?}", now.elapsed());
+    result
+}
+
+/// The window sums and the result of an MSM run through the SMVP, BPR and
+/// Horner shaders
+struct EdwardsMsm<F> {
+    windows: Vec<Affine<F>>,
+    result: Affine<F>,
+}
+
+/// Run the SMVP, BPR and Horner shaders of a twisted Edwards curve on
+/// scalars decomposed and transposed on the CPU.
+async fn bucket_stages<F: PrimeField>(
+    curve: &TwistedEdwards<F>,
+    points: &[Affine<F>],
+    scalars: &[F],
+    chunk_size: usize,
+    num_subtasks: usize,
+) -> EdwardsMsm<F> {
+    let input_size = points.len();
+    let num_columns = 1 << chunk_size;
+    let half_num_columns = num_columns / 2;
+    let num_rows = input_size.div_ceil(num_columns);
+    let params = compute_misc_params(&TwistedEdwards::<F>::modulus(), WORD_SIZE);
+    let num_words = params.num_words;
+    let coord_bytelength = (num_words * 4) as u64;
+
+    let decomposed_scalars = decompose_scalars_signed(scalars, num_subtasks, chunk_size);
+    let (all_csc_col_ptr, _, all_csc_vals) = cpu_transpose(
+        decomposed_scalars.concat(),
+        num_columns,
+        num_rows,
+        num_subtasks,
+        input_size,
+    );
+
+    let shader_manager = curve.shader_manager(chunk_size, input_size);
+
+    let adapter = get_adapter().await.unwrap();
+    let (device, queue) = get_device(&adapter).await.unwrap();
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("Edwards MSM Encoder"),
+    });
+
+    // The SMVP shader reads the points in Montgomery form.
+    let (xs, ys): (Vec<F>, Vec<F>) = points.iter().copied().unzip();
+    let col_ptr_sb = create_and_write_storage_buffer(
+        Some("CSC col ptr buffer"),
+        &device,
+        bytemuck::cast_slice(&all_csc_col_ptr),
+    );
+    let val_idxs_sb = create_and_write_storage_buffer(
+        Some("CSC val idxs buffer"),
+        &device,
+        bytemuck::cast_slice(&all_csc_vals),
+    );
+    let point_x_sb = create_and_write_storage_buffer(
+        Some("Point X buffer"),
+        &device,
+        &fields_to_u8_vec_for_gpu(&xs, &params, &params.r),
+    );
+    let point_y_sb = create_and_write_storage_buffer(
+        Some("Point Y buffer"),
+        &device,
+        &fields_to_u8_vec_for_gpu(&ys, &params, &params.r),
+    );
+
+    // Buffers in the order of CurveModel::coord_names, i.e. x, y, z and t.
+    let coord_buffers = |label: &str, len: usize| {
+        ["X", "Y", "Z", "T"].map(|coord| {
+            create_storage_buffer(
+                Some(&format!("{label} {coord} buffer")),
+                &device,
+                len as u64 * coord_bytelength,
+            )
+        })
+    };
+
+    // SMVP: one thread per pair of buckets, one workgroup per subtask.
+    let bucket_sum_sbs = coord_buffers("Bucket sum", num_subtasks * half_num_columns);
+    let smvp_params_ub = create_and_write_uniform_buffer(
+        Some("SMVP params buffer"),
+        &device,
+        &queue,
+        &to_u8s_for_gpu(vec![input_size, 1, 1, 0]).unwrap(),
+    );
+    let smvp_shader = shader_manager
+        .gen_smvp_shader(half_num_columns, num_columns)
+        .unwrap();
+    let smvp_layout = create_bind_group_layout(
+        Some("SMVP bind group layout"),
+        &device,
+        vec![&col_ptr_sb, &val_idxs_sb, &point_x_sb, &point_y_sb],
+        bucket_sum_sbs.iter().collect(),
+        vec![&smvp_params_ub],
+    );
+    let smvp_bind_group = create_bind_group(
+        Some("SMVP bind group"),
+        &device,
+        &smvp_layout,
+        [&col_ptr_sb, &val_idxs_sb, &point_x_sb, &point_y_sb]
+            .into_iter()
+            .chain(&bucket_sum_sbs)
+            .chain([&smvp_params_ub])
+            .collect(),
+    );
+    let smvp_pipeline = create_compute_pipeline(
+        Some("SMVP shader"),
+        &device,
+        &smvp_layout,
+        &smvp_shader,
+        "main",
+    )
+    .await
+    .unwrap();
+    execute_pipeline(
+        &mut encoder,
+        smvp_pipeline,
+        smvp_bind_group,
+        num_subtasks as u32,
+        1,
+        1,
+    )
+    .await;
+
+    // BPR: one subtask per dispatch, with two buckets per thread.
+    let bpr_workgroup_size = half_num_columns / 2;
+    let g_points_sbs = coord_buffers("G points", num_subtasks * bpr_workgroup_size);
+    let bpr_shader = shader_manager.gen_bpr_shader(bpr_workgroup_size).unwrap();
+    let bpr_params_ubs = (0..num_subtasks)
+        .map(|subtask_idx| {
+            create_and_write_uniform_buffer(
+                Some("BPR params buffer"),
+                &device,
+                &queue,
+                &to_u8s_for_gpu(vec![subtask_idx, num_columns, 1]).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    // The x, y and z buffers of the bucket sums and g points come first,
+    // followed by their t buffers.
+    let bpr_storage = bucket_sum_sbs[..3]
+        .iter()
+        .chain(&g_points_sbs[..3])
+        .chain(&bucket_sum_sbs[3..])
+        .chain(&g_points_sbs[3..])
+        .collect::<Vec<_>>();
+    let bpr_layout = create_bind_group_layout(
+        Some("BPR bind group layout"),
+        &device,
+        vec![],
+        bpr_storage.clone(),
+        vec![&bpr_params_ubs[0]],
+    );
+    for entry_point in ["stage_1", "stage_2"] {
+        let bpr_pipeline = create_compute_pipeline(
+            Some("BPR shader"),
+            &device,
+            &bpr_layout,
+            &bpr_shader,
+            entry_point,
+        )
+        .await
+        .unwrap();
+        for params_ub in &bpr_params_ubs {
+            let bind_group = create_bind_group(
+                Some("BPR bind group"),
+                &device,
+                &bpr_layout,
+                bpr_storage.iter().copied().chain([params_ub]).collect(),
+            );
+            execute_pipeline(&mut encoder, bpr_pipeline.clone(), bind_group, 1, 1, 1).await;
+        }
+    }
+
+    // Window sums and Horner's method.
+    let windows_sb = create_storage_buffer(
+        Some("Windows buffer"),
+        &device,
+        (num_subtasks * 4) as u64 * coord_bytelength,
+    );
+    let result_sb = create_storage_buffer(Some("Result buffer"), &device, 4 * coord_bytelength);
+    let horner_shader = shader_manager
+        .gen_horner_shader(num_subtasks, num_subtasks, bpr_workgroup_size)
+        .unwrap();
+    let horner_layout = create_bind_group_layout(
+        Some("Horner bind group layout"),
+        &device,
+        g_points_sbs.iter().collect(),
+        vec![&windows_sb, &result_sb],
+        vec![],
+    );
+    for entry_point in ["window_sums", "horner"] {
+        let pipeline = create_compute_pipeline(
+            Some("Horner shader"),
+            &device,
+            &horner_layout,
+            &horner_shader,
+            entry_point,
+        )
+        .await
+        .unwrap();
+        let bind_group = create_bind_group(
+            Some("Horner bind group"),
+            &device,
+            &horner_layout,
+            g_points_sbs
+                .iter()
+                .chain([&windows_sb, &result_sb])
+                .collect(),
+        );
+        execute_pipeline(&mut encoder, pipeline, bind_group, 1, 1, 1).await;
+    }
+
+    let data = read_from_gpu_test(&device, &queue, encoder, vec![windows_sb, result_sb]).await;
+    device.destroy();
+
+    // The windows are in Montgomery form, as x, y, z and t per subtask, and
+    // the result is not.
+    let windows = u8_vec_to_fields::<F>(&data[0], &params, &params.rinv)
+        .chunks(4)
+        .map(|c| extended_to_affine(&[c[0], c[1], c[3], c[2]]))
+        .collect();
+    let one = BigUint::from(1u32);
+    let result = u8_vec_to_fields::<F>(&data[1], &params, &one);
+    EdwardsMsm {
+        windows,
+        result: extended_to_affine(&[result[0], result[1], result[3], result[2]]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    fn check_point_add<F: PrimeField>(curve: &TwistedEdwards<F>) {
+        let mut rng = thread_rng();
+        let a = curve.random_point(&mut rng);
+        let b = curve.random_point(&mut rng);
+
+        let result = run_webgpu_edwards_point_op(curve, "test_point_add", a, b, 0);
+        assert_eq!(curve.add(a, b), result);
+
+        // The complete addition law also doubles and cancels points.
+        let result = run_webgpu_edwards_point_op(curve, "test_point_add", a, a, 0);
+        assert_eq!(curve.add(a, a), result);
+        let result =
+            run_webgpu_edwards_point_op(curve, "test_point_add", a, TwistedEdwards::neg(a), 0);
+        assert_eq!(TwistedEdwards::identity(), result);
+    }
+
+    fn check_point_add_mixed<F: PrimeField>(curve: &TwistedEdwards<F>) {
+        let mut rng = thread_rng();
+        let a = curve.random_point(&mut rng);
+        let b = curve.random_point(&mut rng);
+
+        let expected = curve.add(curve.add(a, a), b);
+        let result = run_webgpu_edwards_point_op(curve, "test_point_add_mixed", a, b, 0);
+        assert_eq!(expected, result);
+    }
+
+    fn check_double_and_add<F: PrimeField>(curve: &TwistedEdwards<F>) {
+        let mut rng = thread_rng();
+        let a = curve.random_point(&mut rng);
+        let scalar = rng.gen::<u16>() as u32;
+
+        let result = run_webgpu_edwards_point_op(curve, "test_double_and_add", a, a, scalar);
+        assert_eq!(curve.mul(a, scalar as i64), result);
+    }
+
+    fn check_point_add_identity<F: PrimeField>(curve: &TwistedEdwards<F>) {
+        let mut rng = thread_rng();
+        let a = curve.random_point(&mut rng);
+
+        let result = run_webgpu_edwards_point_op(curve, "test_point_add_identity", a, a, 0);
+        assert_eq!(a, result);
+        let result = run_webgpu_edwards_point_op(curve, "test_point_add_mixed_identity", a, a, 0);
+        assert_eq!(a, result);
+    }
+
+    /// Compare the window sums and the result of a small MSM against the
+    /// CPU, with scalars below `2^16` split into five signed 4-bit windows.
+    fn check_bucket_stages<F: PrimeField>(curve: &TwistedEdwards<F>) {
+        let mut rng = thread_rng();
+        let input_size = 64;
+        let chunk_size = 4;
+        let num_subtasks = 5;
+        let points = (0..input_size)
+            .map(|_| curve.random_point(&mut rng))
+            .collect::<Vec<_>>();
+        let scalars = (0..input_size)
+            .map(|_| rng.gen::<u16>() as u64)
+            .collect::<Vec<_>>();
+        let field_scalars = scalars.iter().map(|s| F::from(*s)).collect::<Vec<_>>();
+
+        let now = Instant::now();
+        let msm = pollster::block_on(bucket_stages(
+            curve,
+            &points,
+            &field_scalars,
+            chunk_size,
+            num_subtasks,
+        ));
+        println!("Edwards MSM time: {:?}", now.elapsed());
+
+        // The decomposed scalars are offset by half the number of columns.
+        let shift = 1 << (chunk_size - 1);
+        let decomposed_scalars = decompose_scalars_signed(&field_scalars, num_subtasks, chunk_size);
+        let windows = decomposed_scalars
+            .iter()
+            .map(|digits| {
+                points
+                    .iter()
+                    .zip(digits)
+                    .fold(TwistedEdwards::identity(), |acc, (point, digit)| {
+                        curve.add(acc, curve.mul(*point, (digit - shift) as i64))
+                    })
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(windows, msm.windows);
+
+        let expected = points
+            .iter()
+            .zip(&scalars)
+            .fold(TwistedEdwards::identity(), |acc, (point, scalar)| {
+                curve.add(acc, curve.mul(*point, *scalar as i64))
+            });
+        assert_eq!(expected, msm.result);
+    }
+
+    #[test]
+    fn test_webgpu_edwards_point_add() {
+        check_point_add(&jubjub());
+        check_point_add(&baby_jubjub());
+    }
+
+    #[test]
+    fn test_webgpu_edwards_point_add_mixed() {
+        check_point_add_mixed(&jubjub());
+        check_point_add_mixed(&baby_jubjub());
+    }
+
+    #[test]
+    fn test_webgpu_edwards_double_and_add() {
+        check_double_and_add(&jubjub());
+        check_double_and_add(&baby_jubjub());
+    }
+
+    #[test]
+    fn test_webgpu_edwards_point_add_identity() {
+        check_point_add_identity(&jubjub());
+        check_point_add_identity(&baby_jubjub());
+    }
+
+    #[test]
+    fn test_webgpu_jubjub_bucket_stages() {
+        check_bucket_stages(&jubjub());
+    }
+
+    #[test]
+    fn test_webgpu_baby_jubjub_bucket_stages() {
+        check_bucket_stages(&baby_jubjub());
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine};
+    use msm_webgpu::cuzk::context::MsmContext;
+    use msm_webgpu::cuzk::msm::to_u8s_for_gpu;
+    use msm_webgpu::{
+        cpu_msm, run_webgpu_msm_with_context, sample_points, sample_scalars, MsmBackend, MsmError,
+    };
+
+    #[test]
+    fn test_to_u8s_for_gpu_overflow() {
+        assert_eq!(
+            to_u8s_for_gpu(vec![1, u32::MAX as usize]).unwrap(),
+            [1, 0, 0, 0, 255, 255, 255, 255]
+        );
+        assert!(matches!(
+            to_u8s_for_gpu(vec![1 << 32]),
+            Err(MsmError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_msm_invalid_input() {
+        let points = sample_points::<G1Affine>(4);
+        let scalars = sample_scalars::<Fr>(3);
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        let result = pollster::block_on(context.compute_msm(&points, &scalars));
+        assert!(matches!(result, Err(MsmError::InvalidInput(_))));
+        let result = pollster::block_on(context.compute_msm::<G1Affine>(&[], &[]));
+        assert!(matches!(result, Err(MsmError::InvalidInput(_))));
+        assert_eq!(context.num_cached_pipelines(), 0);
+    }
+
+    #[test]
+    fn test_gpu_unavailable() {
+        assert!(MsmError::NoAdapter.is_gpu_unavailable());
+        assert!(MsmError::DeviceLost("Unknown".to_string()).is_gpu_unavailable());
+        assert!(!MsmError::InvalidInput("empty MSM".to_string()).is_gpu_unavailable());
+        assert!(!MsmError::Shader("parse error".to_string()).is_gpu_unavailable());
+        assert!(!MsmError::BufferMap(wgpu::BufferAsyncError).is_gpu_unavailable());
+        assert!(!MsmError::InvalidOutput("not on the curve".to_string()).is_gpu_unavailable());
+    }
+
+    #[test]
+    fn test_msm_device_lost() {
+        let points = sample_points::<G1Affine>(4);
+        let scalars = sample_scalars::<Fr>(4);
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        context.device().destroy();
+        context.device().poll(wgpu::Maintain::Wait);
+        assert!(context.is_lost());
+        let result = pollster::block_on(context.compute_msm(&points, &scalars));
+        assert!(matches!(result, Err(MsmError::DeviceLost(_))));
+    }
+
+    #[test]
+    fn test_msm_device_lost_fallback() {
+        let points = sample_points::<G1Affine>(4);
+        let scalars = sample_scalars::<Fr>(4);
+
+        let mut context = pollster::block_on(MsmContext::new()).unwrap();
+        context.device().destroy();
+        context.device().poll(wgpu::Maintain::Wait);
+        let (result, backend) =
+            pollster::block_on(run_webgpu_msm_with_context(&mut context, &points, &scalars))
+                .unwrap();
+        assert_eq!(backend, MsmBackend::Cpu);
+        assert_eq!(cpu_msm(&points, &scalars), result);
+    }
+}
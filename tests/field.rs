@@ -1,6 +1,5 @@
 use std::time::Instant;
 
-use ff::PrimeField;
 use wgpu::CommandEncoderDescriptor;
 
 use msm_webgpu::cuzk::{
@@ -9,32 +8,40 @@ use msm_webgpu::cuzk::{
         create_compute_pipeline, create_storage_buffer, execute_pipeline, get_adapter, get_device,
         read_from_gpu_test,
     },
-    msm::{PARAMS, WORD_SIZE},
+    msm::WORD_SIZE,
     shader_manager::ShaderManager,
-    utils::{bytes_to_field, field_to_u8_vec_for_gpu, to_biguint_le},
+    utils::{to_biguint_le, to_words_le_from_le_bytes},
 };
+use msm_webgpu::{GpuCurve, MsmPlan};
 
-async fn field_op<F: PrimeField>(op: &str, a: F, b: F) -> F {
-    let a_bytes = field_to_u8_vec_for_gpu(&a, PARAMS.num_words, WORD_SIZE);
-    let b_bytes = field_to_u8_vec_for_gpu(&b, PARAMS.num_words, WORD_SIZE);
+/// Split a base field element of `C` into `WORD_SIZE`-bit limbs for the GPU.
+fn base_to_u8_vec_for_gpu<C: GpuCurve>(value: &C::Base) -> Vec<u8> {
+    let limbs = to_words_le_from_le_bytes(&C::base_to_bytes(value), C::num_words(), WORD_SIZE);
+    bytemuck::cast_slice::<u32, u8>(&limbs).to_vec()
+}
+
+async fn field_op<C: GpuCurve>(op: &str, a: C::Base, b: C::Base) -> C::Base {
+    let a_bytes = base_to_u8_vec_for_gpu::<C>(&a);
+    let b_bytes = base_to_u8_vec_for_gpu::<C>(&b);
     let input_size = 1;
-    let chunk_size = if input_size >= 65536 { 16 } else { 4 };
-    let num_words = PARAMS.num_words;
+    let chunk_size = MsmPlan::for_input_size::<C>(input_size).unwrap().chunk_size;
+    let params = C::misc_params();
+    let num_words = params.num_words;
     println!("Input size: {input_size}");
     println!("Chunk size: {chunk_size}");
     println!("Num words: {num_words}");
     println!("Word size: {WORD_SIZE}");
-    println!("Params: {PARAMS:?}");
+    println!("Params: {params:?}");
 
-    let shader_manager = ShaderManager::new(WORD_SIZE, chunk_size, input_size);
+    let shader_manager = ShaderManager::for_curve::<C>(WORD_SIZE, chunk_size, input_size);
 
-    let adapter = get_adapter().await;
-    let (device, queue) = get_device(&adapter).await;
+    let adapter = get_adapter().await.unwrap();
+    let (device, queue) = get_device(&adapter).await.unwrap();
     let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
         label: Some("Field Encoder"),
     });
 
-    let shader_code = shader_manager.gen_test_field_shader();
+    let shader_code = shader_manager.gen_test_field_shader().unwrap();
 
     let a_sb = create_and_write_storage_buffer(Some("A buffer"), &device, &a_bytes);
     let b_sb = create_and_write_storage_buffer(Some("B buffer"), &device, &b_bytes);
@@ -63,7 +70,8 @@ async fn field_op<F: PrimeField>(op: &str, a: F, b: F) -> F {
         &shader_code,
         op,
     )
-    .await;
+    .await
+    .unwrap();
 
     execute_pipeline(&mut encoder, compute_pipeline, bind_group, 1, 1, 1).await;
 
@@ -77,20 +85,18 @@ async fn field_op<F: PrimeField>(op: &str, a: F, b: F) -> F {
 
     let result_biguint = to_biguint_le(data_u32, num_words, WORD_SIZE as u32);
 
-    
-
-    bytes_to_field(&result_biguint.to_bytes_le())
+    C::base_from_bytes(&result_biguint.to_bytes_le())
 }
 
 /// Run WebGPU field op sync
-pub fn run_webgpu_field_op<F: PrimeField>(op: &str, a: F, b: F) -> F {
-    pollster::block_on(run_webgpu_field_op_async(op, a, b))
+pub fn run_webgpu_field_op<C: GpuCurve>(op: &str, a: C::Base, b: C::Base) -> C::Base {
+    pollster::block_on(run_webgpu_field_op_async::<C>(op, a, b))
 }
 
 /// Run WebGPU field op async
-pub async fn run_webgpu_field_op_async<F: PrimeField>(op: &str, a: F, b: F) -> F {
+pub async fn run_webgpu_field_op_async<C: GpuCurve>(op: &str, a: C::Base, b: C::Base) -> C::Base {
     let now = Instant::now();
-    let result = field_op::<F>(op, a, b).await;
+    let result = field_op::<C>(op, a, b).await;
     println!("Field add time: {:?}", now.elapsed());
     result
 }
@@ -98,13 +104,21 @@ pub async fn run_webgpu_field_op_async<F: PrimeField>(op: &str, a: F, b: F) -> F
 #[cfg(test)]
 mod tests {
     use msm_webgpu::{
-        cuzk::{msm::calc_num_words, utils::u8s_to_field_without_assertion},
+        cuzk::{
+            msm::calc_num_words,
+            utils::{field_to_u8_vec_for_gpu, u8s_to_field_without_assertion},
+        },
         sample_scalars,
     };
 
     use super::*;
-    use ff::Field;
-    use halo2curves::bn256::Fq;
+    use ff::{Field, PrimeField};
+    use halo2curves::bls12381;
+    use halo2curves::bn256::{Fq, G1Affine};
+    use halo2curves::pasta::{self, PallasAffine, VestaAffine};
+    use halo2curves::{secp256k1, secp256r1};
+    use msm_webgpu::cuzk::msm::P;
+    use num_bigint::BigUint;
     use rand::thread_rng;
 
     #[test]
@@ -116,7 +130,7 @@ mod tests {
 
             let fast = a + b;
 
-            let result = run_webgpu_field_op::<Fq>("test_field_add", a, b);
+            let result = run_webgpu_field_op::<G1Affine>("test_field_add", a, b);
 
             println!("Result: {:?}", result);
             assert_eq!(fast, result);
@@ -132,7 +146,7 @@ mod tests {
 
             let fast = a - b;
 
-            let result = run_webgpu_field_op::<Fq>("test_field_sub", a, b);
+            let result = run_webgpu_field_op::<G1Affine>("test_field_sub", a, b);
 
             println!("Result: {:?}", result);
             assert_eq!(fast, result);
@@ -146,7 +160,7 @@ mod tests {
         let b = Fq::random(&mut rng);
 
         let fast = a * b;
-        let result = run_webgpu_field_op::<Fq>("test_field_mul", a, b);
+        let result = run_webgpu_field_op::<G1Affine>("test_field_mul", a, b);
 
         println!("Result: {:?}", result);
         assert_eq!(fast, result);
@@ -159,12 +173,77 @@ mod tests {
         let b = Fq::random(&mut rng);
 
         let fast = a;
-        let result = run_webgpu_field_op::<Fq>("test_barret_mul", a, b);
+        let result = run_webgpu_field_op::<G1Affine>("test_barret_mul", a, b);
 
         println!("Result: {:?}", result);
         assert_eq!(fast, result);
     }
 
+    #[test]
+    fn test_webgpu_field_inv() {
+        let mut rng = thread_rng();
+        let a = Fq::random(&mut rng);
+
+        let fast = a.invert().unwrap();
+        let result = run_webgpu_field_op::<G1Affine>("test_field_inv", a, Fq::ZERO);
+        assert_eq!(fast, result);
+
+        // Zero has no inverse, and maps to zero
+        let result = run_webgpu_field_op::<G1Affine>("test_field_inv", Fq::ZERO, Fq::ZERO);
+        assert_eq!(Fq::ZERO, result);
+    }
+
+    #[test]
+    fn test_webgpu_field_pow() {
+        let mut rng = thread_rng();
+        let a = Fq::random(&mut rng);
+        let e = Fq::random(&mut rng);
+
+        // The shader reads the exponent as an integer
+        let a_big = BigUint::from_bytes_le(&G1Affine::base_to_bytes(&a));
+        let e_big = BigUint::from_bytes_le(&G1Affine::base_to_bytes(&e));
+        let fast = G1Affine::base_from_bytes(&a_big.modpow(&e_big, &P).to_bytes_le());
+        let result = run_webgpu_field_op::<G1Affine>("test_field_pow", a, e);
+        assert_eq!(fast, result);
+    }
+
+    #[test]
+    fn test_webgpu_field_legendre() {
+        let mut rng = thread_rng();
+        let square = Fq::random(&mut rng).square();
+
+        let result = run_webgpu_field_op::<G1Affine>("test_field_legendre", square, Fq::ZERO);
+        assert_eq!(Fq::ONE, result);
+
+        // The multiplicative generator is not a square
+        let non_square = square * Fq::MULTIPLICATIVE_GENERATOR;
+        let result = run_webgpu_field_op::<G1Affine>("test_field_legendre", non_square, Fq::ZERO);
+        assert_eq!(-Fq::ONE, result);
+    }
+
+    /// Check the square root of a random square and of a non-square of `C`.
+    fn check_webgpu_field_sqrt<C: GpuCurve>() {
+        let square = C::Base::random(thread_rng()).square();
+        let root = run_webgpu_field_op::<C>("test_field_sqrt", square, C::Base::ZERO);
+        assert_eq!(square, root.square());
+
+        let non_square = square * C::Base::MULTIPLICATIVE_GENERATOR;
+        let result = run_webgpu_field_op::<C>("test_field_sqrt", non_square, C::Base::ZERO);
+        assert_eq!(C::Base::ZERO, result);
+    }
+
+    #[test]
+    fn test_webgpu_field_sqrt() {
+        check_webgpu_field_sqrt::<G1Affine>();
+    }
+
+    #[test]
+    fn test_webgpu_field_sqrt_pallas() {
+        // p - 1 has a two-adicity of 32, so Tonelli-Shanks takes more than
+        // one iteration
+        check_webgpu_field_sqrt::<PallasAffine>();
+    }
+
     #[test]
     fn test_field_to_u8_vec_for_gpu() {
         // random
@@ -177,4 +256,107 @@ mod tests {
             assert_eq!(a, a_from_bytes);
         }
     }
+
+    #[test]
+    fn test_webgpu_field_add_bls12_381() {
+        let scalars = sample_scalars::<bls12381::Fq>(50);
+        for scalar in scalars.chunks(2) {
+            let a = scalar[0];
+            let b = scalar[1];
+
+            let fast = a + b;
+            let result = run_webgpu_field_op::<bls12381::G1Affine>("test_field_add", a, b);
+            assert_eq!(fast, result);
+        }
+    }
+
+    #[test]
+    fn test_webgpu_field_sub_bls12_381() {
+        let scalars = sample_scalars::<bls12381::Fq>(50);
+        for scalar in scalars.chunks(2) {
+            let a = scalar[0];
+            let b = scalar[1];
+
+            let fast = a - b;
+            let result = run_webgpu_field_op::<bls12381::G1Affine>("test_field_sub", a, b);
+            assert_eq!(fast, result);
+        }
+    }
+
+    #[test]
+    fn test_webgpu_field_mul_bls12_381() {
+        let mut rng = thread_rng();
+        let a = bls12381::Fq::random(&mut rng);
+        let b = bls12381::Fq::random(&mut rng);
+
+        let fast = a * b;
+        let result = run_webgpu_field_op::<bls12381::G1Affine>("test_field_mul", a, b);
+        assert_eq!(fast, result);
+    }
+
+    #[test]
+    fn test_webgpu_field_barret_mul_bls12_381() {
+        let mut rng = thread_rng();
+        let a = bls12381::Fq::random(&mut rng);
+        let b = bls12381::Fq::random(&mut rng);
+
+        let fast = a;
+        let result = run_webgpu_field_op::<bls12381::G1Affine>("test_barret_mul", a, b);
+        assert_eq!(fast, result);
+    }
+
+    #[test]
+    fn test_webgpu_field_inv_bls12_381() {
+        let mut rng = thread_rng();
+        let a = bls12381::Fq::random(&mut rng);
+
+        let fast = a.invert().unwrap();
+        let result =
+            run_webgpu_field_op::<bls12381::G1Affine>("test_field_inv", a, bls12381::Fq::ZERO);
+        assert_eq!(fast, result);
+    }
+
+    #[test]
+    fn test_webgpu_field_mul_pallas() {
+        let mut rng = thread_rng();
+        let a = pasta::Fp::random(&mut rng);
+        let b = pasta::Fp::random(&mut rng);
+
+        let fast = a * b;
+        let result = run_webgpu_field_op::<PallasAffine>("test_field_mul", a, b);
+        assert_eq!(fast, result);
+    }
+
+    #[test]
+    fn test_webgpu_field_mul_vesta() {
+        let mut rng = thread_rng();
+        let a = pasta::Fq::random(&mut rng);
+        let b = pasta::Fq::random(&mut rng);
+
+        let fast = a * b;
+        let result = run_webgpu_field_op::<VestaAffine>("test_field_mul", a, b);
+        assert_eq!(fast, result);
+    }
+
+    #[test]
+    fn test_webgpu_field_mul_secp256k1() {
+        let mut rng = thread_rng();
+        let a = secp256k1::Fp::random(&mut rng);
+        let b = secp256k1::Fp::random(&mut rng);
+
+        let fast = a * b;
+        let result = run_webgpu_field_op::<secp256k1::Secp256k1Affine>("test_field_mul", a, b);
+        assert_eq!(fast, result);
+    }
+
+    #[test]
+    fn test_webgpu_field_mul_secp256r1() {
+        let mut rng = thread_rng();
+        let a = secp256r1::Fp::random(&mut rng);
+        let b = secp256r1::Fp::random(&mut rng);
+
+        let fast = a * b;
+        let result = run_webgpu_field_op::<secp256r1::Secp256r1Affine>("test_field_mul", a, b);
+        assert_eq!(fast, result);
+    }
 }
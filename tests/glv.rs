@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use ff::{Field, PrimeField, WithSmallOrderMulGroup};
+    use group::prime::PrimeCurveAffine;
+    use group::Group;
+    use halo2curves::bn256::{Fr, G1Affine, G1};
+    use halo2curves::secp256k1::Secp256k1Affine;
+    use msm_webgpu::cuzk::test::utils::{cpu_glv_window_sum, decompose_scalars_glv, glv_points};
+    use msm_webgpu::{cpu_msm, sample_points, sample_scalars};
+    use msm_webgpu::{CostModel, GpuCurve, MsmConfig, MsmError, MsmPlan};
+    use num_bigint::BigUint;
+    use rand::thread_rng;
+    use wgpu::Limits;
+
+    fn to_fr(magnitude: &BigUint, negative: bool) -> Fr {
+        let value = Fr::from_str_vartime(&magnitude.to_string()).unwrap();
+        if negative {
+            -value
+        } else {
+            value
+        }
+    }
+
+    #[test]
+    fn test_glv_bn254_constants() {
+        let glv = G1Affine::glv().unwrap();
+        assert_eq!(to_fr(&glv.lambda, false), Fr::ZETA);
+        // Both basis vectors are in the lattice, i.e. a + b * lambda = 0.
+        for (a, b) in [&glv.v1, &glv.v2] {
+            let a = to_fr(a.magnitude(), a.sign() == num_bigint::Sign::Minus);
+            let b = to_fr(b.magnitude(), b.sign() == num_bigint::Sign::Minus);
+            assert_eq!(a + b * Fr::ZETA, Fr::ZERO);
+        }
+        assert_eq!(glv.shift, 256);
+        assert_eq!(glv.half_bits, 128);
+    }
+
+    #[test]
+    fn test_glv_decompose() {
+        let glv = G1Affine::glv().unwrap();
+        let lambda = to_fr(&glv.lambda, false);
+        let mut scalars = vec![Fr::ZERO, Fr::ONE, -Fr::ONE, lambda, -lambda];
+        scalars.extend((0..10000).map(|_| Fr::random(thread_rng())));
+        for k in scalars {
+            let [(k1, k1_negative), (k2, k2_negative)] =
+                glv.decompose(&BigUint::from_bytes_le(k.to_repr().as_ref()));
+            assert!(k1.bits() as usize <= glv.half_bits);
+            assert!(k2.bits() as usize <= glv.half_bits);
+            assert_eq!(
+                to_fr(&k1, k1_negative) + to_fr(&k2, k2_negative) * lambda,
+                k
+            );
+        }
+    }
+
+    #[test]
+    fn test_glv_points() {
+        let mut points = sample_points::<G1Affine>(16);
+        points.push(G1Affine::identity());
+        let images = glv_points(&points);
+        assert_eq!(images.len(), 2 * points.len());
+        assert_eq!(&images[..points.len()], &points[..]);
+        for (point, image) in points.iter().zip(&images[points.len()..]) {
+            assert_eq!(G1::from(*image), *point * Fr::ZETA);
+        }
+    }
+
+    #[test]
+    fn test_glv_window_sums() {
+        let input_size = 64;
+        let points = sample_points::<G1Affine>(input_size);
+        let mut scalars = sample_scalars::<Fr>(input_size);
+        scalars[0] = -Fr::ONE;
+        scalars[1] = Fr::ZERO;
+        let expected = cpu_msm(&points, &scalars);
+
+        for chunk_size in [4, 13, 16] {
+            let plan = MsmPlan::new_glv::<G1Affine>(input_size, chunk_size).unwrap();
+            let digits = decompose_scalars_glv::<G1Affine>(&scalars, plan.num_subtasks, chunk_size);
+            assert_eq!(digits.len(), plan.num_subtasks);
+            let s = 1 << (chunk_size - 1);
+            for row in &digits {
+                assert_eq!(row.len(), plan.num_terms());
+                assert!(row.iter().all(|digit| (0..2 * s).contains(digit)));
+            }
+
+            let mut result = G1::identity();
+            for window in (0..plan.num_subtasks).rev() {
+                for _ in 0..chunk_size {
+                    result = result.double();
+                }
+                result +=
+                    cpu_glv_window_sum(&points, &scalars, window, plan.num_subtasks, chunk_size);
+            }
+            assert_eq!(result, expected, "chunk size {chunk_size}");
+        }
+    }
+
+    #[test]
+    fn test_glv_plan() {
+        let plan = MsmPlan::new_glv::<G1Affine>(1 << 16, 16).unwrap();
+        assert!(plan.glv);
+        assert_eq!(plan.num_terms(), 1 << 17);
+        // 128-bit halves and two bits of headroom
+        assert_eq!(plan.num_subtasks, 9);
+        assert_eq!(plan.last_chunk_size, 2);
+        assert!(plan.num_subtasks < MsmPlan::new::<G1Affine>(1 << 16, 16).num_subtasks);
+
+        assert!(MsmPlan::new_glv::<Secp256k1Affine>(1 << 16, 16).is_none());
+        let result = MsmPlan::select_glv::<Secp256k1Affine>(
+            1 << 16,
+            &Limits::default(),
+            &CostModel::default(),
+        );
+        assert!(matches!(result, Err(MsmError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_glv_config() {
+        let limits = Limits::default();
+        let cost_model = CostModel::default();
+        let config = MsmConfig {
+            glv: true,
+            ..Default::default()
+        };
+        let plan = config
+            .plan::<G1Affine>(1 << 16, &limits, &cost_model)
+            .unwrap();
+        assert!(plan.glv);
+        assert_eq!(
+            plan,
+            MsmPlan::select_glv::<G1Affine>(1 << 16, &limits, &cost_model).unwrap()
+        );
+        assert!(config.workgroups(&plan, &limits).unwrap().glv);
+
+        let config = MsmConfig {
+            glv: true,
+            chunk_size: Some(12),
+            ..Default::default()
+        };
+        let plan = config
+            .plan::<G1Affine>(1 << 16, &limits, &cost_model)
+            .unwrap();
+        assert_eq!(plan, MsmPlan::new_glv::<G1Affine>(1 << 16, 12).unwrap());
+
+        let config = MsmConfig {
+            glv: true,
+            ..Default::default()
+        };
+        let result = config.plan::<Secp256k1Affine>(1 << 16, &limits, &cost_model);
+        assert!(matches!(result, Err(MsmError::InvalidConfig(_))));
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::G1Affine;
+    use msm_webgpu::cuzk::msm::PARAMS;
+    use msm_webgpu::cuzk::partition::{
+        max_buffer_size_for_input, max_glv_partition_size, max_partition_size, partition,
+    };
+    use msm_webgpu::{MsmConfig, MsmError, MsmPlan};
+    use wgpu::Limits;
+
+    fn limits(max_storage_buffer_binding_size: u32, max_buffer_size: u64) -> Limits {
+        Limits {
+            max_storage_buffer_binding_size,
+            max_buffer_size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_max_buffer_size_for_input() {
+        let num_words = PARAMS.num_words;
+        // 64 subtasks of 4 bits: the scalar chunks are the largest buffer.
+        assert_eq!(
+            max_buffer_size_for_input::<G1Affine>(1 << 10, 4),
+            (1 << 10) * 256
+        );
+        // 16 subtasks of 16 bits: a Montgomery coordinate is the largest buffer.
+        assert_eq!(
+            max_buffer_size_for_input::<G1Affine>(1 << 20, 16),
+            (1 << 20) * num_words as u64 * 4
+        );
+    }
+
+    #[test]
+    fn test_max_partition_size() {
+        // The WebGPU defaults of 128 MiB bindings and 256 MiB buffers.
+        let size = max_partition_size::<G1Affine>(&Limits::default(), None);
+        assert_eq!(size, 1 << 20);
+
+        // The binding size is the tighter limit.
+        let size = max_partition_size::<G1Affine>(&limits(1 << 30, 1 << 28), None);
+        assert_eq!(size, 1 << 21);
+
+        let size = max_partition_size::<G1Affine>(&limits(u32::MAX, u64::MAX), None);
+        assert_eq!(size, 1 << 25);
+
+        for log_limit in 20..32 {
+            let limit = 1u64 << log_limit;
+            let limits = limits(limit as u32, limit);
+            let size = max_partition_size::<G1Affine>(&limits, None);
+            assert!(size.is_power_of_two());
+            assert!(MsmPlan::select::<G1Affine>(size, &limits, &Default::default()).is_ok());
+            assert!(MsmPlan::select::<G1Affine>(size * 2, &limits, &Default::default()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_max_partition_size_with_chunk_size() {
+        let limits = limits(1 << 24, 1 << 24);
+        let config = MsmConfig {
+            chunk_size: Some(4),
+            ..Default::default()
+        };
+
+        // 4-bit windows need 64 scalar chunks per input, so fewer inputs fit
+        // than with the window size the cost model is free to pick.
+        let size = max_partition_size::<G1Affine>(&limits, config.chunk_size);
+        assert_eq!(size, 1 << 16);
+        assert!(size < max_partition_size::<G1Affine>(&limits, None));
+        assert!(config
+            .plan::<G1Affine>(size, &limits, &Default::default())
+            .is_ok());
+        assert!(matches!(
+            config.plan::<G1Affine>(size * 2, &limits, &Default::default()),
+            Err(MsmError::LimitsExceeded { .. })
+        ));
+
+        let glv_config = MsmConfig {
+            glv: true,
+            ..config
+        };
+        let size = max_glv_partition_size::<G1Affine>(&limits, config.chunk_size).unwrap();
+        assert!(glv_config
+            .plan::<G1Affine>(size, &limits, &Default::default())
+            .is_ok());
+        assert!(glv_config
+            .plan::<G1Affine>(size * 2, &limits, &Default::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_partition() {
+        assert_eq!(partition(0, 4), vec![]);
+        assert_eq!(partition(3, 4), vec![0..3]);
+        assert_eq!(partition(4, 4), vec![0..4]);
+        assert_eq!(partition(10, 4), vec![0..4, 4..8, 8..10]);
+
+        let len = (1 << 24) + 5;
+        let ranges = partition(len, 1 << 20);
+        assert_eq!(ranges.len(), 17);
+        assert!(ranges[..16].iter().all(|r| r.len() == 1 << 20));
+        assert_eq!(ranges[16], (1 << 24)..len);
+        assert_eq!(ranges.iter().map(|r| r.len()).sum::<usize>(), len);
+    }
+}
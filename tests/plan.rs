@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::G1Affine;
+    use halo2curves::secp256k1::Secp256k1Affine;
+    use msm_webgpu::cuzk::plan::{MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+    use msm_webgpu::{CostModel, GpuCurve, MsmError, MsmPlan};
+    use wgpu::Limits;
+
+    #[test]
+    fn test_plan_windows() {
+        // 254-bit scalars and two bits of headroom split evenly into 16-bit
+        // windows.
+        let plan = MsmPlan::new::<G1Affine>(1 << 16, 16);
+        assert_eq!(plan.num_subtasks, 16);
+        assert_eq!(plan.last_chunk_size, 16);
+        assert_eq!(plan.num_columns(), 1 << 16);
+        assert_eq!(plan.num_buckets(), 1 << 15);
+
+        // 13 does not divide 256, so the last window is short.
+        let plan = MsmPlan::new::<G1Affine>(1 << 16, 13);
+        assert_eq!(plan.num_subtasks, 20);
+        assert_eq!(plan.last_chunk_size, 9);
+
+        let plan = MsmPlan::new::<Secp256k1Affine>(1 << 16, 16);
+        assert_eq!(plan.num_subtasks, 17);
+        assert_eq!(plan.last_chunk_size, 2);
+
+        for chunk_size in MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE {
+            let plan = MsmPlan::new::<G1Affine>(1 << 10, chunk_size);
+            assert!(plan.last_chunk_size > 0 && plan.last_chunk_size <= chunk_size);
+            assert_eq!(
+                (plan.num_subtasks - 1) * chunk_size + plan.last_chunk_size,
+                G1Affine::scalar_bits() + 2
+            );
+        }
+    }
+
+    #[test]
+    fn test_plan_default_cost_model() {
+        let chunk_size = |log_size: usize| {
+            MsmPlan::for_input_size::<G1Affine>(1 << log_size)
+                .unwrap()
+                .chunk_size
+        };
+        assert_eq!(chunk_size(0), 2);
+        assert_eq!(chunk_size(8), 6);
+        assert_eq!(chunk_size(10), 8);
+        assert_eq!(chunk_size(16), 13);
+        assert_eq!(chunk_size(18), 16);
+        assert_eq!(chunk_size(20), 16);
+    }
+
+    #[test]
+    fn test_plan_cost_model() {
+        let limits = Limits::default();
+        let cost_model = CostModel::default();
+        let plan = MsmPlan::select::<G1Affine>(1 << 16, &limits, &cost_model).unwrap();
+        for chunk_size in MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE {
+            let other = MsmPlan::new::<G1Affine>(1 << 16, chunk_size);
+            assert!(cost_model.cost(&plan) <= cost_model.cost(&other));
+        }
+
+        // An expensive window favours fewer, wider windows.
+        let cost_model = CostModel {
+            window: 1e9,
+            ..Default::default()
+        };
+        let plan = MsmPlan::select::<G1Affine>(1 << 10, &limits, &cost_model).unwrap();
+        assert_eq!(plan.chunk_size, MAX_CHUNK_SIZE);
+
+        // Free bucket additions favour the narrowest window.
+        let cost_model = CostModel {
+            bucket_add: 0.0,
+            ..Default::default()
+        };
+        let plan = MsmPlan::select::<G1Affine>(1 << 16, &limits, &cost_model).unwrap();
+        assert_eq!(plan.chunk_size, MIN_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_plan_limits() {
+        // The 16-bit window's bucket sums no longer fit, so the widest window
+        // that does is picked instead.
+        let limits = Limits {
+            max_storage_buffer_binding_size: 1 << 25,
+            ..Default::default()
+        };
+        let plan = MsmPlan::select::<G1Affine>(1 << 18, &limits, &CostModel::default()).unwrap();
+        assert!(plan.fits(&limits));
+        assert!(!MsmPlan::new::<G1Affine>(1 << 18, 16).fits(&limits));
+        assert!(plan.chunk_size < 16);
+
+        let limits = Limits {
+            max_storage_buffer_binding_size: 1 << 10,
+            ..Default::default()
+        };
+        let result = MsmPlan::select::<G1Affine>(1 << 18, &limits, &CostModel::default());
+        assert!(matches!(
+            result,
+            Err(MsmError::LimitsExceeded { limit: 1024, .. })
+        ));
+    }
+}
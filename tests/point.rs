@@ -1,6 +1,7 @@
 use std::time::Instant;
 
-use halo2curves::{CurveAffine, CurveExt};
+use ff::Field;
+use num_bigint::BigUint;
 use wgpu::CommandEncoderDescriptor;
 
 use msm_webgpu::cuzk::{
@@ -9,38 +10,65 @@ use msm_webgpu::cuzk::{
         create_bind_group_layout, create_compute_pipeline, create_storage_buffer, execute_pipeline,
         get_adapter, get_device, read_from_gpu_test,
     },
-    msm::{P, PARAMS, WORD_SIZE},
+    msm::WORD_SIZE,
     shader_manager::ShaderManager,
-    utils::{bytes_to_field, points_to_bytes_for_gpu, to_biguint_le},
+    utils::{compute_misc_params, to_biguint_le, to_words_le_from_le_bytes},
 };
+use msm_webgpu::{GpuCurve, MsmPlan};
+
+/// Split the little-endian bytes of the projective coordinates of a point,
+/// `component_bytes` per base field component, into `WORD_SIZE`-bit limbs for
+/// the GPU.
+fn coords_to_u8_vec_for_gpu(
+    coords: &[Vec<u8>],
+    component_bytes: usize,
+    num_words: usize,
+) -> Vec<u8> {
+    let limbs = coords
+        .iter()
+        .flat_map(|coord| coord.chunks(component_bytes))
+        .flat_map(|component| to_words_le_from_le_bytes(component, num_words, WORD_SIZE))
+        .collect::<Vec<_>>();
+    bytemuck::cast_slice::<u32, u8>(&limbs).to_vec()
+}
 
-async fn point_op<C: CurveAffine>(op: &str, a: C, b: C, scalar: u32) -> C::Curve {
-    let a_bytes = points_to_bytes_for_gpu(&[a], PARAMS.num_words, WORD_SIZE);
-    let b_bytes = points_to_bytes_for_gpu(&[b], PARAMS.num_words, WORD_SIZE);
+/// Run a point op of the test point shader generated by `shader_manager` over
+/// the field with modulus `p`, with `a` and `b` given as by
+/// `coords_to_u8_vec_for_gpu`. Returns the base field components of the
+/// `num_components` coordinate components of the result, out of Montgomery
+/// form.
+async fn run_point_shader(
+    shader_manager: &ShaderManager,
+    p: &BigUint,
+    op: &str,
+    a_bytes: &[u8],
+    b_bytes: &[u8],
+    scalar: u32,
+    num_components: usize,
+) -> Vec<BigUint> {
+    let params = compute_misc_params(p, WORD_SIZE);
+    let num_words = params.num_words;
     let scalar_bytes = scalar.to_le_bytes();
-    let input_size = 1;
-    let chunk_size = if input_size >= 65536 { 16 } else { 4 };
-    let num_words = PARAMS.num_words;
-    println!("Input size: {input_size}");
-    println!("Chunk size: {chunk_size}");
     println!("Num words: {num_words}");
     println!("Word size: {WORD_SIZE}");
-    println!("Params: {PARAMS:?}");
+    println!("Params: {params:?}");
 
-    let shader_manager = ShaderManager::new(WORD_SIZE, chunk_size, input_size);
-
-    let adapter = get_adapter().await;
-    let (device, queue) = get_device(&adapter).await;
+    let adapter = get_adapter().await.unwrap();
+    let (device, queue) = get_device(&adapter).await.unwrap();
     let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
         label: Some("Point Encoder"),
     });
 
-    let shader_code = shader_manager.gen_test_point_shader();
+    let shader_code = shader_manager.gen_test_point_shader().unwrap();
 
-    let a_sb = create_and_write_storage_buffer(Some("A buffer"), &device, &a_bytes);
-    let b_sb = create_and_write_storage_buffer(Some("B buffer"), &device, &b_bytes);
+    let a_sb = create_and_write_storage_buffer(Some("A buffer"), &device, a_bytes);
+    let b_sb = create_and_write_storage_buffer(Some("B buffer"), &device, b_bytes);
 
-    let result_sb = create_storage_buffer(Some("Result buffer"), &device, 240);
+    let result_sb = create_storage_buffer(
+        Some("Result buffer"),
+        &device,
+        (num_components * num_words * 4) as u64,
+    );
 
     let scalar_sb =
         create_and_write_uniform_buffer(Some("Scalar buffer"), &device, &queue, &scalar_bytes);
@@ -66,7 +94,8 @@ async fn point_op<C: CurveAffine>(op: &str, a: C, b: C, scalar: u32) -> C::Curve
         &shader_code,
         op,
     )
-    .await;
+    .await
+    .unwrap();
 
     execute_pipeline(&mut encoder, compute_pipeline, bind_group, 1, 1, 1).await;
 
@@ -80,34 +109,66 @@ async fn point_op<C: CurveAffine>(op: &str, a: C, b: C, scalar: u32) -> C::Curve
     println!("Data u32: {data_u32:?}");
     println!("Data length: {:?}", data_u32.len());
 
-    let results = data_u32
-        .chunks(20)
+    data_u32
+        .chunks(num_words)
         .map(|chunk| {
             let biguint_montgomery = to_biguint_le(chunk, num_words, WORD_SIZE as u32);
-            let biguint = biguint_montgomery * &PARAMS.rinv % P.clone();
-            let field: <<C as CurveAffine>::CurveExt as CurveExt>::Base =
-                bytes_to_field(&biguint.to_bytes_le());
-            field
+            biguint_montgomery * &params.rinv % p
+        })
+        .collect()
+}
+
+async fn point_op<C: GpuCurve>(op: &str, a: C, b: C, scalar: u32) -> C::Curve {
+    let input_size = 1;
+    let chunk_size = MsmPlan::for_input_size::<C>(input_size).unwrap().chunk_size;
+    println!("Input size: {input_size}");
+    println!("Chunk size: {chunk_size}");
+    let shader_manager = ShaderManager::for_curve::<C>(WORD_SIZE, chunk_size, input_size);
+
+    let component_bytes = C::COORD_BYTES / C::EXT_DEGREE;
+    let to_bytes = |point: C| {
+        let coords = point.coordinates().unwrap();
+        let coords = [coords.x(), coords.y(), &C::Base::ONE].map(C::base_to_bytes);
+        coords_to_u8_vec_for_gpu(&coords, component_bytes, C::num_words())
+    };
+    let components = run_point_shader(
+        &shader_manager,
+        &C::base_modulus(),
+        op,
+        &to_bytes(a),
+        &to_bytes(b),
+        scalar,
+        3 * C::EXT_DEGREE,
+    )
+    .await;
+
+    let results = components
+        .chunks(C::EXT_DEGREE)
+        .map(|coord| {
+            let bytes = coord
+                .iter()
+                .flat_map(|component| {
+                    let mut bytes = component.to_bytes_le();
+                    bytes.resize(component_bytes, 0);
+                    bytes
+                })
+                .collect::<Vec<_>>();
+            C::base_from_bytes(&bytes)
         })
         .collect::<Vec<_>>();
 
     println!("Results: {results:?}");
 
-    C::Curve::new_jacobian(results[0], results[1], results[2]).unwrap()
+    C::from_gpu_coords(&results).unwrap()
 }
 
 /// Run WebGPU point op sync
-pub fn run_webgpu_point_op<C: CurveAffine>(op: &str, a: C, b: C, scalar: u32) -> C::Curve {
+pub fn run_webgpu_point_op<C: GpuCurve>(op: &str, a: C, b: C, scalar: u32) -> C::Curve {
     pollster::block_on(run_webgpu_point_op_async(op, a, b, scalar))
 }
 
 /// Run WebGPU point op async
-pub async fn run_webgpu_point_op_async<C: CurveAffine>(
-    op: &str,
-    a: C,
-    b: C,
-    scalar: u32,
-) -> C::Curve {
+pub async fn run_webgpu_point_op_async<C: GpuCurve>(op: &str, a: C, b: C, scalar: u32) -> C::Curve {
     let now = Instant::now();
     let result = point_op::<C>(op, a, b, scalar).await;
     println!("Point op time: {:?}", now.elapsed());
@@ -117,10 +178,10 @@ pub async fn run_webgpu_point_op_async<C: CurveAffine>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use group::Curve;
     use group::cofactor::CofactorCurveAffine;
-    use halo2curves::bn256::{Fr, G1Affine};
-    use rand::{Rng, thread_rng};
+    use group::{Curve, Group};
+    use halo2curves::bn256::{Fr, G1Affine, G2Affine, G1, G2};
+    use rand::{thread_rng, Rng};
 
     #[test]
     fn test_webgpu_point_add() {
@@ -184,4 +245,86 @@ mod tests {
         println!("Result: {:?}", result);
         assert_eq!(fast, result);
     }
+
+    #[test]
+    fn test_webgpu_point_add_mixed() {
+        let mut rng = thread_rng();
+        let a = G1Affine::random(&mut rng);
+        let b = G1Affine::random(&mut rng);
+
+        let fast = a + a + b;
+        let result = run_webgpu_point_op::<G1Affine>("test_point_add_mixed", a, b, 0);
+        assert_eq!(fast, result);
+    }
+
+    #[test]
+    fn test_webgpu_point_add_mixed_double() {
+        let mut rng = thread_rng();
+        let a = G1Affine::random(&mut rng);
+        let a_2 = (a + a).to_affine();
+
+        // Adding 2a to itself takes the doubling branch
+        let fast = a_2 + a_2;
+        let result = run_webgpu_point_op::<G1Affine>("test_point_add_mixed", a, a_2, 0);
+        assert_eq!(fast, result);
+
+        // and adding -2a the identity branch
+        let result = run_webgpu_point_op::<G1Affine>("test_point_add_mixed", a, -a_2, 0);
+        assert_eq!(G1::identity(), result);
+    }
+
+    #[test]
+    fn test_webgpu_point_add_mixed_identity() {
+        let mut rng = thread_rng();
+        let a = G1Affine::random(&mut rng);
+
+        let result = run_webgpu_point_op::<G1Affine>("test_point_add_mixed_identity", a, a, 0);
+        assert_eq!(a, result.to_affine());
+    }
+
+    #[test]
+    fn test_webgpu_g2_point_add() {
+        let mut rng = thread_rng();
+        let a = G2Affine::random(&mut rng);
+        let b = G2Affine::random(&mut rng);
+
+        let result = run_webgpu_point_op::<G2Affine>("test_point_add", a, b, 0);
+        assert_eq!(a + b, result);
+    }
+
+    #[test]
+    fn test_webgpu_g2_point_add_mixed() {
+        let mut rng = thread_rng();
+        let a = G2Affine::random(&mut rng);
+        let b = G2Affine::random(&mut rng);
+
+        let fast = a + a + b;
+        let result = run_webgpu_point_op::<G2Affine>("test_point_add_mixed", a, b, 0);
+        assert_eq!(fast, result);
+    }
+
+    #[test]
+    fn test_webgpu_g2_point_add_mixed_double() {
+        let mut rng = thread_rng();
+        let a = G2Affine::random(&mut rng);
+        let a_2 = (a + a).to_affine();
+
+        // Adding 2a to itself takes the doubling branch
+        let fast = a_2 + a_2;
+        let result = run_webgpu_point_op::<G2Affine>("test_point_add_mixed", a, a_2, 0);
+        assert_eq!(fast, result);
+
+        // and adding -2a the identity branch
+        let result = run_webgpu_point_op::<G2Affine>("test_point_add_mixed", a, -a_2, 0);
+        assert_eq!(G2::identity(), result);
+    }
+
+    #[test]
+    fn test_webgpu_g2_point_add_mixed_identity() {
+        let mut rng = thread_rng();
+        let a = G2Affine::random(&mut rng);
+
+        let result = run_webgpu_point_op::<G2Affine>("test_point_add_mixed_identity", a, a, 0);
+        assert_eq!(a, result.to_affine());
+    }
 }
@@ -1,28 +1,30 @@
 use std::{iter::zip, time::Instant};
 
 use group::Group;
-use halo2curves::{CurveAffine, CurveExt};
-use wgpu::CommandEncoderDescriptor;
+use halo2curves::CurveExt;
+use wgpu::{CommandEncoderDescriptor, Limits};
 
 use msm_webgpu::cuzk::{
     gpu::{create_storage_buffer, get_adapter, get_device, read_from_gpu_test},
     msm::{
-        P, PARAMS, WORD_SIZE, convert_point_coords_and_decompose_shaders, smvp_gpu, transpose_gpu,
+        convert_point_coords_and_decompose_shaders, smvp_gpu, transpose_gpu, P, PARAMS, WORD_SIZE,
     },
     shader_manager::ShaderManager,
     utils::{bytes_to_field, debug, to_biguint_le},
 };
-use msm_webgpu::{points_to_bytes, scalars_to_bytes};
+use msm_webgpu::{points_to_bytes, scalars_to_bytes, GpuCurve, MsmConfig, MsmPlan};
 
-async fn smvp_shader<C: CurveAffine>(
+async fn smvp_shader<C: GpuCurve>(
     points: &[C],
     scalars: &[C::Scalar],
+    batch_affine: bool,
 ) -> Vec<C::Curve> {
     let input_size = scalars.len();
-    let chunk_size = if input_size >= 65536 { 16 } else { 4 };
-    let num_columns = 1 << chunk_size;
+    let plan = MsmPlan::for_input_size::<C>(input_size).unwrap();
+    let chunk_size = plan.chunk_size;
+    let num_columns = plan.num_columns();
     let num_rows = input_size.div_ceil(num_columns);
-    let num_subtasks = 256_usize.div_ceil(chunk_size);
+    let num_subtasks = plan.num_subtasks;
     let num_words = PARAMS.num_words;
     debug(&format!("Input size: {input_size}"));
     debug(&format!("Chunk size: {chunk_size}"));
@@ -36,10 +38,11 @@ async fn smvp_shader<C: CurveAffine>(
     let point_bytes = points_to_bytes(points);
     let scalar_bytes = scalars_to_bytes(scalars);
 
-    let shader_manager = ShaderManager::new(WORD_SIZE, chunk_size, input_size);
+    let mut shader_manager = ShaderManager::new(WORD_SIZE, chunk_size, input_size);
+    shader_manager.set_batch_affine(batch_affine);
 
-    let adapter = get_adapter().await;
-    let (device, queue) = get_device(&adapter).await;
+    let adapter = get_adapter().await.unwrap();
+    let (device, queue) = get_device(&adapter).await.unwrap();
     let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
         label: Some("Decompose Encoder"),
     });
@@ -66,40 +69,44 @@ async fn smvp_shader<C: CurveAffine>(
         c_workgroup_size = 256;
         c_num_x_workgroups = 32;
         c_num_y_workgroups = input_size / c_workgroup_size / c_num_x_workgroups;
-    } 
+    }
 
-    let c_shader = shader_manager.gen_decomp_scalars_shader(
-        c_workgroup_size,
-        c_num_y_workgroups,
-        num_subtasks,
-        num_columns,
-    );
+    let c_shader = shader_manager
+        .gen_decomp_scalars_shader(
+            c_workgroup_size,
+            c_num_y_workgroups,
+            num_subtasks,
+            num_columns,
+        )
+        .unwrap();
 
     // println!("C shader: {}", c_shader);
 
-    let (point_x_sb, point_y_sb, scalar_chunks_sb) = convert_point_coords_and_decompose_shaders(
-        &c_shader,
-        c_num_x_workgroups,
-        c_num_y_workgroups,
-        c_num_z_workgroups,
-        &device,
-        &queue,
-        &mut encoder,
-        &point_bytes,
-        &scalar_bytes,
-        num_subtasks,
-        chunk_size,
-        num_words,
-    )
-    .await;
+    let (point_x_sb, point_y_sb, scalar_chunks_sb) =
+        convert_point_coords_and_decompose_shaders::<C>(
+            &c_shader,
+            c_num_x_workgroups,
+            c_num_y_workgroups,
+            c_num_z_workgroups,
+            &device,
+            &queue,
+            &mut encoder,
+            &point_bytes,
+            &scalar_bytes,
+            num_subtasks,
+            chunk_size,
+            num_words,
+        )
+        .await
+        .unwrap();
 
     ////////////////////////////////////////////////////////////////////////////////////////////
     // 2. Sparse Matrix Transposition                                                         /
     //                                                                                        /
     // Compute the indices of the points which share the same                                 /
     // scalar chunks, enabling the parallel accumulation of points                            /
-    // into buckets. Transposing each subtask (CSR sparse matrix)                             /
-    // is a serial computation.                                                               /
+    // into buckets. Each subtask (CSR sparse matrix) is transposed                           /
+    // with a parallel counting sort.                                                         /
     //                                                                                        /
     // The transpose step generates the CSR sparse matrix and                                 /
     // transpoes the matrix simultaneously, resulting in a                                    /
@@ -107,27 +114,26 @@ async fn smvp_shader<C: CurveAffine>(
     // and height of the matrix (m) = 1.                                                      /
     ////////////////////////////////////////////////////////////////////////////////////////////
 
-    let t_num_x_workgroups = 1;
-    let t_num_y_workgroups = 1;
-    let t_num_z_workgroups = 1;
+    let t_workgroup_size = 256;
 
-    let t_shader = shader_manager.gen_transpose_shader(num_subtasks);
+    let t_shader = shader_manager
+        .gen_transpose_shader(t_workgroup_size)
+        .unwrap();
 
     let (all_csc_col_ptr_sb, all_csc_val_idxs_sb) = transpose_gpu(
         &t_shader,
         &device,
         &queue,
         &mut encoder,
-        t_num_x_workgroups,
-        t_num_y_workgroups,
-        t_num_z_workgroups,
+        t_workgroup_size,
         input_size,
         num_columns,
         num_rows,
         num_subtasks,
         scalar_chunks_sb,
     )
-    .await;
+    .await
+    .unwrap();
 
     ////////////////////////////////////////////////////////////////////////////////////////////
     // 3. Sparse Matrix Vector Product (SMVP)                                                 /
@@ -137,35 +143,27 @@ async fn smvp_shader<C: CurveAffine>(
     // minimizing shader invocations.                                                         /
     ////////////////////////////////////////////////////////////////////////////////////////////
 
-    let half_num_columns = num_columns / 2;
-    let mut s_workgroup_size = 256;
-    let mut s_num_x_workgroups = 64;
-    let mut s_num_y_workgroups = half_num_columns / s_workgroup_size / s_num_x_workgroups;
-    let mut s_num_z_workgroups = num_subtasks;
-
-    if half_num_columns < 32768 {
-        s_workgroup_size = 32;
-        s_num_x_workgroups = 1;
-        s_num_y_workgroups = half_num_columns.div_ceil(s_workgroup_size * s_num_x_workgroups);
-    }
-
-    if num_columns < 256 {
-        s_workgroup_size = 1;
-        s_num_x_workgroups = half_num_columns;
-        s_num_y_workgroups = 1;
-        s_num_z_workgroups = 1;
-    }
+    // The workgroups MsmContext picks for this plan. Every thread handles one
+    // positive and one negative bucket.
+    let config = MsmConfig {
+        batch_affine,
+        ..Default::default()
+    };
+    let workgroups = config.workgroups(&plan, &Limits::default()).unwrap();
+    let s_workgroup_size = workgroups.smvp_workgroup_size;
+    let s_num_x_workgroups = workgroups.smvp_num_x_workgroups;
+    let s_num_y_workgroups = workgroups.smvp_num_y_workgroups;
+
+    // The number of CSR matrices processed per invocation of the shader.
+    let num_subtask_chunk_size = workgroups.smvp_subtasks_per_dispatch;
+    let s_num_z_workgroups = num_subtask_chunk_size;
+    let entry_point = if batch_affine { "batch_affine" } else { "main" };
 
-    debug(&format!("Half num columns: {half_num_columns:?}"));
     debug(&format!("S workgroup size: {s_workgroup_size:?}"));
     debug(&format!("S num x workgroups: {s_num_x_workgroups:?}"));
     debug(&format!("S num y workgroups: {s_num_y_workgroups:?}"));
     debug(&format!("S num z workgroups: {s_num_z_workgroups:?}"));
 
-    // This is a dynamic variable that determines the number of CSR
-    // matrices processed per invocation of the shader. A safe default is 1.
-    let num_subtask_chunk_size = 4;
-
     // Buffers that store the SMVP result, ie. bucket sums. They are
     // overwritten per iteration.
     let bucket_sum_coord_bytelength = (num_columns / 2) * num_words * 4 * num_subtasks;
@@ -187,21 +185,18 @@ async fn smvp_shader<C: CurveAffine>(
         &device,
         bucket_sum_coord_bytelength as u64,
     );
-    let smvp_shader = shader_manager.gen_smvp_shader(s_workgroup_size, num_columns);
+    let smvp_shader = shader_manager
+        .gen_smvp_shader(s_workgroup_size, num_columns)
+        .unwrap();
 
     debug(&format!("SMVP shader: {smvp_shader}"));
-    debug(&format!(
-        "s_num_x_workgroups / (num_subtasks / num_subtask_chunk_size): {:?}",
-        s_num_x_workgroups / (num_subtasks / num_subtask_chunk_size)
-    ));
-    debug(&format!("s_num_y_workgroups: {s_num_y_workgroups:?}"));
-    debug(&format!("s_num_z_workgroups: {s_num_z_workgroups:?}"));
 
     for offset in (0..num_subtasks).step_by(num_subtask_chunk_size) {
         debug(&format!("Offset: {offset:?}"));
         smvp_gpu(
             &smvp_shader,
-            s_num_x_workgroups / (num_subtasks / num_subtask_chunk_size),
+            entry_point,
+            s_num_x_workgroups,
             s_num_y_workgroups,
             s_num_z_workgroups,
             offset,
@@ -217,7 +212,8 @@ async fn smvp_shader<C: CurveAffine>(
             &bucket_sum_y_sb,
             &bucket_sum_z_sb,
         )
-        .await;
+        .await
+        .unwrap();
     }
     // Map results back from GPU to CPU.
     let data = read_from_gpu_test(
@@ -235,7 +231,6 @@ async fn smvp_shader<C: CurveAffine>(
     let p_y = bytemuck::cast_slice::<u8, u32>(&data[1]).chunks(20);
     let p_z = bytemuck::cast_slice::<u8, u32>(&data[2]).chunks(20);
 
-    
     zip(zip(p_x, p_y), p_z)
         .enumerate()
         .map(|(i, ((x, y), z))| {
@@ -263,20 +258,22 @@ async fn smvp_shader<C: CurveAffine>(
 }
 
 /// Run WebGPU SMVP shader sync
-pub fn run_webgpu_smvp_shader<C: CurveAffine>(
+pub fn run_webgpu_smvp_shader<C: GpuCurve>(
     points: &[C],
     scalars: &[C::Scalar],
+    batch_affine: bool,
 ) -> Vec<C::Curve> {
-    pollster::block_on(run_webgpu_smvp_shader_async(points, scalars))
+    pollster::block_on(run_webgpu_smvp_shader_async(points, scalars, batch_affine))
 }
 
 /// Run WebGPU SMVP shader async
-pub async fn run_webgpu_smvp_shader_async<C: CurveAffine>(
+pub async fn run_webgpu_smvp_shader_async<C: GpuCurve>(
     points: &[C],
     scalars: &[C::Scalar],
+    batch_affine: bool,
 ) -> Vec<C::Curve> {
     let now = Instant::now();
-    let result = smvp_shader::<C>(points, scalars).await;
+    let result = smvp_shader::<C>(points, scalars, batch_affine).await;
     println!("SMVP time: {:?}", now.elapsed());
     result
 }
@@ -295,11 +292,11 @@ mod tests {
         let scalars = sample_scalars::<Fr>(input_size);
         let points = sample_points::<G1Affine>(input_size);
 
-        let chunk_size = if input_size >= 65536 { 16 } else { 4 };
-        let num_columns = 1 << chunk_size;
-        let num_rows = (input_size + num_columns - 1) / num_columns;
-        let num_chunks_per_scalar = (256 + chunk_size - 1) / chunk_size;
-        let num_subtasks = num_chunks_per_scalar;
+        let plan = MsmPlan::for_input_size::<G1Affine>(input_size).unwrap();
+        let chunk_size = plan.chunk_size;
+        let num_columns = plan.num_columns();
+        let num_rows = input_size.div_ceil(num_columns);
+        let num_subtasks = plan.num_subtasks;
         let decomposed_scalars = decompose_scalars_signed(&scalars, num_subtasks, chunk_size);
 
         // Perform multiple transpositions "in parallel"}
@@ -311,7 +308,7 @@ mod tests {
             input_size,
         );
 
-        let result_bucket_sums = run_webgpu_smvp_shader::<G1Affine>(&points, &scalars);
+        let result_bucket_sums = run_webgpu_smvp_shader::<G1Affine>(&points, &scalars, false);
         println!("Result bucket sums length: {:?}", result_bucket_sums.len());
 
         let mut bucket_sums = vec![];
@@ -332,4 +329,66 @@ mod tests {
         }
         assert_eq!(result_bucket_sums, bucket_sums);
     }
+
+    /// Compare the buckets of a small SMVP against `cpu_smvp_signed`.
+    fn check_small_smvp(batch_affine: bool) {
+        let input_size = 1 << 10;
+        let quarter = input_size / 4;
+
+        // The second half repeats the scalars of the first, with the same
+        // points in the third quarter and their negations in the fourth, so
+        // that rows double and cancel points as well as add them.
+        let mut scalars = sample_scalars::<Fr>(input_size / 2);
+        scalars.extend_from_within(..);
+        let mut points = sample_points::<G1Affine>(input_size / 2);
+        points.extend_from_within(..quarter);
+        points.extend(
+            points[quarter..2 * quarter]
+                .iter()
+                .map(|p| -*p)
+                .collect::<Vec<_>>(),
+        );
+
+        let plan = MsmPlan::for_input_size::<G1Affine>(input_size).unwrap();
+        let chunk_size = plan.chunk_size;
+        let num_columns = plan.num_columns();
+        let num_rows = input_size.div_ceil(num_columns);
+        let num_subtasks = plan.num_subtasks;
+        let decomposed_scalars = decompose_scalars_signed(&scalars, num_subtasks, chunk_size);
+        let (all_csc_col_ptr_cpu, _, all_csc_val_idxs_cpu) = cpu_transpose(
+            decomposed_scalars.concat(),
+            num_columns,
+            num_rows,
+            num_subtasks,
+            input_size,
+        );
+
+        let result_bucket_sums =
+            run_webgpu_smvp_shader::<G1Affine>(&points, &scalars, batch_affine);
+
+        let bucket_sums = (0..num_subtasks)
+            .flat_map(|subtask_idx| {
+                cpu_smvp_signed(
+                    subtask_idx,
+                    input_size,
+                    num_columns,
+                    chunk_size,
+                    &all_csc_col_ptr_cpu,
+                    &all_csc_val_idxs_cpu,
+                    &points,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(result_bucket_sums, bucket_sums);
+    }
+
+    #[test]
+    fn test_webgpu_smvp_shader_small() {
+        check_small_smvp(false);
+    }
+
+    #[test]
+    fn test_webgpu_smvp_shader_batch_affine() {
+        check_small_smvp(true);
+    }
 }
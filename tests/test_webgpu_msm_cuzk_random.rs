@@ -1,4 +1,4 @@
-#[cfg(test)]
+#[cfg(all(test, target_arch = "wasm32"))]
 mod tests_wasm_pack_16 {
     use msm_webgpu::tests_wasm_pack::test_webgpu_msm_cuzk;
     use rand::Rng;
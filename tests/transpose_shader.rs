@@ -1,25 +1,25 @@
 use std::time::Instant;
 
-use halo2curves::CurveAffine;
 use wgpu::CommandEncoderDescriptor;
 
 use msm_webgpu::cuzk::{
-    gpu::{get_adapter, get_device, read_from_gpu_test},
-    msm::{PARAMS, WORD_SIZE, convert_point_coords_and_decompose_shaders, transpose_gpu},
+    gpu::{create_and_write_storage_buffer, get_adapter, get_device, read_from_gpu_test},
+    msm::{convert_point_coords_and_decompose_shaders, transpose_gpu, PARAMS, WORD_SIZE},
     shader_manager::ShaderManager,
     utils::debug,
 };
-use msm_webgpu::{points_to_bytes, scalars_to_bytes};
+use msm_webgpu::{points_to_bytes, scalars_to_bytes, GpuCurve, MsmPlan};
 
-async fn transpose_shader<C: CurveAffine>(
+async fn transpose_shader<C: GpuCurve>(
     points: &[C],
     scalars: &[C::Scalar],
 ) -> (Vec<i32>, Vec<i32>) {
     let input_size = scalars.len();
-    let chunk_size = if input_size >= 65536 { 16 } else { 4 };
-    let num_columns = 1 << chunk_size;
+    let plan = MsmPlan::for_input_size::<C>(input_size).unwrap();
+    let chunk_size = plan.chunk_size;
+    let num_columns = plan.num_columns();
     let num_rows = input_size.div_ceil(num_columns);
-    let num_subtasks = 256_usize.div_ceil(chunk_size);
+    let num_subtasks = plan.num_subtasks;
     let num_words = PARAMS.num_words;
     debug(&format!("Input size: {input_size}"));
     debug(&format!("Chunk size: {chunk_size}"));
@@ -35,8 +35,8 @@ async fn transpose_shader<C: CurveAffine>(
 
     let shader_manager = ShaderManager::new(WORD_SIZE, chunk_size, input_size);
 
-    let adapter = get_adapter().await;
-    let (device, queue) = get_device(&adapter).await;
+    let adapter = get_adapter().await.unwrap();
+    let (device, queue) = get_device(&adapter).await.unwrap();
     let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
         label: Some("Decompose Encoder"),
     });
@@ -63,38 +63,42 @@ async fn transpose_shader<C: CurveAffine>(
         c_workgroup_size = 256;
         c_num_x_workgroups = 32;
         c_num_y_workgroups = input_size / c_workgroup_size / c_num_x_workgroups;
-    } 
+    }
 
-    let c_shader = shader_manager.gen_decomp_scalars_shader(
-        c_workgroup_size,
-        c_num_y_workgroups,
-        num_subtasks,
-        num_columns,
-    );
+    let c_shader = shader_manager
+        .gen_decomp_scalars_shader(
+            c_workgroup_size,
+            c_num_y_workgroups,
+            num_subtasks,
+            num_columns,
+        )
+        .unwrap();
 
-    let (_point_x_sb, _point_y_sb, scalar_chunks_sb) = convert_point_coords_and_decompose_shaders(
-        &c_shader,
-        c_num_x_workgroups,
-        c_num_y_workgroups,
-        c_num_z_workgroups,
-        &device,
-        &queue,
-        &mut encoder,
-        &point_bytes,
-        &scalar_bytes,
-        num_subtasks,
-        chunk_size,
-        num_words,
-    )
-    .await;
+    let (_point_x_sb, _point_y_sb, scalar_chunks_sb) =
+        convert_point_coords_and_decompose_shaders::<C>(
+            &c_shader,
+            c_num_x_workgroups,
+            c_num_y_workgroups,
+            c_num_z_workgroups,
+            &device,
+            &queue,
+            &mut encoder,
+            &point_bytes,
+            &scalar_bytes,
+            num_subtasks,
+            chunk_size,
+            num_words,
+        )
+        .await
+        .unwrap();
 
     ////////////////////////////////////////////////////////////////////////////////////////////
     // 2. Sparse Matrix Transposition                                                         /
     //                                                                                        /
     // Compute the indices of the points which share the same                                 /
     // scalar chunks, enabling the parallel accumulation of points                            /
-    // into buckets. Transposing each subtask (CSR sparse matrix)                             /
-    // is a serial computation.                                                               /
+    // into buckets. Each subtask (CSR sparse matrix) is transposed                           /
+    // with a parallel counting sort.                                                         /
     //                                                                                        /
     // The transpose step generates the CSR sparse matrix and                                 /
     // transpoes the matrix simultaneously, resulting in a                                    /
@@ -102,27 +106,26 @@ async fn transpose_shader<C: CurveAffine>(
     // and height of the matrix (m) = 1.                                                      /
     ////////////////////////////////////////////////////////////////////////////////////////////
 
-    let t_num_x_workgroups = 1;
-    let t_num_y_workgroups = 1;
-    let t_num_z_workgroups = 1;
+    let t_workgroup_size = 256;
 
-    let t_shader = shader_manager.gen_transpose_shader(num_subtasks);
+    let t_shader = shader_manager
+        .gen_transpose_shader(t_workgroup_size)
+        .unwrap();
 
     let (all_csc_col_ptr_sb, all_csc_val_idxs_sb) = transpose_gpu(
         &t_shader,
         &device,
         &queue,
         &mut encoder,
-        t_num_x_workgroups,
-        t_num_y_workgroups,
-        t_num_z_workgroups,
+        t_workgroup_size,
         input_size,
         num_columns,
         num_rows,
         num_subtasks,
         scalar_chunks_sb,
     )
-    .await;
+    .await
+    .unwrap();
 
     // Map results back from GPU to CPU.
     let data = read_from_gpu_test(
@@ -142,8 +145,60 @@ async fn transpose_shader<C: CurveAffine>(
     (all_csc_col_ptr.to_vec(), all_csc_val_idxs.to_vec())
 }
 
+/// Run the transpose shader alone over scalar chunks given as column indices,
+/// one row of `input_size` per subtask.
+async fn transpose_chunks(
+    chunks: &[u32],
+    input_size: usize,
+    chunk_size: usize,
+    workgroup_size: usize,
+) -> (Vec<i32>, Vec<i32>) {
+    let num_columns = 1 << chunk_size;
+    let num_subtasks = chunks.len() / input_size;
+    let shader_manager = ShaderManager::new(WORD_SIZE, chunk_size, input_size);
+    let t_shader = shader_manager.gen_transpose_shader(workgroup_size).unwrap();
+
+    let adapter = get_adapter().await.unwrap();
+    let (device, queue) = get_device(&adapter).await.unwrap();
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("Transpose Encoder"),
+    });
+    let scalar_chunks_sb = create_and_write_storage_buffer(
+        Some("Scalar chunks buffer"),
+        &device,
+        bytemuck::cast_slice(chunks),
+    );
+    let (all_csc_col_ptr_sb, all_csc_val_idxs_sb) = transpose_gpu(
+        &t_shader,
+        &device,
+        &queue,
+        &mut encoder,
+        workgroup_size,
+        input_size,
+        num_columns,
+        input_size.div_ceil(num_columns),
+        num_subtasks,
+        scalar_chunks_sb,
+    )
+    .await
+    .unwrap();
+    let data = read_from_gpu_test(
+        &device,
+        &queue,
+        encoder,
+        vec![all_csc_col_ptr_sb, all_csc_val_idxs_sb],
+    )
+    .await;
+    device.destroy();
+
+    (
+        bytemuck::cast_slice::<u8, i32>(&data[0]).to_vec(),
+        bytemuck::cast_slice::<u8, i32>(&data[1]).to_vec(),
+    )
+}
+
 /// Run WebGPU transpose shader sync
-pub fn run_webgpu_transpose_shader<C: CurveAffine>(
+pub fn run_webgpu_transpose_shader<C: GpuCurve>(
     points: &[C],
     scalars: &[C::Scalar],
 ) -> (Vec<i32>, Vec<i32>) {
@@ -151,7 +206,7 @@ pub fn run_webgpu_transpose_shader<C: CurveAffine>(
 }
 
 /// Run WebGPU transpose shader async
-pub async fn run_webgpu_transpose_shader_async<C: CurveAffine>(
+pub async fn run_webgpu_transpose_shader_async<C: GpuCurve>(
     points: &[C],
     scalars: &[C::Scalar],
 ) -> (Vec<i32>, Vec<i32>) {
@@ -163,11 +218,14 @@ pub async fn run_webgpu_transpose_shader_async<C: CurveAffine>(
 
 #[cfg(test)]
 mod tests {
-    use msm_webgpu::cuzk::test::utils::{cpu_transpose, decompose_scalars_signed};
+    use msm_webgpu::cuzk::test::utils::{
+        cpu_transpose, decompose_scalars_signed, sort_csc_columns,
+    };
     use msm_webgpu::{sample_points, sample_scalars};
 
     use super::*;
     use halo2curves::bn256::{Fr, G1Affine};
+    use rand::Rng;
 
     #[test]
     fn test_webgpu_transpose_shader() {
@@ -175,13 +233,13 @@ mod tests {
         let scalars = sample_scalars::<Fr>(input_size);
         let points = sample_points::<G1Affine>(input_size);
 
-        let chunk_size = if input_size >= 65536 { 16 } else { 4 };
-        let num_columns = 1 << chunk_size;
-        let num_rows = (input_size + num_columns - 1) / num_columns;
-        let num_chunks_per_scalar = (256 + chunk_size - 1) / chunk_size;
-        let num_subtasks = num_chunks_per_scalar;
+        let plan = MsmPlan::for_input_size::<G1Affine>(input_size).unwrap();
+        let chunk_size = plan.chunk_size;
+        let num_columns = plan.num_columns();
+        let num_rows = input_size.div_ceil(num_columns);
+        let num_subtasks = plan.num_subtasks;
 
-        let (all_csc_col_ptr, all_csc_val_idxs) =
+        let (all_csc_col_ptr, mut all_csc_val_idxs) =
             run_webgpu_transpose_shader::<G1Affine>(&points, &scalars);
 
         let decomposed_scalars = decompose_scalars_signed(&scalars, num_subtasks, chunk_size);
@@ -196,6 +254,62 @@ mod tests {
         );
 
         assert_eq!(all_csc_col_ptr, all_csc_col_ptr_cpu);
+        sort_csc_columns(
+            &all_csc_col_ptr,
+            &mut all_csc_val_idxs,
+            num_columns,
+            num_subtasks,
+            input_size,
+        );
         assert_eq!(all_csc_val_idxs, all_csc_val_idxs_cpu);
     }
+
+    #[test]
+    fn test_webgpu_transpose_shader_shapes() {
+        let mut rng = rand::thread_rng();
+        // (input size, chunk size, subtasks, workgroup size), with workgroup
+        // sizes that do not divide the number of columns.
+        for (input_size, chunk_size, num_subtasks, workgroup_size) in [
+            (1 << 10, 8, 4, 256),
+            (1 << 12, 12, 3, 64),
+            (1 << 9, 4, 2, 7),
+            (1 << 8, 8, 5, 1),
+        ] {
+            let num_columns = 1 << chunk_size;
+            // Every third chunk lands in the zero bucket, as padding does.
+            let chunks = (0..input_size * num_subtasks)
+                .map(|i| {
+                    if i % 3 == 0 {
+                        num_columns / 2
+                    } else {
+                        rng.gen_range(0..num_columns)
+                    }
+                })
+                .collect::<Vec<u32>>();
+
+            let (all_csc_col_ptr, mut all_csc_val_idxs) = pollster::block_on(transpose_chunks(
+                &chunks,
+                input_size,
+                chunk_size,
+                workgroup_size,
+            ));
+            let (all_csc_col_ptr_cpu, _, all_csc_val_idxs_cpu) = cpu_transpose(
+                chunks.iter().map(|&chunk| chunk as i32).collect(),
+                num_columns as usize,
+                input_size.div_ceil(num_columns as usize),
+                num_subtasks,
+                input_size,
+            );
+
+            assert_eq!(all_csc_col_ptr, all_csc_col_ptr_cpu);
+            sort_csc_columns(
+                &all_csc_col_ptr,
+                &mut all_csc_val_idxs,
+                num_columns as usize,
+                num_subtasks,
+                input_size,
+            );
+            assert_eq!(all_csc_val_idxs, all_csc_val_idxs_cpu);
+        }
+    }
 }
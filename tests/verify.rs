@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+    use halo2curves::bn256::{Fr, G1Affine, G1};
+    use msm_webgpu::cuzk::verify::{check_window_sum, cpu_glv_window_sum, cpu_window_sum};
+    use msm_webgpu::{sample_points, sample_scalars, MsmError, MsmPlan};
+
+    #[test]
+    fn test_check_window_sum() {
+        let input_size = 1 << 8;
+        let points = sample_points::<G1Affine>(input_size);
+        let scalars = sample_scalars::<Fr>(input_size);
+        let plan = MsmPlan::new::<G1Affine>(input_size, 13);
+        let window_sums = (0..plan.num_subtasks)
+            .map(|window| {
+                cpu_window_sum(
+                    &points,
+                    &scalars,
+                    window,
+                    plan.num_subtasks,
+                    plan.chunk_size,
+                )
+            })
+            .collect::<Vec<_>>();
+        for window in 0..plan.num_subtasks {
+            check_window_sum(&plan, &points, &scalars, &window_sums, window).unwrap();
+        }
+
+        let mut corrupted = window_sums.clone();
+        corrupted[5] += G1::generator();
+        let result = check_window_sum(&plan, &points, &scalars, &corrupted, 5);
+        assert!(matches!(
+            result,
+            Err(MsmError::VerificationFailed { window: 5 })
+        ));
+        // Only the recomputed window is compared.
+        check_window_sum(&plan, &points, &scalars, &corrupted, 4).unwrap();
+    }
+
+    #[test]
+    fn test_check_glv_window_sum() {
+        let input_size = 1 << 8;
+        let points = sample_points::<G1Affine>(input_size);
+        let scalars = sample_scalars::<Fr>(input_size);
+        let plan = MsmPlan::new_glv::<G1Affine>(input_size, 13).unwrap();
+        let mut window_sums = (0..plan.num_subtasks)
+            .map(|window| {
+                cpu_glv_window_sum(
+                    &points,
+                    &scalars,
+                    window,
+                    plan.num_subtasks,
+                    plan.chunk_size,
+                )
+            })
+            .collect::<Vec<_>>();
+        check_window_sum(&plan, &points, &scalars, &window_sums, 0).unwrap();
+
+        window_sums[0] = -window_sums[0];
+        let result = check_window_sum(&plan, &points, &scalars, &window_sums, 0);
+        assert!(matches!(
+            result,
+            Err(MsmError::VerificationFailed { window: 0 })
+        ));
+    }
+}